@@ -0,0 +1,88 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aws_sdk_s3::Client as S3Client;
+
+use crate::upload::TEMP_BUCKET;
+
+/// Default age after which an unfinished multipart upload is treated as
+/// abandoned (`STALE_UPLOAD_AGE_SECS`).
+const DEFAULT_STALE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often the cleanup runs after the one at boot.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn stale_age_from_env() -> Result<Duration, String> {
+    match std::env::var("STALE_UPLOAD_AGE_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid STALE_UPLOAD_AGE_SECS: {}", v)),
+        Err(_) => Ok(DEFAULT_STALE_AGE),
+    }
+}
+
+/// Aborts multipart uploads in the temp bucket that were started more than
+/// `max_age` ago. An ingest interrupted by a restart never completes or
+/// aborts its upload, and S3 keeps billing for the stored parts until
+/// someone does. Returns how many uploads were aborted.
+pub async fn cleanup_stale_uploads(
+    s3: &S3Client,
+    max_age: Duration,
+) -> Result<usize, aws_sdk_s3::Error> {
+    let cutoff = SystemTime::now()
+        .checked_sub(max_age)
+        .unwrap_or(UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+
+    let mut aborted = 0;
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+    loop {
+        let page = s3
+            .list_multipart_uploads()
+            .bucket(TEMP_BUCKET)
+            .set_key_marker(key_marker.take())
+            .set_upload_id_marker(upload_id_marker.take())
+            .send()
+            .await?;
+
+        for upload in page.uploads() {
+            let (Some(key), Some(upload_id)) = (upload.key(), upload.upload_id()) else {
+                continue;
+            };
+            if upload.initiated().is_none_or(|at| at.secs() > cutoff) {
+                continue;
+            }
+            println!("Aborting stale multipart upload {} ({})", key, upload_id);
+            s3.abort_multipart_upload()
+                .bucket(TEMP_BUCKET)
+                .key(key)
+                .upload_id(upload_id)
+                .send()
+                .await?;
+            aborted += 1;
+        }
+
+        if !page.is_truncated().unwrap_or(false) {
+            return Ok(aborted);
+        }
+        key_marker = page.next_key_marker().map(str::to_string);
+        upload_id_marker = page.next_upload_id_marker().map(str::to_string);
+    }
+}
+
+/// Runs [`cleanup_stale_uploads`] now and then every hour, forever.
+pub async fn run_cleanup_loop(s3: S3Client, max_age: Duration) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match cleanup_stale_uploads(&s3, max_age).await {
+            Ok(0) => {}
+            Ok(count) => println!("Aborted {} stale multipart upload(s)", count),
+            Err(e) => println!("Stale upload cleanup failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::{Error, HttpResponse, error, post, web};
+use aws_sdk_s3::Client as S3Client;
+use futures_util::StreamExt;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+
+use crate::upload::{Encryption, MAX_UPLOAD_BYTES, is_allowed_content_type, store_stream};
+
+#[derive(Deserialize)]
+struct IngestRequest {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct IngestResponse {
+    key: String,
+}
+
+#[post("/ingest")]
+async fn ingest_video(
+    req: web::Json<IngestRequest>,
+    s3: web::Data<S3Client>,
+    http: web::Data<reqwest::Client>,
+    encryption: web::Data<Encryption>,
+) -> Result<HttpResponse, Error> {
+    let url = Url::parse(&req.url).map_err(|_| error::ErrorBadRequest("Invalid URL"))?;
+    validate_remote_url(&url).await?;
+
+    let resp = http
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            println!("Ingest fetch error: {:?}", e);
+            error::ErrorBadGateway("Failed to fetch remote video")
+        })?;
+
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !is_allowed_content_type(&content_type) {
+        return Err(error::ErrorUnsupportedMediaType("Unsupported content type"));
+    }
+    if resp
+        .content_length()
+        .is_some_and(|len| len > MAX_UPLOAD_BYTES)
+    {
+        return Err(error::ErrorPayloadTooLarge("Video exceeds size limit"));
+    }
+
+    let body = resp.bytes_stream().map(|chunk| {
+        chunk.map_err(|e| {
+            println!("Ingest stream error: {:?}", e);
+            error::ErrorBadGateway("Failed to fetch remote video")
+        })
+    });
+    let file_name = store_stream(&s3, &encryption, &content_type, None, body).await?;
+
+    Ok(HttpResponse::Ok().json(IngestResponse { key: file_name }))
+}
+
+/// Rejects anything that isn't plain HTTP(S) to a public address, so the
+/// endpoint can't be used to reach internal services.
+async fn validate_remote_url(url: &Url) -> Result<(), Error> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(error::ErrorBadRequest(
+            "Only http and https URLs are allowed",
+        ));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| error::ErrorBadRequest("URL has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|_| error::ErrorBadRequest("Could not resolve host"))?;
+    for addr in addrs {
+        if !is_public_ip(addr.ip()) {
+            return Err(error::ErrorBadRequest("URL resolves to a private address"));
+        }
+    }
+    Ok(())
+}
+
+/// DNS for the ingest client. The check in [`validate_remote_url`] happens
+/// before the fetch resolves the host again, so a host could answer it with
+/// a public address and the connection with an internal one. Filtering
+/// here, at connect time, closes that gap.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            // reqwest fills in the port itself.
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("{} resolves to a private address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                // 100.64.0.0/10 carrier-grade NAT
+                || (v4.octets()[0] == 100 && (v4.octets()[1] & 0xc0) == 64))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 unique local, fe80::/10 link local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
@@ -1,19 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use actix_web::{App, HttpServer, web};
+use aws_config::timeout::TimeoutConfig;
 use aws_sdk_s3::client;
 
-use crate::upload::upload_video;
+use crate::ingest::ingest_video;
+use crate::status::transcode_status;
+use crate::upload::{Encryption, upload_video};
 
+mod cleanup;
+mod ingest;
+mod status;
 mod upload;
 
+/// Ceiling on any one S3 call (`AWS_OP_TIMEOUT_SECS`). An upload is stored
+/// with a single `put_object`, so the default allows for a large file.
+const DEFAULT_AWS_OP_TIMEOUT: Duration = Duration::from_secs(300);
+const AWS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeouts for the S3 client, so a request doesn't hang on a network
+/// black hole until the caller gives up.
+fn aws_timeouts() -> Result<TimeoutConfig, String> {
+    let operation = match std::env::var("AWS_OP_TIMEOUT_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid AWS_OP_TIMEOUT_SECS: {}", v))?,
+        Err(_) => DEFAULT_AWS_OP_TIMEOUT,
+    };
+    Ok(TimeoutConfig::builder()
+        .connect_timeout(AWS_CONNECT_TIMEOUT)
+        .operation_timeout(operation)
+        .build())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
-    let config = aws_config::load_from_env().await;
+    let config = aws_config::from_env()
+        .timeout_config(aws_timeouts().map_err(std::io::Error::other)?)
+        .load()
+        .await;
     let s3_client = client::Client::new(&config);
+    let encryption = Encryption::from_env().map_err(std::io::Error::other)?;
+    let stale_upload_age = cleanup::stale_age_from_env().map_err(std::io::Error::other)?;
+    tokio::spawn(cleanup::run_cleanup_loop(
+        s3_client.clone(),
+        stale_upload_age,
+    ));
+    // Redirects are not followed so a public URL can't bounce the fetch to an
+    // internal address after validation, and every lookup the client makes
+    // is checked again when it connects.
+    let http_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(ingest::PublicResolver))
+        .build()
+        .map_err(std::io::Error::other)?;
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(s3_client.clone()))
+            .app_data(web::Data::new(http_client.clone()))
+            .app_data(web::Data::new(encryption.clone()))
             .service(upload_video)
+            .service(ingest_video)
+            .service(transcode_status)
     })
     .bind("127.0.0.1:8080")?
     .run()
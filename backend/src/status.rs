@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use actix_web::{Error, HttpResponse, error, get, web};
+use aws_sdk_s3::Client as S3Client;
+use serde::Serialize;
+
+pub const PERM_BUCKET: &str = "perm-video-storage-0306";
+
+/// Renditions the transcoder produces by default, in `<stem>/<name>.mp4`.
+const EXPECTED_RENDITIONS: [&str; 3] = ["480p", "720p", "1080p"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobState {
+    Pending,
+    Partial,
+    Complete,
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    key: String,
+    status: JobState,
+    /// Every object under the output prefix, relative to it.
+    outputs: Vec<String>,
+    /// Expected renditions not uploaded yet.
+    missing: Vec<String>,
+}
+
+/// Reports how far the transcode of an uploaded key has got, judged by
+/// which outputs exist under `<stem>/` in the permanent bucket.
+#[get("/status/{key:.*}")]
+async fn transcode_status(
+    key: web::Path<String>,
+    s3: web::Data<S3Client>,
+) -> Result<HttpResponse, Error> {
+    let key = key.into_inner();
+    let stem = Path::new(&key)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| error::ErrorBadRequest("Invalid key"))?;
+    let prefix = format!("{}/", stem);
+
+    let mut outputs = Vec::new();
+    let mut pages = s3
+        .list_objects_v2()
+        .bucket(PERM_BUCKET)
+        .prefix(&prefix)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        let page = page.map_err(|e| {
+            println!("S3 list error: {:?}", e);
+            error::ErrorInternalServerError("Status lookup failed")
+        })?;
+        outputs.extend(
+            page.contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .filter_map(|k| k.strip_prefix(&prefix))
+                .map(str::to_string),
+        );
+    }
+    outputs.sort();
+
+    let missing: Vec<String> = EXPECTED_RENDITIONS
+        .iter()
+        .map(|name| format!("{}.mp4", name))
+        .filter(|file| !outputs.contains(file))
+        .collect();
+    let status = if missing.is_empty() {
+        JobState::Complete
+    } else if missing.len() < EXPECTED_RENDITIONS.len() {
+        JobState::Partial
+    } else {
+        JobState::Pending
+    };
+
+    Ok(HttpResponse::Ok().json(StatusResponse {
+        key,
+        status,
+        outputs,
+        missing,
+    }))
+}
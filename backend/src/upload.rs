@@ -1,41 +1,345 @@
 use actix_multipart::Multipart;
-use actix_web::{Error, HttpResponse, post, web};
+use actix_web::{Error, HttpResponse, error, post, web};
 use aws_sdk_s3::Client as S3Client;
-use bytes::BytesMut;
-use futures_util::StreamExt;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, ServerSideEncryption};
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
 
+pub const TEMP_BUCKET: &str = "temp-video-storage-0306";
+
+/// S3 requires every part except the last to be at least 5 MiB.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Largest video accepted by either the upload or ingest endpoints.
+pub const MAX_UPLOAD_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Longest `title` accepted with an upload.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Server-side encryption for uploaded objects, from `SSE_MODE` (`none`,
+/// `aes256` or `kms`) and `SSE_KMS_KEY_ID`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Encryption {
+    None,
+    Aes256,
+    Kms { key_id: String },
+}
+
+impl Encryption {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(
+            std::env::var("SSE_MODE").ok().as_deref(),
+            std::env::var("SSE_KMS_KEY_ID").ok().as_deref(),
+        )
+    }
+
+    fn parse(mode: Option<&str>, key_id: Option<&str>) -> Result<Self, String> {
+        let key_id = key_id.filter(|v| !v.is_empty()).map(str::to_string);
+        let mode = mode.unwrap_or("none").to_ascii_lowercase();
+        match mode.as_str() {
+            "none" | "aes256" if key_id.is_some() => {
+                Err("SSE_KMS_KEY_ID is only used with SSE_MODE=kms".to_string())
+            }
+            "none" => Ok(Self::None),
+            "aes256" => Ok(Self::Aes256),
+            "kms" => key_id
+                .map(|key_id| Self::Kms { key_id })
+                .ok_or_else(|| "SSE_MODE=kms requires SSE_KMS_KEY_ID".to_string()),
+            _ => Err(format!("Unknown SSE_MODE: {}", mode)),
+        }
+    }
+
+    /// The `server_side_encryption` and `ssekms_key_id` request parameters.
+    pub fn params(&self) -> (Option<ServerSideEncryption>, Option<String>) {
+        match self {
+            Encryption::None => (None, None),
+            Encryption::Aes256 => (Some(ServerSideEncryption::Aes256), None),
+            Encryption::Kms { key_id } => {
+                (Some(ServerSideEncryption::AwsKms), Some(key_id.clone()))
+            }
+        }
+    }
+}
+
+pub fn is_allowed_content_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence.starts_with("video/") || essence == "application/octet-stream"
+}
+
+/// A trimmed title, or `None` if it is blank. S3 carries metadata as HTTP
+/// headers, so only printable ASCII is accepted.
+fn parse_title(raw: &[u8]) -> Result<Option<String>, Error> {
+    let title = std::str::from_utf8(raw)
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid title"))?
+        .trim();
+    if title.is_empty() {
+        return Ok(None);
+    }
+    if title.chars().count() > MAX_TITLE_CHARS
+        || !title.chars().all(|c| c.is_ascii() && !c.is_ascii_control())
+    {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Title must be printable ASCII of at most {} characters",
+            MAX_TITLE_CHARS
+        )));
+    }
+    Ok(Some(title.to_string()))
+}
+
+/// Streams a multipart form's `video` field straight into S3, one part in
+/// memory at a time. The object's metadata is fixed when the upload starts,
+/// so a `title` field has to come before `video`.
 #[post("/upload")]
 async fn upload_video(
     mut payload: Multipart,
     s3: web::Data<S3Client>,
+    encryption: web::Data<Encryption>,
 ) -> Result<HttpResponse, Error> {
-    // For very large videos, stream directly → S3 multipart upload.
-    let mut video_bytes = BytesMut::new();
+    let mut file_name = None;
+    let mut title = None;
 
     while let Some(item) = payload.next().await {
         let mut field = item?;
         let content_disposition = field.content_disposition().unwrap();
 
         if content_disposition.get_name() == Some("video") {
+            if file_name.is_some() {
+                return Err(error::ErrorBadRequest("Only one video per upload"));
+            }
+            let content_type = field
+                .content_type()
+                .map(|m| m.essence_str().to_string())
+                .unwrap_or_default();
+            if !is_allowed_content_type(&content_type) {
+                return Err(actix_web::error::ErrorUnsupportedMediaType(
+                    "Unsupported content type",
+                ));
+            }
+            let body = (&mut field).map(|chunk| chunk.map_err(Error::from));
+            file_name =
+                Some(store_stream(&s3, &encryption, &content_type, title.take(), body).await?);
+        } else if content_disposition.get_name() == Some("title") {
+            if file_name.is_some() {
+                return Err(error::ErrorBadRequest(
+                    "The title must come before the video",
+                ));
+            }
+            let mut raw = BytesMut::new();
             while let Some(chunk) = field.next().await {
                 let data = chunk?;
-                video_bytes.extend_from_slice(&data);
+                if raw.len() + data.len() > MAX_TITLE_CHARS * 4 {
+                    return Err(actix_web::error::ErrorBadRequest("Title is too long"));
+                }
+                raw.extend_from_slice(&data);
             }
+            title = parse_title(&raw)?;
         }
     }
 
+    let file_name = file_name.ok_or_else(|| error::ErrorBadRequest("Empty video upload"))?;
+    Ok(HttpResponse::Ok().body(format!("Uploaded as {}", file_name)))
+}
+
+/// Stores `body` in [`TEMP_BUCKET`] under a new `upload-<uuid>.mp4` key as an
+/// S3 multipart upload and returns the key. The upload is aborted if the
+/// body fails, is empty or is over [`MAX_UPLOAD_BYTES`].
+pub async fn store_stream<S>(
+    s3: &S3Client,
+    encryption: &Encryption,
+    content_type: &str,
+    title: Option<String>,
+    body: S,
+) -> Result<String, Error>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
+{
     let file_name = format!("upload-{}.mp4", uuid::Uuid::new_v4());
 
-    s3.put_object()
-        .bucket("temp-video-storage-0306")
+    // Encryption is fixed when the upload is created and covers every part.
+    let (sse, kms_key_id) = encryption.params();
+    let upload = s3
+        .create_multipart_upload()
+        .bucket(TEMP_BUCKET)
         .key(&file_name)
-        .body(video_bytes.freeze().into())
+        .content_type(content_type)
+        .set_metadata(title.map(|title| [("title".to_string(), title)].into()))
+        .set_server_side_encryption(sse)
+        .set_ssekms_key_id(kms_key_id)
         .send()
         .await
         .map_err(|e| {
             println!("S3 error: {:?}", e);
-            actix_web::error::ErrorInternalServerError("Upload failed")
+            error::ErrorInternalServerError("Upload failed")
         })?;
+    let upload_id = upload.upload_id().unwrap_or_default().to_string();
 
-    Ok(HttpResponse::Ok().body(format!("Uploaded as {}", file_name)))
+    let completed = match stream_parts(s3, &file_name, &upload_id, body).await {
+        Ok(parts) => s3
+            .complete_multipart_upload()
+            .bucket(TEMP_BUCKET)
+            .key(&file_name)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                println!("S3 error: {:?}", e);
+                error::ErrorInternalServerError("Upload failed")
+            }),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = completed {
+        let _ = s3
+            .abort_multipart_upload()
+            .bucket(TEMP_BUCKET)
+            .key(&file_name)
+            .upload_id(&upload_id)
+            .send()
+            .await;
+        return Err(e);
+    }
+    Ok(file_name)
+}
+
+/// Uploads the body one part at a time so that only a single part is ever
+/// held in memory.
+async fn stream_parts<S>(
+    s3: &S3Client,
+    key: &str,
+    upload_id: &str,
+    mut body: S,
+) -> Result<Vec<CompletedPart>, Error>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
+{
+    let mut buffer = BytesMut::with_capacity(PART_SIZE);
+    let mut parts = Vec::new();
+    let mut total: u64 = 0;
+
+    loop {
+        let chunk = body.next().await.transpose()?;
+        if let Some(data) = &chunk {
+            total += data.len() as u64;
+            if total > MAX_UPLOAD_BYTES {
+                return Err(error::ErrorPayloadTooLarge("Video exceeds size limit"));
+            }
+            buffer.extend_from_slice(data);
+        }
+
+        let finished = chunk.is_none();
+        if finished && total == 0 {
+            return Err(error::ErrorBadRequest("Video is empty"));
+        }
+        if buffer.len() >= PART_SIZE || (finished && !buffer.is_empty()) {
+            let part_number = parts.len() as i32 + 1;
+            let body = buffer.split().freeze();
+            let out = s3
+                .upload_part()
+                .bucket(TEMP_BUCKET)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(body))
+                .send()
+                .await
+                .map_err(|e| {
+                    println!("S3 error: {:?}", e);
+                    error::ErrorInternalServerError("Upload failed")
+                })?;
+            parts.push(
+                CompletedPart::builder()
+                    .set_e_tag(out.e_tag().map(str::to_string))
+                    .part_number(part_number)
+                    .build(),
+            );
+        }
+
+        if finished {
+            return Ok(parts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encryption_modes_map_to_request_parameters() {
+        assert_eq!(
+            Encryption::parse(None, None).unwrap().params(),
+            (None, None)
+        );
+        assert_eq!(
+            Encryption::parse(Some("aes256"), None).unwrap().params(),
+            (Some(ServerSideEncryption::Aes256), None)
+        );
+        assert_eq!(
+            Encryption::parse(Some("KMS"), Some("alias/media"))
+                .unwrap()
+                .params(),
+            (
+                Some(ServerSideEncryption::AwsKms),
+                Some("alias/media".to_string())
+            )
+        );
+        assert!(Encryption::parse(Some("kms"), None).is_err());
+        assert!(Encryption::parse(Some("none"), Some("alias/media")).is_err());
+    }
+
+    /// A client that is never expected to send anything.
+    fn offline_client() -> S3Client {
+        S3Client::from_conf(
+            aws_sdk_s3::config::Builder::new()
+                .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+                .region(aws_sdk_s3::config::Region::new("us-east-1"))
+                .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn empty_bodies_are_rejected_before_any_part_is_sent() {
+        let bodies: [Vec<Result<Bytes, Error>>; 2] =
+            [vec![], vec![Ok(Bytes::new()), Ok(Bytes::new())]];
+        for chunks in bodies {
+            let err = stream_parts(
+                &offline_client(),
+                "upload.mp4",
+                "id",
+                futures_util::stream::iter(chunks),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(
+                err.as_response_error().status_code(),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+            assert_eq!(err.to_string(), "Video is empty");
+        }
+    }
+
+    #[test]
+    fn titles_are_trimmed_and_blank_ones_dropped() {
+        assert_eq!(
+            parse_title(b"  Launch keynote \n").unwrap().as_deref(),
+            Some("Launch keynote")
+        );
+        assert_eq!(parse_title(b"   ").unwrap(), None);
+        assert_eq!(parse_title(b"").unwrap(), None);
+    }
+
+    #[test]
+    fn titles_must_fit_in_a_metadata_header() {
+        assert!(parse_title("Café".as_bytes()).is_err());
+        assert!(parse_title(b"line\tbreak").is_err());
+        assert!(parse_title(&[0xff, 0xfe]).is_err());
+        assert!(parse_title(&[b'a'; MAX_TITLE_CHARS]).unwrap().is_some());
+        assert!(parse_title(&[b'a'; MAX_TITLE_CHARS + 1]).is_err());
+    }
 }
@@ -0,0 +1,107 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Where the SHA-256 of each rendition goes (`CHECKSUM_MODE`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumMode {
+    None,
+    /// `x-amz-meta-sha256` on the rendition itself.
+    Metadata,
+    /// A `<key>.sha256` object next to the rendition, in `sha256sum` format.
+    Sidecar,
+}
+
+impl ChecksumMode {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("CHECKSUM_MODE") {
+            Ok(v) => match v.to_ascii_lowercase().as_str() {
+                "none" => Ok(Self::None),
+                "metadata" => Ok(Self::Metadata),
+                "sidecar" => Ok(Self::Sidecar),
+                _ => Err(format!("Unknown CHECKSUM_MODE: {}", v)),
+            },
+            Err(_) => Ok(Self::None),
+        }
+    }
+}
+
+/// Hex SHA-256 of the file at `path`, read in chunks so a large rendition
+/// isn't held in memory.
+pub fn sha256_file(path: &str) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// The body of a `.sha256` sidecar, as `sha256sum` prints it, so
+/// `sha256sum -c` can check a download saved under its original name.
+pub fn sidecar_body(hash: &str, key: &str) -> String {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    format!("{}  {}\n", hash, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::JobWorkspace;
+
+    fn hash_of(body: &[u8]) -> String {
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        let path = workspace.file("rendition.mp4");
+        std::fs::write(&path, body).unwrap();
+        sha256_file(&path).unwrap()
+    }
+
+    #[test]
+    fn hashes_match_known_digests() {
+        assert_eq!(
+            hash_of(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash_of(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn files_longer_than_one_chunk_hash_whole() {
+        let body: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| i as u8).collect();
+        let expected: String = Sha256::digest(&body)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(hash_of(&body), expected);
+    }
+
+    #[test]
+    fn missing_files_are_an_error() {
+        assert!(sha256_file("/nonexistent/rendition.mp4").is_err());
+    }
+
+    #[test]
+    fn sidecars_name_the_file_not_the_key() {
+        assert_eq!(
+            sidecar_body("abc123", "clip/720p.mp4"),
+            "abc123  720p.mp4\n"
+        );
+        assert_eq!(sidecar_body("abc123", "720p.mp4"), "abc123  720p.mp4\n");
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use aws_config::timeout::TimeoutConfig;
+
+use serde_json::{Value, json};
+
+use crate::checksum::ChecksumMode;
+use crate::destination::{Destination, KeyLayout};
+use crate::ffmpeg::{
+    self, EncodeSettings, LadderMode, OutputFormat, Rung, TrackMode, UpscalePolicy,
+};
+use crate::notify::JobStatus;
+use crate::preview::{ClipFormat, PreviewClipSettings};
+use crate::profile::Profile;
+use crate::progress::ProgressSettings;
+use crate::s3::OnExisting;
+use crate::slug;
+use crate::storyboard::StoryboardSettings;
+use crate::tags::OutputTags;
+
+pub const SOURCE_BUCKET: &str = "temp-video-storage-0342";
+pub const DEST_BUCKET: &str = "perm-video-storage-0342";
+
+/// Everything the job reads from its environment, resolved and validated
+/// up front so a bad setting fails before any work is done.
+#[derive(Clone, Debug)]
+pub struct JobConfig {
+    /// The first of `source_keys`; names the job's outputs and notices.
+    pub source_key: String,
+    /// Parts to join in order before transcoding (`SOURCE_KEYS`), or just
+    /// `source_key`.
+    pub source_keys: Vec<String>,
+    /// First path segment of the source key when `ENFORCE_TENANT_PREFIX` is
+    /// set; outputs are confined to it.
+    pub tenant: Option<String>,
+    pub work_root: String,
+    /// Name the downloaded source after the key's file name instead of
+    /// `input`, so ffmpeg's messages mention something recognizable.
+    pub input_name_from_key: bool,
+    pub ladder: Vec<Rung>,
+    /// `Native` replaces `ladder` with one rung sized to the source.
+    pub ladder_mode: LadderMode,
+    pub output_format: OutputFormat,
+    pub ffmpeg_loglevel: String,
+    pub upscale_policy: UpscalePolicy,
+    /// Under `CAP_BITRATE_TO_SOURCE`, no rung targets more than this many
+    /// times the source bitrate (`BITRATE_CAP_FACTOR`, default 1.2).
+    pub bitrate_cap_factor: Option<f64>,
+    pub encode: EncodeSettings,
+    pub destination: Destination,
+    pub on_existing: OnExisting,
+    pub key_layout: KeyLayout,
+    /// Keep the source key's directories in front of the output prefix
+    /// (`PRESERVE_SOURCE_PREFIX`), so `a/clip.mp4` and `b/clip.mp4` don't
+    /// both write to `clip/`.
+    pub preserve_source_prefix: bool,
+    /// Slugify the stem in output keys (`SANITIZE_OUTPUT_KEYS`); see
+    /// [`slug::slugify`].
+    pub sanitize_output_keys: bool,
+    pub checksum_mode: ChecksumMode,
+    pub upload_logs: bool,
+    /// Renditions uploading while the next rung encodes
+    /// (`UPLOAD_CONCURRENCY`, default 2).
+    pub upload_concurrency: usize,
+    /// Burn the `<stem>.srt` sidecar next to the source into the video, when
+    /// there is one (`BURN_SUBTITLES`).
+    pub burn_subtitles: bool,
+    /// Fail the job if any rung fails (`REQUIRE_ALL_RUNGS`). Otherwise it
+    /// only fails when no rung succeeds.
+    pub require_all_rungs: bool,
+    pub delete_source_on_success: bool,
+    /// Delete a source rejected as empty or corrupt.
+    pub delete_corrupt_source: bool,
+    /// Copy the source instead of running ffmpeg for each rung.
+    pub fake_transcode: bool,
+    pub storyboard: Option<StoryboardSettings>,
+    pub preview_clip: Option<PreviewClipSettings>,
+    pub preview_first: bool,
+    /// Trim the `PREVIEW_FIRST` proxy to this many seconds.
+    pub preview_first_secs: Option<f64>,
+    pub tags: Option<OutputTags>,
+    pub progress: Option<ProgressSettings>,
+    pub min_duration_secs: Option<f64>,
+    pub max_duration_secs: Option<f64>,
+    /// Key template for the `_SUCCESS`/`_FAILED` object written when the
+    /// job ends (`WRITE_DONE_MARKER`).
+    pub done_marker: Option<String>,
+    /// Free space to keep on top of the estimated need (`MIN_FREE_DISK_MB`).
+    pub min_free_disk_bytes: u64,
+    /// Source user metadata keys passed through to the completion notice.
+    pub metadata_keys: Vec<String>,
+    /// Also set the allowlisted metadata on every uploaded output.
+    pub metadata_on_outputs: bool,
+}
+
+impl JobConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let source_keys = source_keys_from_env()?;
+        let source_key = source_keys[0].clone();
+        let tenant = if env_flag("ENFORCE_TENANT_PREFIX") {
+            let tenant = tenant_of(&source_key)?;
+            for key in &source_keys[1..] {
+                if tenant_of(key)? != tenant {
+                    return Err(format!("SOURCE_KEYS span more than one tenant: {}", key));
+                }
+            }
+            Some(tenant)
+        } else {
+            None
+        };
+        let preview_clip = if env_flag("PREVIEW_CLIP") {
+            Some(PreviewClipSettings::from_env()?)
+        } else {
+            None
+        };
+        let preview_first = env_flag("PREVIEW_FIRST");
+        // Both would be written to <stem>/preview.mp4.
+        if preview_first
+            && preview_clip
+                .as_ref()
+                .is_some_and(|c| c.format == ClipFormat::Mp4)
+        {
+            return Err("PREVIEW_FIRST cannot be combined with an mp4 PREVIEW_CLIP".to_string());
+        }
+        let output_format = OutputFormat::from_env()?;
+        // A DASH output is a directory of files referencing each other, so
+        // it can't be skipped or versioned one object at a time.
+        let on_existing = OnExisting::from_env()?;
+        if output_format == OutputFormat::Dash && on_existing != OnExisting::Overwrite {
+            return Err("ON_EXISTING is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        // The manifest finds its segments by relative path.
+        let key_layout = KeyLayout::from_env()?;
+        if output_format == OutputFormat::Dash && key_layout == KeyLayout::Flat {
+            return Err("KEY_LAYOUT=flat is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        let checksum_mode = ChecksumMode::from_env()?;
+        if output_format == OutputFormat::Dash && checksum_mode != ChecksumMode::None {
+            return Err("CHECKSUM_MODE is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        let encode = EncodeSettings::from_env()?;
+        let burn_subtitles = env_flag("BURN_SUBTITLES");
+        if burn_subtitles && encode.track_mode == TrackMode::AudioOnly {
+            return Err("BURN_SUBTITLES needs the video re-encoded, not AUDIO_ONLY".to_string());
+        }
+        if let Some(reason) =
+            ffmpeg::audio_codec_incompatibility(output_format, &encode.audio_codec)
+        {
+            return Err(reason);
+        }
+        if output_format == OutputFormat::Dash && encode.track_mode != TrackMode::Both {
+            return Err(
+                "VIDEO_ONLY/AUDIO_ONLY are not supported with OUTPUT_FORMAT=dash".to_string(),
+            );
+        }
+        Ok(Self {
+            source_key,
+            source_keys,
+            tenant,
+            work_root: env::var("WORK_DIR").unwrap_or_else(|_| "/tmp".to_string()),
+            input_name_from_key: env_flag("INPUT_NAME_FROM_KEY"),
+            ladder: ffmpeg::default_ladder(),
+            ladder_mode: LadderMode::from_env()?,
+            output_format,
+            ffmpeg_loglevel: ffmpeg::loglevel_from_env()?,
+            upscale_policy: UpscalePolicy::from_env()?,
+            bitrate_cap_factor: if env_flag("CAP_BITRATE_TO_SOURCE") {
+                Some(match env::var("BITRATE_CAP_FACTOR") {
+                    Ok(v) => v
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|n| n.is_finite() && *n > 0.0)
+                        .ok_or_else(|| format!("Invalid BITRATE_CAP_FACTOR: {}", v))?,
+                    Err(_) => 1.2,
+                })
+            } else {
+                None
+            },
+            encode,
+            destination: Destination::from_env(DEST_BUCKET)?,
+            on_existing,
+            key_layout,
+            preserve_source_prefix: env_flag("PRESERVE_SOURCE_PREFIX"),
+            sanitize_output_keys: env_flag("SANITIZE_OUTPUT_KEYS"),
+            checksum_mode,
+            upload_logs: env_flag("UPLOAD_LOGS"),
+            upload_concurrency: match env::var("UPLOAD_CONCURRENCY") {
+                Ok(v) => v
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("Invalid UPLOAD_CONCURRENCY: {}", v))?,
+                Err(_) => 2,
+            },
+            burn_subtitles,
+            require_all_rungs: env_flag("REQUIRE_ALL_RUNGS"),
+            delete_source_on_success: env_flag("DELETE_SOURCE_ON_SUCCESS"),
+            delete_corrupt_source: env_flag("DELETE_CORRUPT_SOURCE"),
+            fake_transcode: env_flag("FAKE_TRANSCODE"),
+            storyboard: if env_flag("STORYBOARD") {
+                Some(StoryboardSettings::from_env()?)
+            } else {
+                None
+            },
+            preview_clip,
+            preview_first,
+            preview_first_secs: env_secs("PREVIEW_FIRST_SECS")?,
+            tags: OutputTags::from_env()?,
+            progress: ProgressSettings::from_env()?,
+            min_duration_secs: env_secs("MIN_DURATION_SECS")?,
+            max_duration_secs: env_secs("MAX_DURATION_SECS")?,
+            done_marker: if env_flag("WRITE_DONE_MARKER") {
+                Some(marker_template_from_env()?)
+            } else {
+                None
+            },
+            min_free_disk_bytes: match env::var("MIN_FREE_DISK_MB") {
+                Ok(v) => v
+                    .parse::<u64>()
+                    .map(|mb| mb * 1024 * 1024)
+                    .map_err(|_| format!("Invalid MIN_FREE_DISK_MB: {}", v))?,
+                Err(_) => 256 * 1024 * 1024,
+            },
+            // S3 hands user metadata back with lowercased keys.
+            metadata_keys: env::var("METADATA_KEYS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|k| k.trim().to_ascii_lowercase())
+                        .filter(|k| !k.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            metadata_on_outputs: env_flag("METADATA_ON_OUTPUTS"),
+        })
+    }
+
+    /// One structured line per job so "what settings produced this output?"
+    /// is answered by a single grep. Built from the same values that drive
+    /// ffmpeg.
+    pub fn summary(&self) -> Value {
+        json!({
+            "level": "info",
+            "event": "job_settings",
+            "source_key": self.source_key,
+            "source_keys": self.source_keys,
+            "tenant": self.tenant,
+            "source_bucket": SOURCE_BUCKET,
+            "destination": self.destination,
+            "on_existing": self.on_existing,
+            "key_layout": self.key_layout,
+            "preserve_source_prefix": self.preserve_source_prefix,
+            "sanitize_output_keys": self.sanitize_output_keys,
+            "checksum_mode": self.checksum_mode,
+            "output_format": self.output_format,
+            "ffmpeg_loglevel": self.ffmpeg_loglevel,
+            "ladder": self.ladder,
+            "ladder_mode": self.ladder_mode,
+            "upscale_policy": self.upscale_policy,
+            "bitrate_cap_factor": self.bitrate_cap_factor,
+            "encode": self.encode,
+            "storyboard": self.storyboard.is_some(),
+            "preview_clip": self.preview_clip.is_some(),
+            "preview_first": self.preview_first,
+            "tags": self.tags,
+            "progress_interval_secs": self.progress.as_ref().map(|p| p.interval.as_secs()),
+            "upload_logs": self.upload_logs,
+            "upload_concurrency": self.upload_concurrency,
+            "burn_subtitles": self.burn_subtitles,
+            "require_all_rungs": self.require_all_rungs,
+            "delete_source_on_success": self.delete_source_on_success,
+            "delete_corrupt_source": self.delete_corrupt_source,
+            "fake_transcode": self.fake_transcode,
+            "min_duration_secs": self.min_duration_secs,
+            "max_duration_secs": self.max_duration_secs,
+            "done_marker": self.done_marker,
+            "min_free_disk_bytes": self.min_free_disk_bytes,
+            "metadata_keys": self.metadata_keys,
+            "metadata_on_outputs": self.metadata_on_outputs,
+        })
+    }
+
+    /// Overrides settings with those from an encoding profile.
+    pub fn apply_profile(&mut self, profile: Profile) -> Result<(), String> {
+        if let Some(rungs) = profile.rungs {
+            self.ladder = rungs;
+        }
+        if let Some(v) = profile.video_codec {
+            self.encode.video_codec = v;
+        }
+        if let Some(v) = profile.preset {
+            self.encode.preset = v;
+        }
+        if let Some(v) = profile.crf {
+            self.encode.crf = v;
+        }
+        if let Some(v) = profile.audio_codec {
+            self.encode.audio_codec = v;
+        }
+        if let Some(v) = profile.audio_bitrate {
+            self.encode.audio_bitrate = v;
+        }
+        if let Some(v) = profile.output_format {
+            self.output_format = v;
+        }
+        if self.output_format == OutputFormat::Dash && self.on_existing != OnExisting::Overwrite {
+            return Err("ON_EXISTING is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        if self.output_format == OutputFormat::Dash && self.key_layout == KeyLayout::Flat {
+            return Err("KEY_LAYOUT=flat is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        if self.output_format == OutputFormat::Dash && self.checksum_mode != ChecksumMode::None {
+            return Err("CHECKSUM_MODE is only supported with OUTPUT_FORMAT=mp4".to_string());
+        }
+        if let Some(reason) =
+            ffmpeg::audio_codec_incompatibility(self.output_format, &self.encode.audio_codec)
+        {
+            return Err(reason);
+        }
+        if self.output_format == OutputFormat::Dash && self.encode.track_mode != TrackMode::Both {
+            return Err(
+                "VIDEO_ONLY/AUDIO_ONLY are not supported with OUTPUT_FORMAT=dash".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Disk space a source of `source_bytes` needs: the source, plus room
+    /// for outputs assumed no larger than it. MP4 rungs wait on disk while up
+    /// to `upload_concurrency` of them upload, but DASH writes every rung
+    /// before uploading. Joined
+    /// parts briefly sit next to the file they were joined into.
+    pub fn disk_needed(&self, source_bytes: u64) -> u64 {
+        let source_bytes = if self.source_keys.len() > 1 {
+            source_bytes * 2
+        } else {
+            source_bytes
+        };
+        let outputs = match (self.output_format, self.ladder_mode) {
+            (OutputFormat::Dash, LadderMode::Full) => self.ladder.len() as u64,
+            (OutputFormat::Mp4, LadderMode::Full) => {
+                self.ladder.len().min(self.upload_concurrency + 1) as u64
+            }
+            (_, LadderMode::Native) => 1,
+        };
+        source_bytes + source_bytes * outputs + self.min_free_disk_bytes
+    }
+
+    /// File name of the source without its extension.
+    pub fn source_stem(&self) -> String {
+        Path::new(&self.source_key)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&self.source_key)
+            .to_string()
+    }
+
+    /// The stem output keys are built from: the source stem, slugified under
+    /// `SANITIZE_OUTPUT_KEYS`.
+    pub fn output_stem(&self) -> String {
+        let stem = self.source_stem();
+        if self.sanitize_output_keys {
+            slug::slugify(&stem)
+        } else {
+            stem
+        }
+    }
+
+    /// The captions sidecar for `BURN_SUBTITLES`: the source key with an
+    /// `.srt` extension.
+    pub fn subtitles_key(&self) -> String {
+        Path::new(&self.source_key)
+            .with_extension("srt")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Where the done marker for a job that ended with `status` goes, if
+    /// markers are on. Rejected jobs produced no outputs, so they count as
+    /// failed; partial jobs get their own `PARTIAL` marker.
+    pub fn done_marker_key(&self, status: &JobStatus) -> Option<String> {
+        let status = match status {
+            JobStatus::Success => "SUCCESS",
+            JobStatus::Partial => "PARTIAL",
+            JobStatus::Rejected | JobStatus::Failed => "FAILED",
+        };
+        let stem = self.output_stem();
+        self.done_marker.as_ref().map(|template| {
+            template
+                .replace("{prefix}", &self.output_prefix(&stem))
+                .replace("{stem}", &stem)
+                .replace("{status}", status)
+        })
+    }
+
+    /// Key prefix for every output of a source with file stem `stem`:
+    /// `<tenant>/<stem>` under `ENFORCE_TENANT_PREFIX`, else just `<stem>`.
+    /// Under `PRESERVE_SOURCE_PREFIX` it is the source's own directory plus
+    /// `<stem>`, which already starts with the tenant.
+    pub fn output_prefix(&self, stem: &str) -> String {
+        if self.preserve_source_prefix
+            && let Some((dir, _)) = self.source_key.rsplit_once('/')
+            && !dir.is_empty()
+        {
+            return format!("{}/{}", dir, stem);
+        }
+        match &self.tenant {
+            Some(tenant) => format!("{}/{}", tenant, stem),
+            None => stem.to_string(),
+        }
+    }
+
+    /// The allowlisted subset of the source's user metadata.
+    pub fn propagated_metadata(
+        &self,
+        metadata: &HashMap<String, String>,
+    ) -> BTreeMap<String, String> {
+        metadata
+            .iter()
+            .filter(|(key, _)| self.metadata_keys.contains(&key.to_ascii_lowercase()))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value.clone()))
+            .collect()
+    }
+
+    /// Why a source of this length should not be transcoded, if it shouldn't.
+    pub fn duration_rejection(&self, duration_secs: f64) -> Option<String> {
+        if let Some(min) = self.min_duration_secs
+            && duration_secs < min
+        {
+            return Some(format!(
+                "duration {:.1}s is below the minimum of {}s",
+                duration_secs, min
+            ));
+        }
+        if let Some(max) = self.max_duration_secs
+            && duration_secs > max
+        {
+            return Some(format!(
+                "duration {:.1}s exceeds the maximum of {}s",
+                duration_secs, max
+            ));
+        }
+        None
+    }
+}
+
+/// The tenant segment of `tenant-123/video.mp4`. Keys without one, or with
+/// an empty or relative first segment, are rejected.
+pub fn tenant_of(source_key: &str) -> Result<String, String> {
+    match source_key.split_once('/') {
+        Some((tenant, rest))
+            if !tenant.is_empty() && tenant != "." && tenant != ".." && !rest.is_empty() =>
+        {
+            Ok(tenant.to_string())
+        }
+        _ => Err(format!(
+            "ENFORCE_TENANT_PREFIX is set but {} has no tenant segment",
+            source_key
+        )),
+    }
+}
+
+/// `SOURCE_KEYS`, comma-separated, or else the single `SOURCE_KEY`.
+fn source_keys_from_env() -> Result<Vec<String>, String> {
+    parse_source_keys(
+        env::var("SOURCE_KEY").ok().as_deref(),
+        env::var("SOURCE_KEYS").ok().as_deref(),
+    )
+}
+
+fn parse_source_keys(key: Option<&str>, keys: Option<&str>) -> Result<Vec<String>, String> {
+    let Some(v) = keys else {
+        let key = key.ok_or("SOURCE_KEY environment variable not set")?;
+        return Ok(vec![key.to_string()]);
+    };
+    if key.is_some() {
+        return Err("Set SOURCE_KEY or SOURCE_KEYS, not both".to_string());
+    }
+    let keys: Vec<String> = v
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect();
+    if keys.is_empty() {
+        return Err(format!("Invalid SOURCE_KEYS: {}", v));
+    }
+    Ok(keys)
+}
+
+/// `DONE_MARKER_KEY`, default `{prefix}/_{status}`. It must name the
+/// source, or every job would write the same marker.
+fn marker_template_from_env() -> Result<String, String> {
+    let template = env::var("DONE_MARKER_KEY").unwrap_or_else(|_| "{prefix}/_{status}".to_string());
+    if !template.contains("{prefix}") && !template.contains("{stem}") {
+        return Err(format!(
+            "DONE_MARKER_KEY must contain {{prefix}} or {{stem}}: {}",
+            template
+        ));
+    }
+    Ok(template)
+}
+
+/// Ceiling on any one S3 or SNS call (`AWS_OP_TIMEOUT_SECS`). Outputs go
+/// up in a single `put_object`, so the default leaves room for a large
+/// rendition.
+const DEFAULT_AWS_OP_TIMEOUT: Duration = Duration::from_secs(900);
+const AWS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeouts for the AWS clients, so a call into a network black hole fails
+/// instead of hanging the job.
+pub fn aws_timeouts() -> Result<TimeoutConfig, String> {
+    let operation = match env::var("AWS_OP_TIMEOUT_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid AWS_OP_TIMEOUT_SECS: {}", v))?,
+        Err(_) => DEFAULT_AWS_OP_TIMEOUT,
+    };
+    Ok(TimeoutConfig::builder()
+        .connect_timeout(AWS_CONNECT_TIMEOUT)
+        .operation_timeout(operation)
+        .build())
+}
+
+pub fn env_flag(name: &str) -> bool {
+    env::var(name)
+        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Optional non-negative number of seconds; unset means no limit.
+fn env_secs(name: &str) -> Result<Option<f64>, String> {
+    match env::var(name) {
+        Ok(v) => v
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}: {}", name, v)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::destination::Encryption;
+
+    /// What [`JobConfig::from_env`] returns with only `SOURCE_KEY` set,
+    /// except that the workspace goes under the system temp directory.
+    pub(crate) fn job(source_key: &str) -> JobConfig {
+        JobConfig {
+            source_key: source_key.to_string(),
+            source_keys: vec![source_key.to_string()],
+            tenant: None,
+            work_root: env::temp_dir().to_string_lossy().into_owned(),
+            input_name_from_key: false,
+            ladder: ffmpeg::default_ladder(),
+            ladder_mode: LadderMode::Full,
+            output_format: OutputFormat::Mp4,
+            ffmpeg_loglevel: "warning".to_string(),
+            upscale_policy: UpscalePolicy::Skip,
+            bitrate_cap_factor: None,
+            encode: ffmpeg::tests::settings(),
+            destination: Destination {
+                default_bucket: DEST_BUCKET.to_string(),
+                rung_buckets: HashMap::new(),
+                default_storage_class: "STANDARD".to_string(),
+                rung_storage_classes: HashMap::new(),
+                encryption: Encryption::None,
+            },
+            on_existing: OnExisting::Overwrite,
+            key_layout: KeyLayout::Nested,
+            preserve_source_prefix: false,
+            sanitize_output_keys: false,
+            checksum_mode: ChecksumMode::None,
+            upload_logs: false,
+            upload_concurrency: 2,
+            burn_subtitles: false,
+            require_all_rungs: false,
+            delete_source_on_success: false,
+            delete_corrupt_source: false,
+            fake_transcode: false,
+            storyboard: None,
+            preview_clip: None,
+            preview_first: false,
+            preview_first_secs: None,
+            tags: None,
+            progress: None,
+            min_duration_secs: None,
+            max_duration_secs: None,
+            done_marker: None,
+            min_free_disk_bytes: 256 * 1024 * 1024,
+            metadata_keys: Vec::new(),
+            metadata_on_outputs: false,
+        }
+    }
+
+    #[test]
+    fn tenant_is_the_first_key_segment() {
+        assert_eq!(tenant_of("tenant-123/video.mp4").unwrap(), "tenant-123");
+        assert_eq!(tenant_of("acme/2024/06/video.mp4").unwrap(), "acme");
+        for key in [
+            "video.mp4",
+            "/video.mp4",
+            "acme/",
+            "../video.mp4",
+            "./video.mp4",
+        ] {
+            assert!(tenant_of(key).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn tenant_outputs_stay_under_the_tenant() {
+        let mut job = job("acme/uploads/clip.mp4");
+        job.tenant = Some(tenant_of(&job.source_key).unwrap());
+        assert_eq!(job.output_prefix("clip"), "acme/clip");
+    }
+
+    #[test]
+    fn only_allowlisted_metadata_is_propagated() {
+        let mut job = job("clip.mp4");
+        job.metadata_keys = vec!["title".to_string(), "uploader-id".to_string()];
+        let source = HashMap::from([
+            ("title".to_string(), "Launch day".to_string()),
+            ("Uploader-Id".to_string(), "u-42".to_string()),
+            ("internal-note".to_string(), "do not share".to_string()),
+        ]);
+        assert_eq!(
+            job.propagated_metadata(&source),
+            BTreeMap::from([
+                ("title".to_string(), "Launch day".to_string()),
+                ("uploader-id".to_string(), "u-42".to_string()),
+            ])
+        );
+
+        job.metadata_keys.clear();
+        assert!(job.propagated_metadata(&source).is_empty());
+    }
+
+    #[test]
+    fn done_marker_keys_follow_the_template() {
+        let mut job = job("acme/uploads/clip.mp4");
+        assert_eq!(job.done_marker_key(&JobStatus::Success), None);
+
+        job.done_marker = Some("{prefix}/_{status}".to_string());
+        assert_eq!(
+            job.done_marker_key(&JobStatus::Success).as_deref(),
+            Some("clip/_SUCCESS")
+        );
+        assert_eq!(
+            job.done_marker_key(&JobStatus::Partial).as_deref(),
+            Some("clip/_PARTIAL")
+        );
+        assert_eq!(
+            job.done_marker_key(&JobStatus::Rejected).as_deref(),
+            Some("clip/_FAILED")
+        );
+
+        job.tenant = Some("acme".to_string());
+        job.done_marker = Some("markers/{stem}.{status}.json".to_string());
+        assert_eq!(
+            job.done_marker_key(&JobStatus::Failed).as_deref(),
+            Some("markers/clip.FAILED.json")
+        );
+        job.done_marker = Some("{prefix}/done".to_string());
+        assert_eq!(
+            job.done_marker_key(&JobStatus::Success).as_deref(),
+            Some("acme/clip/done")
+        );
+    }
+
+    #[test]
+    fn source_keys_come_from_one_variable_or_the_other() {
+        assert_eq!(
+            parse_source_keys(Some("clip.mp4"), None).unwrap(),
+            ["clip.mp4"]
+        );
+        assert_eq!(
+            parse_source_keys(None, Some(" a/part1.mp4, a/part2.mp4 ,,")).unwrap(),
+            ["a/part1.mp4", "a/part2.mp4"]
+        );
+        assert!(parse_source_keys(None, None).is_err());
+        assert!(parse_source_keys(Some("clip.mp4"), Some("a.mp4,b.mp4")).is_err());
+        assert!(parse_source_keys(None, Some(" , ")).is_err());
+    }
+
+    #[test]
+    fn subtitles_sit_next_to_the_source() {
+        assert_eq!(job("acme/clip.mp4").subtitles_key(), "acme/clip.srt");
+        assert_eq!(job("acme/v1.2/clip").subtitles_key(), "acme/v1.2/clip.srt");
+    }
+
+    fn prefix_of(source_key: &str, preserve: bool) -> String {
+        let mut job = job(source_key);
+        job.preserve_source_prefix = preserve;
+        job.output_prefix(&job.output_stem())
+    }
+
+    #[test]
+    fn source_directories_are_dropped_by_default() {
+        assert_eq!(prefix_of("incoming/2024/clip.mp4", false), "clip");
+        assert_eq!(prefix_of("clip.mp4", false), "clip");
+    }
+
+    #[test]
+    fn preserved_prefixes_keep_nested_directories() {
+        assert_eq!(
+            prefix_of("incoming/2024/clip.mp4", true),
+            "incoming/2024/clip"
+        );
+        assert_eq!(prefix_of("clip.mp4", true), "clip");
+        assert_eq!(prefix_of("/clip.mp4", true), "clip");
+    }
+
+    #[test]
+    fn preserved_prefixes_keep_same_named_sources_apart() {
+        let sources = ["incoming/2024/clip.mp4", "archive/clip.mp4", "clip.mp4"];
+        let flattened: Vec<String> = sources.iter().map(|key| prefix_of(key, false)).collect();
+        assert_eq!(flattened, ["clip", "clip", "clip"]);
+        let preserved: Vec<String> = sources.iter().map(|key| prefix_of(key, true)).collect();
+        assert_eq!(preserved, ["incoming/2024/clip", "archive/clip", "clip"]);
+    }
+
+    #[test]
+    fn preserved_prefixes_already_start_with_the_tenant() {
+        let mut job = job("acme/incoming/clip.mp4");
+        job.tenant = Some("acme".to_string());
+        assert_eq!(job.output_prefix("clip"), "acme/clip");
+        job.preserve_source_prefix = true;
+        assert_eq!(job.output_prefix("clip"), "acme/incoming/clip");
+    }
+
+    #[test]
+    fn output_stems_are_slugified_only_when_asked() {
+        let mut job = job("uploads/Café clip.mp4");
+        assert_eq!(job.output_stem(), "Café clip");
+        job.sanitize_output_keys = true;
+        assert_eq!(job.source_stem(), "Café clip");
+        assert_eq!(job.output_stem(), "Cafe-clip");
+        assert_eq!(job.output_prefix(&job.output_stem()), "Cafe-clip");
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::env;
+
+use aws_sdk_s3::types::{ServerSideEncryption, StorageClass};
+use serde::Serialize;
+
+/// Where outputs are written. Individual rungs can be routed to their own
+/// bucket and storage class; everything else uses the defaults.
+#[derive(Clone, Debug, Serialize)]
+pub struct Destination {
+    pub default_bucket: String,
+    pub rung_buckets: HashMap<String, String>,
+    pub default_storage_class: String,
+    pub rung_storage_classes: HashMap<String, String>,
+    pub encryption: Encryption,
+}
+
+/// How output keys hang off the job's prefix (`KEY_LAYOUT`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyLayout {
+    /// `<stem>/480p.mp4`, `<stem>/storyboard/storyboard.vtt`.
+    Nested,
+    /// `<stem>_480p.mp4`, `<stem>_storyboard_storyboard.vtt`.
+    Flat,
+}
+
+impl KeyLayout {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("KEY_LAYOUT") {
+            Ok(v) => Self::parse(&v),
+            Err(_) => Ok(Self::Nested),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "nested" => Ok(Self::Nested),
+            "flat" => Ok(Self::Flat),
+            _ => Err(format!("Unknown KEY_LAYOUT: {}", value)),
+        }
+    }
+
+    /// The key for output `path` (e.g. `logs/480p.log`) of the job whose
+    /// keys start with `prefix`. Every output key is built here.
+    pub fn key(self, prefix: &str, path: &str) -> String {
+        match self {
+            Self::Nested => format!("{}/{}", prefix, path),
+            Self::Flat => format!("{}_{}", prefix, path.replace('/', "_")),
+        }
+    }
+}
+
+/// Server-side encryption applied to every output (`SSE_MODE`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum Encryption {
+    None,
+    /// SSE-S3 with S3-managed keys.
+    Aes256,
+    /// SSE-KMS with the key from `SSE_KMS_KEY_ID`.
+    Kms {
+        key_id: String,
+    },
+}
+
+impl Encryption {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(
+            env::var("SSE_MODE").ok().as_deref(),
+            env::var("SSE_KMS_KEY_ID").ok().as_deref(),
+        )
+    }
+
+    /// `SSE_MODE` (default `none`) and `SSE_KMS_KEY_ID`, which only goes
+    /// with `kms`.
+    pub fn parse(mode: Option<&str>, key_id: Option<&str>) -> Result<Self, String> {
+        let key_id = key_id.filter(|v| !v.is_empty()).map(str::to_string);
+        let mode = mode.unwrap_or("none").to_ascii_lowercase();
+        match mode.as_str() {
+            "none" | "aes256" if key_id.is_some() => {
+                Err("SSE_KMS_KEY_ID is only used with SSE_MODE=kms".to_string())
+            }
+            "none" => Ok(Self::None),
+            "aes256" => Ok(Self::Aes256),
+            "kms" => key_id
+                .map(|key_id| Self::Kms { key_id })
+                .ok_or_else(|| "SSE_MODE=kms requires SSE_KMS_KEY_ID".to_string()),
+            _ => Err(format!("Unknown SSE_MODE: {}", mode)),
+        }
+    }
+
+    /// The `server_side_encryption` and `ssekms_key_id` request parameters.
+    pub fn params(&self) -> (Option<ServerSideEncryption>, Option<&str>) {
+        match self {
+            Encryption::None => (None, None),
+            Encryption::Aes256 => (Some(ServerSideEncryption::Aes256), None),
+            Encryption::Kms { key_id } => (Some(ServerSideEncryption::AwsKms), Some(key_id)),
+        }
+    }
+}
+
+impl Destination {
+    pub fn from_env(default_bucket: &str) -> Result<Self, String> {
+        let rung_buckets = match env::var("RUNG_BUCKETS") {
+            Ok(v) => parse_rung_buckets(&v)?,
+            Err(_) => HashMap::new(),
+        };
+        let default_storage_class = match env::var("DEST_STORAGE_CLASS") {
+            Ok(v) => validate_storage_class(&v)?,
+            Err(_) => StorageClass::Standard.as_str().to_string(),
+        };
+        let rung_storage_classes = match env::var("RUNG_STORAGE_CLASSES") {
+            Ok(v) => parse_rung_storage_classes(&v)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(Self {
+            default_bucket: default_bucket.to_string(),
+            rung_buckets,
+            default_storage_class,
+            rung_storage_classes,
+            encryption: Encryption::from_env()?,
+        })
+    }
+
+    /// Every bucket outputs may be written to.
+    pub fn buckets(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.default_bucket.as_str())
+            .chain(self.rung_buckets.values().map(String::as_str))
+    }
+
+    pub fn bucket_for(&self, rung: &str) -> &str {
+        self.rung_buckets
+            .get(rung)
+            .map(String::as_str)
+            .unwrap_or(&self.default_bucket)
+    }
+
+    /// Storage class for a rung, or for a non-rung output such as a log or
+    /// storyboard when `rung` is `None`.
+    pub fn storage_class_for(&self, rung: Option<&str>) -> &str {
+        rung.and_then(|rung| self.rung_storage_classes.get(rung))
+            .map(String::as_str)
+            .unwrap_or(&self.default_storage_class)
+    }
+}
+
+/// Parses `1080p@GLACIER_IR,480p@STANDARD_IA` into a rung → class map.
+pub fn parse_rung_storage_classes(value: &str) -> Result<HashMap<String, String>, String> {
+    let mut map = HashMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (rung, class) = entry.split_once('@').ok_or_else(|| {
+            format!(
+                "Invalid RUNG_STORAGE_CLASSES entry (expected rung@CLASS): {}",
+                entry
+            )
+        })?;
+        let rung = rung.trim();
+        if rung.is_empty() {
+            return Err(format!(
+                "Missing rung name in RUNG_STORAGE_CLASSES entry: {}",
+                entry
+            ));
+        }
+        let class = validate_storage_class(class.trim())?;
+        if map.insert(rung.to_string(), class).is_some() {
+            return Err(format!("Duplicate rung in RUNG_STORAGE_CLASSES: {}", rung));
+        }
+    }
+    Ok(map)
+}
+
+/// Accepts any storage class the SDK knows, case-insensitively, and returns
+/// its canonical spelling.
+pub fn validate_storage_class(value: &str) -> Result<String, String> {
+    StorageClass::values()
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(value))
+        .map(|known| known.to_string())
+        .ok_or_else(|| {
+            format!(
+                "Unknown S3 storage class: {} (expected one of {})",
+                value,
+                StorageClass::values().join(", ")
+            )
+        })
+}
+
+/// Parses `480p@bucket-low,1080p@bucket-high` into a rung → bucket map.
+pub fn parse_rung_buckets(value: &str) -> Result<HashMap<String, String>, String> {
+    let mut map = HashMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (rung, bucket) = entry.split_once('@').ok_or_else(|| {
+            format!(
+                "Invalid RUNG_BUCKETS entry (expected rung@bucket): {}",
+                entry
+            )
+        })?;
+        let (rung, bucket) = (rung.trim(), bucket.trim());
+        if rung.is_empty() {
+            return Err(format!(
+                "Missing rung name in RUNG_BUCKETS entry: {}",
+                entry
+            ));
+        }
+        validate_bucket_name(bucket)?;
+        if map.insert(rung.to_string(), bucket.to_string()).is_some() {
+            return Err(format!("Duplicate rung in RUNG_BUCKETS: {}", rung));
+        }
+    }
+    Ok(map)
+}
+
+/// Checks the S3 bucket naming rules that are cheap to verify locally.
+pub fn validate_bucket_name(name: &str) -> Result<(), String> {
+    let valid_chars = name
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'.');
+    let valid_ends = name
+        .bytes()
+        .next()
+        .zip(name.bytes().last())
+        .is_some_and(|(first, last)| first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric());
+    if !(3..=63).contains(&name.len()) || !valid_chars || !valid_ends || name.contains("..") {
+        return Err(format!("Invalid S3 bucket name: {}", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rung_buckets_parse_into_a_map() {
+        let map = parse_rung_buckets(" 480p@bucket-low, 1080p@bucket.high ,").unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["480p"], "bucket-low");
+        assert_eq!(map["1080p"], "bucket.high");
+        assert!(parse_rung_buckets("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_rung_buckets_are_rejected() {
+        for value in [
+            "480p",
+            "@bucket-low",
+            "480p@Bucket",
+            "480p@ab",
+            "480p@-bucket",
+            "480p@a..b",
+            "480p@one-bucket,480p@two-bucket",
+        ] {
+            assert!(parse_rung_buckets(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn unrouted_rungs_use_the_default_bucket() {
+        let destination = Destination {
+            default_bucket: "perm".to_string(),
+            rung_buckets: parse_rung_buckets("1080p@bucket-high").unwrap(),
+            default_storage_class: "STANDARD".to_string(),
+            rung_storage_classes: HashMap::new(),
+            encryption: Encryption::None,
+        };
+        assert_eq!(destination.bucket_for("1080p"), "bucket-high");
+        assert_eq!(destination.bucket_for("480p"), "perm");
+        let mut buckets: Vec<&str> = destination.buckets().collect();
+        buckets.sort();
+        assert_eq!(buckets, ["bucket-high", "perm"]);
+    }
+
+    #[test]
+    fn storage_classes_are_canonicalized() {
+        assert_eq!(validate_storage_class("glacier_ir").unwrap(), "GLACIER_IR");
+        assert_eq!(
+            validate_storage_class("STANDARD_IA").unwrap(),
+            "STANDARD_IA"
+        );
+        assert!(validate_storage_class("COLD").is_err());
+        assert!(validate_storage_class("").is_err());
+    }
+
+    #[test]
+    fn rung_storage_classes_reject_unknown_classes() {
+        let map = parse_rung_storage_classes("1080p@glacier_ir, 480p@STANDARD_IA").unwrap();
+        assert_eq!(map["1080p"], "GLACIER_IR");
+        assert_eq!(map["480p"], "STANDARD_IA");
+        for value in [
+            "1080p@COLD",
+            "1080p",
+            "@GLACIER",
+            "1080p@GLACIER,1080p@STANDARD",
+        ] {
+            assert!(parse_rung_storage_classes(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn outputs_without_a_rung_class_use_the_default() {
+        let destination = Destination {
+            default_bucket: "perm".to_string(),
+            rung_buckets: HashMap::new(),
+            default_storage_class: "STANDARD".to_string(),
+            rung_storage_classes: parse_rung_storage_classes("1080p@GLACIER_IR").unwrap(),
+            encryption: Encryption::None,
+        };
+        assert_eq!(destination.storage_class_for(Some("1080p")), "GLACIER_IR");
+        assert_eq!(destination.storage_class_for(Some("480p")), "STANDARD");
+        assert_eq!(destination.storage_class_for(None), "STANDARD");
+    }
+
+    #[test]
+    fn encryption_modes_map_to_request_parameters() {
+        assert_eq!(
+            Encryption::parse(None, None).unwrap().params(),
+            (None, None)
+        );
+        assert_eq!(
+            Encryption::parse(Some("AES256"), None).unwrap().params(),
+            (Some(ServerSideEncryption::Aes256), None)
+        );
+        let kms = Encryption::parse(Some("kms"), Some("alias/media")).unwrap();
+        assert_eq!(
+            kms.params(),
+            (Some(ServerSideEncryption::AwsKms), Some("alias/media"))
+        );
+    }
+
+    #[test]
+    fn kms_key_id_goes_with_kms_only() {
+        assert!(Encryption::parse(Some("kms"), None).is_err());
+        assert!(Encryption::parse(Some("kms"), Some("")).is_err());
+        assert!(Encryption::parse(Some("aes256"), Some("alias/media")).is_err());
+        assert!(Encryption::parse(None, Some("alias/media")).is_err());
+        assert!(Encryption::parse(Some("sse-c"), None).is_err());
+    }
+
+    #[test]
+    fn key_layouts_parse_case_insensitively() {
+        assert_eq!(KeyLayout::parse("Nested"), Ok(KeyLayout::Nested));
+        assert_eq!(KeyLayout::parse("FLAT"), Ok(KeyLayout::Flat));
+        assert!(KeyLayout::parse("tree").is_err());
+    }
+
+    #[test]
+    fn every_output_kind_follows_the_layout() {
+        let cases = [
+            ("480p.mp4", "clip/480p.mp4", "clip_480p.mp4"),
+            ("logs/480p.log", "clip/logs/480p.log", "clip_logs_480p.log"),
+            ("preview.mp4", "clip/preview.mp4", "clip_preview.mp4"),
+            ("preview.gif", "clip/preview.gif", "clip_preview.gif"),
+            (
+                "storyboard/storyboard.jpg",
+                "clip/storyboard/storyboard.jpg",
+                "clip_storyboard_storyboard.jpg",
+            ),
+            (
+                "storyboard/storyboard.vtt",
+                "clip/storyboard/storyboard.vtt",
+                "clip_storyboard_storyboard.vtt",
+            ),
+        ];
+        for (path, nested, flat) in cases {
+            assert_eq!(KeyLayout::Nested.key("clip", path), nested);
+            assert_eq!(KeyLayout::Flat.key("clip", path), flat);
+        }
+    }
+
+    #[test]
+    fn only_the_output_path_is_flattened() {
+        assert_eq!(
+            KeyLayout::Flat.key("acme/clip", "logs/480p.log"),
+            "acme/clip_logs_480p.log"
+        );
+    }
+}
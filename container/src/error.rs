@@ -0,0 +1,231 @@
+use aws_sdk_s3::primitives::ByteStreamError;
+use serde::Serialize;
+
+/// Failures the transcoding job can run into, kept distinct so callers can
+/// decide what is worth retrying.
+#[derive(Debug, thiserror::Error)]
+pub enum TranscodeError {
+    #[error("failed to download s3://{bucket}/{key}: {source}")]
+    S3Download {
+        bucket: String,
+        key: String,
+        #[source]
+        source: Box<aws_sdk_s3::Error>,
+    },
+
+    /// The object doesn't exist, e.g. it was deleted before the job ran.
+    /// Retrying won't bring it back.
+    #[error("s3://{bucket}/{key} does not exist")]
+    SourceMissing { bucket: String, key: String },
+
+    #[error("failed to upload s3://{bucket}/{key}: {source}")]
+    S3Upload {
+        bucket: String,
+        key: String,
+        #[source]
+        source: Box<aws_sdk_s3::Error>,
+    },
+
+    #[error("failed to delete s3://{bucket}/{key}: {source}")]
+    S3Delete {
+        bucket: String,
+        key: String,
+        #[source]
+        source: Box<aws_sdk_s3::Error>,
+    },
+
+    #[error("refusing to delete s3://{bucket}/{prefix}: {reason}")]
+    DestructiveRefused {
+        bucket: String,
+        prefix: String,
+        reason: &'static str,
+    },
+
+    #[error(
+        "insufficient ephemeral storage (need {} MiB, have {} MiB)",
+        need / (1024 * 1024),
+        have / (1024 * 1024)
+    )]
+    InsufficientStorage { need: u64, have: u64 },
+
+    #[error("s3://{bucket}/{key} already exists and ON_EXISTING=fail")]
+    OutputExists { bucket: String, key: String },
+
+    #[error("refusing to write {key} outside tenant prefix {tenant}/")]
+    OutsideTenant { key: String, tenant: String },
+
+    #[error("S3 body stream failed: {0}")]
+    S3Body(#[from] ByteStreamError),
+
+    #[error(transparent)]
+    Ffmpeg(#[from] FfmpegError),
+
+    #[error("ffprobe failed: {0}")]
+    Probe(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl TranscodeError {
+    /// Failure category for notifications, when one is known.
+    pub fn category(&self) -> Option<&'static str> {
+        match self {
+            TranscodeError::Ffmpeg(e) => Some(e.kind.slug()),
+            _ => None,
+        }
+    }
+}
+
+/// A failed ffmpeg run, classified from its stderr.
+#[derive(Debug, thiserror::Error)]
+#[error("ffmpeg failed ({}) with {}", .kind.slug(), describe_exit(.code))]
+pub struct FfmpegError {
+    pub kind: FfmpegErrorKind,
+    pub code: Option<i32>,
+    /// Tail of ffmpeg's stderr.
+    pub stderr: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FfmpegErrorKind {
+    /// The source is truncated, not a video, or has a broken header.
+    CorruptInput,
+    /// The source uses a codec this ffmpeg build cannot decode.
+    UnsupportedCodec,
+    /// The configured encoder is not compiled into this ffmpeg build.
+    EncoderMissing,
+    /// An option or filter was rejected; usually a configuration mistake.
+    InvalidOption,
+    /// The working directory ran out of space.
+    DiskFull,
+    /// ffmpeg was killed by a signal, typically the OOM killer.
+    Killed,
+    Unknown,
+}
+
+/// Known stderr fragments, checked in order, with the category they imply.
+const STDERR_PATTERNS: &[(&str, FfmpegErrorKind)] = &[
+    ("Unknown encoder", FfmpegErrorKind::EncoderMissing),
+    ("Encoder not found", FfmpegErrorKind::EncoderMissing),
+    ("Decoder not found", FfmpegErrorKind::UnsupportedCodec),
+    ("Unsupported codec", FfmpegErrorKind::UnsupportedCodec),
+    (
+        "could not find codec parameters",
+        FfmpegErrorKind::UnsupportedCodec,
+    ),
+    ("moov atom not found", FfmpegErrorKind::CorruptInput),
+    (
+        "Invalid data found when processing input",
+        FfmpegErrorKind::CorruptInput,
+    ),
+    ("End of file", FfmpegErrorKind::CorruptInput),
+    ("No space left on device", FfmpegErrorKind::DiskFull),
+    ("Unrecognized option", FfmpegErrorKind::InvalidOption),
+    ("Option not found", FfmpegErrorKind::InvalidOption),
+    ("Error initializing filter", FfmpegErrorKind::InvalidOption),
+    ("Invalid argument", FfmpegErrorKind::InvalidOption),
+];
+
+impl FfmpegErrorKind {
+    pub fn classify(stderr: &str, code: Option<i32>) -> Self {
+        if code.is_none() {
+            return FfmpegErrorKind::Killed;
+        }
+        STDERR_PATTERNS
+            .iter()
+            .find(|(pattern, _)| stderr.contains(pattern))
+            .map(|(_, kind)| *kind)
+            .unwrap_or(FfmpegErrorKind::Unknown)
+    }
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            FfmpegErrorKind::CorruptInput => "corrupt-input",
+            FfmpegErrorKind::UnsupportedCodec => "unsupported-codec",
+            FfmpegErrorKind::EncoderMissing => "encoder-missing",
+            FfmpegErrorKind::InvalidOption => "invalid-option",
+            FfmpegErrorKind::DiskFull => "disk-full",
+            FfmpegErrorKind::Killed => "killed",
+            FfmpegErrorKind::Unknown => "unknown",
+        }
+    }
+}
+
+fn describe_exit(code: &Option<i32>) -> String {
+    match code {
+        Some(code) => format!("code {}", code),
+        None => "a signal".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_snippets_map_to_categories() {
+        for (stderr, expected) in [
+            (
+                "[h264 @ 0x55d] Unknown encoder 'libx265'",
+                FfmpegErrorKind::EncoderMissing,
+            ),
+            (
+                "Decoder (codec prores_raw) not found for input stream #0:0\nDecoder not found",
+                FfmpegErrorKind::UnsupportedCodec,
+            ),
+            (
+                "[mov,mp4,m4a,3gp,3g2,mj2 @ 0x5581] moov atom not found\ninput.mp4: Invalid data found when processing input",
+                FfmpegErrorKind::CorruptInput,
+            ),
+            (
+                "input.mp4: Invalid data found when processing input",
+                FfmpegErrorKind::CorruptInput,
+            ),
+            (
+                "av_interleaved_write_frame(): No space left on device",
+                FfmpegErrorKind::DiskFull,
+            ),
+            (
+                "Unrecognized option 'frobnicate'.\nError splitting the argument list: Option not found",
+                FfmpegErrorKind::InvalidOption,
+            ),
+            (
+                "[Parsed_scale_0 @ 0x5] Error initializing filter 'scale'",
+                FfmpegErrorKind::InvalidOption,
+            ),
+            ("Conversion failed!", FfmpegErrorKind::Unknown),
+        ] {
+            assert_eq!(
+                FfmpegErrorKind::classify(stderr, Some(1)),
+                expected,
+                "{}",
+                stderr
+            );
+        }
+    }
+
+    #[test]
+    fn a_signal_means_killed_whatever_stderr_says() {
+        assert_eq!(
+            FfmpegErrorKind::classify("No space left on device", None),
+            FfmpegErrorKind::Killed
+        );
+    }
+
+    #[test]
+    fn ffmpeg_errors_report_their_category() {
+        let error = TranscodeError::from(FfmpegError {
+            kind: FfmpegErrorKind::UnsupportedCodec,
+            code: Some(1),
+            stderr: String::new(),
+        });
+        assert_eq!(error.category(), Some("unsupported-codec"));
+        assert_eq!(
+            error.to_string(),
+            "ffmpeg failed (unsupported-codec) with code 1"
+        );
+        assert_eq!(TranscodeError::Probe("x".to_string()).category(), None);
+    }
+}
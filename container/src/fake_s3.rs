@@ -0,0 +1,196 @@
+//! Just enough of the S3 REST API for tests to run uploads and downloads
+//! through a real client: path-style GET, HEAD, PUT and DELETE of whole
+//! objects, held in memory.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::{
+    BehaviorVersion, Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation,
+};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Default)]
+struct State {
+    /// Object bodies by `bucket/key`.
+    objects: BTreeMap<String, Vec<u8>>,
+    /// `bucket/key`s every request for which fails with a 500.
+    failing: HashSet<String>,
+}
+
+pub struct FakeS3 {
+    endpoint: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeS3 {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+        let shared = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, shared.clone()));
+            }
+        });
+        Self { endpoint, state }
+    }
+
+    /// A client for this endpoint. Retries are off so a failure shows up
+    /// on the first attempt.
+    pub fn client(&self) -> S3Client {
+        let conf = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "fake-s3"))
+            .endpoint_url(&self.endpoint)
+            .force_path_style(true)
+            .retry_config(RetryConfig::disabled())
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+            .build();
+        S3Client::from_conf(conf)
+    }
+
+    pub fn put(&self, bucket: &str, key: &str, body: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .objects
+            .insert(format!("{}/{}", bucket, key), body.to_vec());
+    }
+
+    /// Makes every request for `bucket/key` fail with a 500, the way a
+    /// struggling S3 would.
+    pub fn fail(&self, bucket: &str, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.failing.insert(format!("{}/{}", bucket, key));
+    }
+
+    pub fn get(&self, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        state.objects.get(&format!("{}/{}", bucket, key)).cloned()
+    }
+
+    /// Every stored object as `bucket/key`, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        self.state.lock().unwrap().objects.keys().cloned().collect()
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if stream.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+        let path = percent_decode(target.split('?').next().unwrap_or_default());
+        let path = path.trim_start_matches('/').to_string();
+
+        let mut content_length = 0;
+        let mut expect_continue = false;
+        loop {
+            let mut header = String::new();
+            if stream.read_line(&mut header).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("expect") {
+                expect_continue = value.trim().eq_ignore_ascii_case("100-continue");
+            }
+        }
+        if expect_continue
+            && stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .is_err()
+        {
+            return;
+        }
+        let mut body = vec![0; content_length];
+        if stream.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let (status, response_body) = {
+            let mut state = state.lock().unwrap();
+            match method.as_str() {
+                _ if state.failing.contains(&path) => {
+                    ("500 Internal Server Error", error_xml("InternalError"))
+                }
+                "PUT" => {
+                    state.objects.insert(path, body);
+                    ("200 OK", Vec::new())
+                }
+                "GET" | "HEAD" => match state.objects.get(&path) {
+                    Some(object) => ("200 OK", object.clone()),
+                    None => ("404 Not Found", error_xml("NoSuchKey")),
+                },
+                "DELETE" => {
+                    state.objects.remove(&path);
+                    ("204 No Content", Vec::new())
+                }
+                _ => ("501 Not Implemented", error_xml("NotImplemented")),
+            }
+        };
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nETag: \"fake\"\r\n\r\n",
+            status,
+            response_body.len()
+        );
+        let stream = stream.get_mut();
+        if stream.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        if method != "HEAD" && stream.write_all(&response_body).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn error_xml(code: &str) -> Vec<u8> {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>{}</Code></Error>",
+        code
+    )
+    .into_bytes()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,1454 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::env_flag;
+use crate::error::{FfmpegError, FfmpegErrorKind, TranscodeError};
+
+/// One rendition of the output ladder.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rung {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub video_bitrate: String,
+}
+
+impl Rung {
+    pub fn new(name: &str, width: u32, height: u32, video_bitrate: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            video_bitrate: video_bitrate.to_string(),
+        }
+    }
+
+    pub fn scale_filter(&self) -> String {
+        format!("scale={}:{}", self.width, self.height)
+    }
+
+    /// The `-vf` chain for this rung. Frame dropping goes before scaling so
+    /// the scaler only sees frames that are kept; interpolation goes after
+    /// it so the costly motion search runs on the smaller frames. Captions
+    /// are burned in last, onto the final frames.
+    pub fn video_filter(
+        &self,
+        fps: Option<f64>,
+        smooth_fps: Option<f64>,
+        subtitles: Option<&str>,
+    ) -> String {
+        let mut filters = Vec::new();
+        if let Some(fps) = fps {
+            filters.push(format!("fps={}", fps));
+        }
+        filters.push(self.scale_filter());
+        if let Some(fps) = smooth_fps {
+            filters.push(minterpolate_filter(fps));
+        }
+        if let Some(path) = subtitles {
+            filters.push(subtitles_filter(path));
+        }
+        filters.join(",")
+    }
+
+    /// This rung with its bitrate lowered to `factor` times the source's,
+    /// if it currently targets more than that. Spending more bits than the
+    /// source has only makes a bigger file.
+    pub fn capped_to_source(&self, source_bps: u64, factor: f64) -> Option<Rung> {
+        let target = parse_bitrate(&self.video_bitrate)?;
+        let cap = (source_bps as f64 * factor) as u64;
+        (target > cap).then(|| Rung {
+            video_bitrate: format!("{}k", (cap / 1000).max(1)),
+            ..self.clone()
+        })
+    }
+}
+
+/// Motion-compensated interpolation to `fps`, synthesizing in-between
+/// frames rather than repeating them.
+fn minterpolate_filter(fps: f64) -> String {
+    format!(
+        "minterpolate=fps={}:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1",
+        fps
+    )
+}
+
+/// `subtitles` filter burning in the captions at `path`. The path is
+/// escaped twice: once as the option's value, then again for the
+/// filtergraph the option sits in.
+fn subtitles_filter(path: &str) -> String {
+    let value = escape_filter_text(path, &['\\', '\'', ':']);
+    format!(
+        "subtitles=filename={}",
+        escape_filter_text(&value, &['\\', '\'', '[', ']', ',', ';'])
+    )
+}
+
+fn escape_filter_text(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Bits per second from an ffmpeg-style bitrate such as `2500k`, `5M` or
+/// `800000`.
+pub fn parse_bitrate(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1_000.0),
+        'm' | 'M' => (&value[..value.len() - 1], 1_000_000.0),
+        _ => (value, 1.0),
+    };
+    digits
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n > 0.0)
+        .map(|n| (n * multiplier) as u64)
+}
+
+/// Which tracks are re-encoded. The other track is stream-copied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackMode {
+    Both,
+    /// `VIDEO_ONLY`: re-encode video, copy audio.
+    VideoOnly,
+    /// `AUDIO_ONLY`: copy video, re-encode audio.
+    AudioOnly,
+}
+
+impl TrackMode {
+    pub fn from_env() -> Result<Self, String> {
+        match (env_flag("VIDEO_ONLY"), env_flag("AUDIO_ONLY")) {
+            (true, true) => Err("VIDEO_ONLY and AUDIO_ONLY are mutually exclusive".to_string()),
+            (true, false) => Ok(Self::VideoOnly),
+            (false, true) => Ok(Self::AudioOnly),
+            (false, false) => Ok(Self::Both),
+        }
+    }
+}
+
+/// Codecs an MP4 can carry, and so may be stream-copied into one.
+pub const MP4_VIDEO_CODECS: [&str; 5] = ["h264", "hevc", "av1", "vp9", "mpeg4"];
+pub const MP4_AUDIO_CODECS: [&str; 7] = ["aac", "mp3", "ac3", "eac3", "opus", "alac", "flac"];
+
+/// Audio codec for re-encoded audio (`AUDIO_CODEC`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Mp3,
+}
+
+impl AudioCodec {
+    /// `AUDIO_CODEC`, defaulting to AAC, which every output format carries.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("AUDIO_CODEC") {
+            Ok(v) => Self::parse(&v),
+            Err(_) => Ok(Self::Aac),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "aac" => Ok(Self::Aac),
+            "opus" => Ok(Self::Opus),
+            "mp3" => Ok(Self::Mp3),
+            _ => Err(format!("Unknown AUDIO_CODEC: {}", value)),
+        }
+    }
+
+    /// The ffmpeg encoder name.
+    pub fn encoder(self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::Opus => "libopus",
+            Self::Mp3 => "libmp3lame",
+        }
+    }
+}
+
+/// Sample rates libopus accepts.
+const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12000, 16000, 24000, 48000];
+
+/// Why `codec` can't encode at `sample_rate`, if it can't.
+fn sample_rate_incompatibility(codec: AudioCodec, sample_rate: Option<u32>) -> Option<String> {
+    let rate = sample_rate.filter(|rate| !OPUS_SAMPLE_RATES.contains(rate))?;
+    (codec == AudioCodec::Opus).then(|| {
+        format!(
+            "AUDIO_SAMPLE_RATE {} is not supported by opus (expected one of {:?})",
+            rate, OPUS_SAMPLE_RATES
+        )
+    })
+}
+
+/// Why audio from `encoder` can't be packaged as `format`, if it can't.
+/// DASH segments are fragmented MP4, where MP3 isn't supported.
+pub fn audio_codec_incompatibility(format: OutputFormat, encoder: &str) -> Option<String> {
+    let is_mp3 = matches!(encoder, "libmp3lame" | "mp3");
+    (format == OutputFormat::Dash && is_mp3).then(|| {
+        format!(
+            "audio codec {} is not supported with OUTPUT_FORMAT=dash",
+            encoder
+        )
+    })
+}
+
+/// How the ladder is packaged (`OUTPUT_FORMAT`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One progressive MP4 per rung.
+    Mp4,
+    /// One MPEG-DASH presentation covering every rung.
+    Dash,
+}
+
+impl OutputFormat {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("OUTPUT_FORMAT") {
+            Ok(v) => match v.to_ascii_lowercase().as_str() {
+                "mp4" => Ok(Self::Mp4),
+                "dash" => Ok(Self::Dash),
+                _ => Err(format!("Unknown OUTPUT_FORMAT: {}", v)),
+            },
+            Err(_) => Ok(Self::Mp4),
+        }
+    }
+}
+
+/// How each MP4 rendition is laid out (`MP4_MODE`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mp4Mode {
+    /// Index moved to the front for progressive download.
+    Faststart,
+    /// Fragmented MP4, playable while it is still being written.
+    Fragmented,
+    /// ffmpeg's default layout, index at the end.
+    None,
+}
+
+impl Mp4Mode {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("MP4_MODE") {
+            Ok(v) => Self::parse(&v),
+            Err(_) => Ok(Self::Faststart),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "faststart" => Ok(Self::Faststart),
+            "fragmented" => Ok(Self::Fragmented),
+            "none" => Ok(Self::None),
+            _ => Err(format!("Unknown MP4_MODE: {}", value)),
+        }
+    }
+
+    /// The `-movflags` value for this layout, if any.
+    pub fn movflags(self) -> Option<&'static str> {
+        match self {
+            Self::Faststart => Some("+faststart"),
+            Self::Fragmented => Some("+frag_keyframe+empty_moov"),
+            Self::None => None,
+        }
+    }
+}
+
+/// What to do with rungs taller than the source (`UPSCALE_POLICY`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpscalePolicy {
+    /// Don't produce them. The smallest rung is still produced if every
+    /// rung would be an upscale, so a job never ends with no output.
+    Skip,
+    /// Produce them like any other rung.
+    Allow,
+    /// Produce them, but tag the output `upscale=true`.
+    Flag,
+}
+
+impl UpscalePolicy {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("UPSCALE_POLICY") {
+            Ok(v) => match v.to_ascii_lowercase().as_str() {
+                "skip" => Ok(Self::Skip),
+                "allow" => Ok(Self::Allow),
+                "flag" => Ok(Self::Flag),
+                _ => Err(format!("Unknown UPSCALE_POLICY: {}", v)),
+            },
+            Err(_) => Ok(Self::Skip),
+        }
+    }
+
+    /// The rungs to produce for a source `source_height` pixels tall, each
+    /// paired with whether it is an upscale.
+    pub fn plan(self, ladder: &[Rung], source_height: u32) -> Vec<(&Rung, bool)> {
+        let planned: Vec<(&Rung, bool)> = ladder
+            .iter()
+            .map(|rung| (rung, rung.height > source_height))
+            .filter(|(_, upscale)| self != UpscalePolicy::Skip || !upscale)
+            .collect();
+        if planned.is_empty() {
+            return ladder
+                .iter()
+                .min_by_key(|rung| rung.height)
+                .map(|rung| vec![(rung, true)])
+                .unwrap_or_default();
+        }
+        planned
+    }
+}
+
+/// The `PREVIEW_FIRST` proxy is 360 lines tall; the width follows the
+/// source aspect ratio.
+const PROXY_SCALE: &str = "scale=-2:360";
+
+pub fn default_ladder() -> Vec<Rung> {
+    vec![
+        Rung::new("480p", 854, 480, "1000k"),
+        Rung::new("720p", 1280, 720, "2500k"),
+        Rung::new("1080p", 1920, 1080, "5000k"),
+    ]
+}
+
+/// Standard sizes a `LADDER_MODE=native` source is rounded to, tallest last.
+const NATIVE_SIZES: [(u32, u32, &str); 5] = [
+    (426, 240, "400k"),
+    (640, 360, "700k"),
+    (854, 480, "1000k"),
+    (1280, 720, "2500k"),
+    (1920, 1080, "5000k"),
+];
+
+/// Whether to encode the whole ladder or one rung sized to the source
+/// (`LADDER_MODE`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LadderMode {
+    Full,
+    Native,
+}
+
+impl LadderMode {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("LADDER_MODE") {
+            Ok(v) => match v.to_ascii_lowercase().as_str() {
+                "full" => Ok(Self::Full),
+                "native" => Ok(Self::Native),
+                _ => Err(format!("Unknown LADDER_MODE: {}", v)),
+            },
+            Err(_) => Ok(Self::Full),
+        }
+    }
+}
+
+/// The single rung for a source `source_height` pixels tall: the nearest
+/// standard size, capped at 1080p. Ties round down.
+pub fn native_rung(source_height: u32) -> Rung {
+    let (width, height, bitrate) = NATIVE_SIZES
+        .into_iter()
+        .min_by_key(|(_, height, _)| height.abs_diff(source_height))
+        .unwrap_or(NATIVE_SIZES[NATIVE_SIZES.len() - 1]);
+    Rung::new(&format!("{}p", height), width, height, bitrate)
+}
+
+/// Color tagging applied to every rendition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorProfile {
+    /// Leave color metadata as ffmpeg derives it from the source.
+    Passthrough,
+    /// Tag output as BT.709 SDR, which is what most players assume.
+    Sdr,
+}
+
+impl ColorProfile {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "passthrough" => Ok(Self::Passthrough),
+            "sdr" => Ok(Self::Sdr),
+            other => Err(format!("Unknown COLOR_PROFILE: {}", other)),
+        }
+    }
+}
+
+/// Accepted range for `SMOOTH_FPS`.
+const MIN_SMOOTH_FPS: f64 = 12.0;
+const MAX_SMOOTH_FPS: f64 = 120.0;
+
+/// Sample rates accepted for `AUDIO_SAMPLE_RATE`; all are valid for AAC.
+const AUDIO_SAMPLE_RATES: [u32; 9] = [8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000, 96000];
+
+/// Channel counts accepted for `AUDIO_CHANNELS`, with their layout names.
+const AUDIO_LAYOUTS: [(u32, &str); 3] = [(1, "mono"), (2, "stereo"), (6, "5.1")];
+
+/// Encoder options shared by every rung of the ladder.
+#[derive(Clone, Debug, Serialize)]
+pub struct EncodeSettings {
+    pub video_codec: String,
+    pub preset: String,
+    pub crf: u32,
+    pub audio_codec: String,
+    pub audio_bitrate: String,
+    pub pix_fmt: String,
+    pub color_profile: ColorProfile,
+    pub colorspace: Option<String>,
+    pub color_primaries: Option<String>,
+    pub color_trc: Option<String>,
+    pub fps_cap: Option<f64>,
+    /// Interpolate to this frame rate (`SMOOTH_FPS`). Several times slower
+    /// than a plain encode.
+    pub smooth_fps: Option<f64>,
+    /// Target segment length for segmented (adaptive streaming) output.
+    pub segment_secs: u32,
+    pub track_mode: TrackMode,
+    /// EBU R128 loudness normalization of the audio track.
+    pub normalize_audio: bool,
+    /// `-threads` for encoding; `None` lets ffmpeg pick.
+    pub threads: Option<u32>,
+    /// `-ar` for the audio track; `None` keeps the source rate.
+    pub audio_sample_rate: Option<u32>,
+    /// `-ac` for the audio track; `None` keeps the source layout.
+    pub audio_channels: Option<u32>,
+    pub mp4_mode: Mp4Mode,
+    /// Captions file burned into the video. Set per job when
+    /// `BURN_SUBTITLES` finds a sidecar, never from the environment.
+    pub subtitles: Option<String>,
+}
+
+impl EncodeSettings {
+    pub fn from_env() -> Result<Self, String> {
+        let color_profile = match env::var("COLOR_PROFILE") {
+            Ok(v) => ColorProfile::parse(&v)?,
+            Err(_) => ColorProfile::Passthrough,
+        };
+        let track_mode = TrackMode::from_env()?;
+        let normalize_audio = env_flag("NORMALIZE_AUDIO");
+        if normalize_audio && track_mode == TrackMode::VideoOnly {
+            return Err("NORMALIZE_AUDIO needs the audio re-encoded, not VIDEO_ONLY".to_string());
+        }
+        let smooth_fps = match env::var("SMOOTH_FPS") {
+            Ok(v) => Some(
+                v.parse::<f64>()
+                    .ok()
+                    .filter(|fps| (MIN_SMOOTH_FPS..=MAX_SMOOTH_FPS).contains(fps))
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid SMOOTH_FPS: {} (expected {}-{})",
+                            v, MIN_SMOOTH_FPS, MAX_SMOOTH_FPS
+                        )
+                    })?,
+            ),
+            Err(_) => None,
+        };
+        if smooth_fps.is_some() && env::var("FPS_CAP").is_ok() {
+            return Err("SMOOTH_FPS and FPS_CAP cannot be combined".to_string());
+        }
+        if smooth_fps.is_some() && track_mode == TrackMode::AudioOnly {
+            return Err("SMOOTH_FPS needs the video re-encoded, not AUDIO_ONLY".to_string());
+        }
+        let audio_codec = AudioCodec::from_env()?;
+        if audio_codec != AudioCodec::Aac && track_mode == TrackMode::VideoOnly {
+            return Err("AUDIO_CODEC needs the audio re-encoded, not VIDEO_ONLY".to_string());
+        }
+        let audio_sample_rate = env_choice("AUDIO_SAMPLE_RATE", &AUDIO_SAMPLE_RATES)?;
+        if let Some(reason) = sample_rate_incompatibility(audio_codec, audio_sample_rate) {
+            return Err(reason);
+        }
+        let audio_channels = env_choice("AUDIO_CHANNELS", &AUDIO_LAYOUTS.map(|(n, _)| n))?;
+        if (audio_sample_rate.is_some() || audio_channels.is_some())
+            && track_mode == TrackMode::VideoOnly
+        {
+            return Err(
+                "AUDIO_SAMPLE_RATE/AUDIO_CHANNELS need the audio re-encoded, not VIDEO_ONLY"
+                    .to_string(),
+            );
+        }
+        Ok(Self {
+            video_codec: "libx264".to_string(),
+            preset: "medium".to_string(),
+            crf: 23,
+            audio_codec: audio_codec.encoder().to_string(),
+            audio_bitrate: "128k".to_string(),
+            pix_fmt: env::var("PIX_FMT").unwrap_or_else(|_| "yuv420p".to_string()),
+            color_profile,
+            colorspace: env::var("COLORSPACE").ok(),
+            color_primaries: env::var("COLOR_PRIMARIES").ok(),
+            color_trc: env::var("COLOR_TRC").ok(),
+            fps_cap: match env::var("FPS_CAP") {
+                Ok(v) => Some(
+                    v.parse::<f64>()
+                        .ok()
+                        .filter(|fps| fps.is_finite() && *fps > 0.0)
+                        .ok_or_else(|| format!("Invalid FPS_CAP: {}", v))?,
+                ),
+                Err(_) => None,
+            },
+            smooth_fps,
+            segment_secs: match env::var("SEGMENT_SECS") {
+                Ok(v) => v
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("Invalid SEGMENT_SECS: {}", v))?,
+                Err(_) => 4,
+            },
+            track_mode,
+            normalize_audio,
+            threads: match env::var("FFMPEG_THREADS") {
+                Ok(v) => Some(
+                    v.parse::<u32>()
+                        .ok()
+                        .filter(|threads| *threads > 0)
+                        .ok_or_else(|| format!("Invalid FFMPEG_THREADS: {}", v))?,
+                ),
+                Err(_) => None,
+            },
+            audio_sample_rate,
+            audio_channels,
+            mp4_mode: Mp4Mode::from_env()?,
+            subtitles: None,
+        })
+    }
+
+    /// The frame rate to force, if the source runs faster than `FPS_CAP`.
+    /// Sources at or below the cap, or of unknown rate, are left alone.
+    pub fn fps_limit(&self, source_fps: Option<f64>) -> Option<f64> {
+        match (self.fps_cap, source_fps) {
+            (Some(cap), Some(source)) if source > cap => Some(cap),
+            _ => None,
+        }
+    }
+
+    /// Video encoding flags for one rung, or a stream copy under `AUDIO_ONLY`.
+    pub fn video_args(&self, rung: &Rung, source_fps: Option<f64>) -> Vec<String> {
+        if self.track_mode == TrackMode::AudioOnly {
+            return vec!["-c:v".to_string(), "copy".to_string()];
+        }
+        let mut args = vec![
+            "-vf".to_string(),
+            rung.video_filter(
+                self.fps_limit(source_fps),
+                self.smooth_fps,
+                self.subtitles.as_deref(),
+            ),
+            "-c:v".to_string(),
+            self.video_codec.clone(),
+            "-b:v".to_string(),
+            rung.video_bitrate.clone(),
+            "-preset".to_string(),
+            self.preset.clone(),
+            "-crf".to_string(),
+            self.crf.to_string(),
+        ];
+        args.extend(self.color_args());
+        args
+    }
+
+    /// `-movflags` for an MP4 rendition, per `MP4_MODE`.
+    pub fn movflags_args(&self) -> Vec<String> {
+        match self.mp4_mode.movflags() {
+            Some(flags) => vec!["-movflags".to_string(), flags.to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    /// `-threads N` when `FFMPEG_THREADS` pins the count, else nothing.
+    pub fn thread_args(&self) -> Vec<String> {
+        match self.threads {
+            Some(threads) => vec!["-threads".to_string(), threads.to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Audio encoding flags, or a stream copy under `VIDEO_ONLY`.
+    pub fn audio_args(&self) -> Vec<String> {
+        if self.track_mode == TrackMode::VideoOnly {
+            return vec!["-c:a".to_string(), "copy".to_string()];
+        }
+        // The layout is fixed ahead of loudnorm so it measures what is
+        // actually played. Downmixing goes through swresample's standard
+        // matrix, which folds in centre and surrounds at -3 dB.
+        let mut filters = Vec::new();
+        if let Some(layout) = self.audio_channels.and_then(channel_layout) {
+            filters.push(format!("aformat=channel_layouts={}", layout));
+        }
+        if self.normalize_audio {
+            filters.push("loudnorm".to_string());
+        }
+        let mut args = Vec::new();
+        if !filters.is_empty() {
+            args.extend(["-af".to_string(), filters.join(",")]);
+        }
+        args.extend([
+            "-c:a".to_string(),
+            self.audio_codec.clone(),
+            "-b:a".to_string(),
+            self.audio_bitrate.clone(),
+        ]);
+        // loudnorm resamples to 192 kHz, so -ar matters even when the source
+        // is already at the target rate.
+        if let Some(rate) = self.audio_sample_rate {
+            args.extend(["-ar".to_string(), rate.to_string()]);
+        }
+        if let Some(channels) = self.audio_channels {
+            args.extend(["-ac".to_string(), channels.to_string()]);
+        }
+        args
+    }
+
+    /// Why the source can't be stream-copied into an MP4 under the current
+    /// track mode, if it can't. `None` codecs mean the track is absent.
+    pub fn copy_incompatibility(
+        &self,
+        video_codec: Option<&str>,
+        audio_codec: Option<&str>,
+    ) -> Option<String> {
+        let (track, codec, allowed) = match self.track_mode {
+            TrackMode::Both => return None,
+            TrackMode::AudioOnly => ("video", video_codec, &MP4_VIDEO_CODECS[..]),
+            TrackMode::VideoOnly => ("audio", audio_codec, &MP4_AUDIO_CODECS[..]),
+        };
+        codec
+            .filter(|codec| !allowed.contains(codec))
+            .map(|codec| format!("source {} codec {} cannot be copied into MP4", track, codec))
+    }
+
+    /// Keyframes on every segment boundary, so every rendition can switch
+    /// at the same points. Time-based, so it holds for any frame rate.
+    /// Used by every segmented output format.
+    pub fn keyframe_args(&self) -> Vec<String> {
+        vec![
+            "-force_key_frames".to_string(),
+            format!("expr:gte(t,n_forced*{})", self.segment_secs),
+            "-sc_threshold".to_string(),
+            "0".to_string(),
+        ]
+    }
+
+    /// Pixel format and color flags. Explicit `COLORSPACE`/`COLOR_PRIMARIES`/
+    /// `COLOR_TRC` values win over whatever the profile would set.
+    pub fn color_args(&self) -> Vec<String> {
+        let (mut colorspace, mut primaries, mut trc) = match self.color_profile {
+            ColorProfile::Passthrough => (None, None, None),
+            ColorProfile::Sdr => (Some("bt709"), Some("bt709"), Some("bt709")),
+        };
+        if let Some(v) = &self.colorspace {
+            colorspace = Some(v);
+        }
+        if let Some(v) = &self.color_primaries {
+            primaries = Some(v);
+        }
+        if let Some(v) = &self.color_trc {
+            trc = Some(v);
+        }
+
+        let mut args = vec!["-pix_fmt".to_string(), self.pix_fmt.clone()];
+        for (flag, value) in [
+            ("-colorspace", colorspace),
+            ("-color_primaries", primaries),
+            ("-color_trc", trc),
+        ] {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value.to_string());
+            }
+        }
+        args
+    }
+}
+
+pub fn transcode_video(
+    input: &str,
+    output: &str,
+    rung: &Rung,
+    settings: &EncodeSettings,
+    source_fps: Option<f64>,
+    log_path: Option<&str>,
+    on_progress: Option<&dyn Fn(f64)>,
+) -> Result<(), TranscodeError> {
+    let mut args: Vec<String> = vec!["-i".to_string(), input.to_string()];
+    args.extend(settings.video_args(rung, source_fps));
+    args.extend(settings.audio_args());
+    args.extend(settings.thread_args());
+    args.extend(settings.movflags_args());
+    args.extend(["-y".to_string(), output.to_string()]);
+
+    run_ffmpeg(&args, log_path, on_progress)
+}
+
+/// Encodes every rung in one pass into an MPEG-DASH presentation in
+/// `output_dir`: `manifest.mpd` plus per-rung init and media segments.
+pub fn transcode_dash(
+    input: &str,
+    output_dir: &str,
+    rungs: &[&Rung],
+    settings: &EncodeSettings,
+    source_fps: Option<f64>,
+    log_path: Option<&str>,
+    on_progress: Option<&dyn Fn(f64)>,
+) -> Result<(), TranscodeError> {
+    let mut args: Vec<String> = vec!["-i".to_string(), input.to_string()];
+    for _ in rungs {
+        args.extend(["-map".to_string(), "0:v:0".to_string()]);
+    }
+    args.extend(["-map".to_string(), "0:a:0?".to_string()]);
+    let fps = settings.fps_limit(source_fps);
+    for (i, rung) in rungs.iter().enumerate() {
+        args.extend([
+            format!("-filter:v:{}", i),
+            rung.video_filter(fps, settings.smooth_fps, settings.subtitles.as_deref()),
+            format!("-b:v:{}", i),
+            rung.video_bitrate.clone(),
+        ]);
+    }
+    args.extend([
+        "-c:v".to_string(),
+        settings.video_codec.clone(),
+        "-preset".to_string(),
+        settings.preset.clone(),
+    ]);
+    args.extend(settings.color_args());
+    args.extend(settings.keyframe_args());
+    args.extend(settings.thread_args());
+    args.extend(settings.audio_args());
+    args.extend([
+        "-f".to_string(),
+        "dash".to_string(),
+        "-seg_duration".to_string(),
+        settings.segment_secs.to_string(),
+        "-use_template".to_string(),
+        "1".to_string(),
+        "-use_timeline".to_string(),
+        "1".to_string(),
+        "-init_seg_name".to_string(),
+        "init-$RepresentationID$.m4s".to_string(),
+        "-media_seg_name".to_string(),
+        "chunk-$RepresentationID$-$Number%05d$.m4s".to_string(),
+        "-adaptation_sets".to_string(),
+        "id=0,streams=v id=1,streams=a".to_string(),
+        "-y".to_string(),
+        format!("{}/manifest.mpd", output_dir),
+    ]);
+    run_ffmpeg(&args, log_path, on_progress)
+}
+
+/// Fast, low-quality 360p proxy for reviewers, optionally cut to the first
+/// `max_secs` seconds. Speed matters more than size here.
+pub fn transcode_proxy(
+    input: &str,
+    output: &str,
+    settings: &EncodeSettings,
+    max_secs: Option<f64>,
+) -> Result<(), TranscodeError> {
+    let mut args: Vec<String> = vec!["-i".to_string(), input.to_string()];
+    if let Some(max_secs) = max_secs {
+        args.extend(["-t".to_string(), max_secs.to_string()]);
+    }
+    args.extend([
+        "-vf".to_string(),
+        PROXY_SCALE.to_string(),
+        "-c:v".to_string(),
+        settings.video_codec.clone(),
+        "-preset".to_string(),
+        "veryfast".to_string(),
+        "-crf".to_string(),
+        "28".to_string(),
+        "-pix_fmt".to_string(),
+        settings.pix_fmt.clone(),
+        "-c:a".to_string(),
+        settings.audio_codec.clone(),
+        "-b:a".to_string(),
+        "96k".to_string(),
+    ]);
+    args.extend(settings.thread_args());
+    args.extend([
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        "-y".to_string(),
+        output.to_string(),
+    ]);
+    run_ffmpeg(&args, None, None)
+}
+
+/// A concat demuxer list naming `parts` in order, quoted with any `'`
+/// escaped the way the demuxer expects.
+pub fn concat_list(parts: &[String]) -> String {
+    parts
+        .iter()
+        .map(|part| format!("file '{}'\n", part.replace('\'', "'\\''")))
+        .collect()
+}
+
+/// Joins the files named in `list_path` into `output` without re-encoding.
+/// The parts must share codecs, which the caller checks.
+pub fn concat_parts(list_path: &str, output: &str) -> Result<(), TranscodeError> {
+    let args = [
+        "-f", "concat", "-safe", "0", "-i", list_path, "-c", "copy", "-y", output,
+    ]
+    .map(str::to_string);
+    run_ffmpeg(&args, None, None)
+}
+
+/// ffmpeg's `-loglevel` names, quietest first.
+pub const LOGLEVELS: [&str; 9] = [
+    "quiet", "panic", "fatal", "error", "warning", "info", "verbose", "debug", "trace",
+];
+
+/// Index of `info` in [`LOGLEVELS`].
+const INFO_LEVEL: usize = 5;
+
+/// `FFMPEG_LOGLEVEL`, defaulting to `warning`.
+pub fn loglevel_from_env() -> Result<String, String> {
+    parse_loglevel(env::var("FFMPEG_LOGLEVEL").ok().as_deref())
+}
+
+fn parse_loglevel(value: Option<&str>) -> Result<String, String> {
+    match value {
+        Some(v) if LOGLEVELS.contains(&v.to_ascii_lowercase().as_str()) => {
+            Ok(v.to_ascii_lowercase())
+        }
+        Some(v) => Err(format!(
+            "Unknown FFMPEG_LOGLEVEL: {} (expected one of {})",
+            v,
+            LOGLEVELS.join(", ")
+        )),
+        None => Ok("warning".to_string()),
+    }
+}
+
+/// Flags placed before every ffmpeg invocation. Below `info` the banner is
+/// hidden too; `-progress` output is unaffected by the level.
+fn loglevel_args(level: &str) -> Vec<String> {
+    let mut args = vec!["-loglevel".to_string(), level.to_string()];
+    let below_info = LOGLEVELS.iter().position(|l| *l == level) < Some(INFO_LEVEL);
+    if below_info {
+        args.push("-hide_banner".to_string());
+    }
+    args
+}
+
+/// Runs ffmpeg, classifying any failure from its stderr. With a log path,
+/// stderr goes to that file (headed by the exact invocation, so a rendition
+/// can be traced back to how it was produced); otherwise, or if the file
+/// can't be created, it is captured in memory. `on_progress` receives the
+/// output position in seconds as ffmpeg reports it.
+pub fn run_ffmpeg(
+    args: &[String],
+    log_path: Option<&str>,
+    on_progress: Option<&dyn Fn(f64)>,
+) -> Result<(), TranscodeError> {
+    // Already validated by JobConfig::from_env.
+    let level = loglevel_from_env().unwrap_or_else(|_| "warning".to_string());
+    let mut command = Command::new("ffmpeg");
+    command.args(loglevel_args(&level));
+    if on_progress.is_some() {
+        command
+            .args(["-progress", "pipe:1", "-nostats"])
+            .stdout(Stdio::piped());
+    } else {
+        command.stdout(Stdio::null());
+    }
+    command.args(args);
+
+    let log_path = log_path.and_then(|log_path| {
+        let log_file = open_log(log_path, args)?;
+        command.stderr(Stdio::from(log_file));
+        Some(log_path)
+    });
+    if log_path.is_none() {
+        command.stderr(Stdio::piped());
+    }
+
+    let mut child = command.spawn()?;
+    // Drain stderr on its own thread so ffmpeg never blocks on a full pipe
+    // while progress is being read from stdout.
+    let stderr_reader = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            text
+        })
+    });
+    if let (Some(on_progress), Some(stdout)) = (on_progress, child.stdout.take()) {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(secs) = parse_progress_line(&line) {
+                on_progress(secs);
+            }
+        }
+    }
+    let status = child.wait()?;
+
+    let stderr = match (stderr_reader, log_path) {
+        (Some(reader), _) => reader.join().unwrap_or_default(),
+        (None, Some(log_path)) if !status.success() => {
+            std::fs::read_to_string(log_path).unwrap_or_default()
+        }
+        _ => String::new(),
+    };
+
+    if !status.success() {
+        let error = FfmpegError {
+            kind: FfmpegErrorKind::classify(&stderr, status.code()),
+            code: status.code(),
+            stderr: tail_lines(&stderr, STDERR_TAIL_LINES),
+        };
+        println!(
+            "{}",
+            json!({
+                "level": "error",
+                "event": "ffmpeg_failed",
+                "category": error.kind,
+                "code": error.code,
+                "stderr": error.stderr,
+            })
+        );
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+/// Creates the log for one run, headed by its invocation. A failure here is
+/// reported on its own and the run goes ahead without the file, rather than
+/// being mistaken for ffmpeg failing.
+fn open_log(log_path: &str, args: &[String]) -> Option<File> {
+    let open = || -> std::io::Result<File> {
+        let mut log_file = File::create(log_path)?;
+        writeln!(log_file, "$ ffmpeg {}", args.join(" "))?;
+        writeln!(log_file)?;
+        Ok(log_file)
+    };
+    match open() {
+        Ok(log_file) => Some(log_file),
+        Err(e) => {
+            println!(
+                "{}",
+                json!({
+                    "level": "warning",
+                    "event": "ffmpeg_log_unavailable",
+                    "path": log_path,
+                    "error": e.to_string(),
+                })
+            );
+            let _ = std::fs::remove_file(log_path);
+            None
+        }
+    }
+}
+
+/// How much of a captured ffmpeg log is kept on a failure.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Output position from a `-progress` line such as `out_time_us=1500000`.
+fn parse_progress_line(line: &str) -> Option<f64> {
+    let micros = line
+        .strip_prefix("out_time_us=")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(micros as f64 / 1_000_000.0)
+}
+
+fn tail_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+fn channel_layout(channels: u32) -> Option<&'static str> {
+    AUDIO_LAYOUTS
+        .iter()
+        .find(|(n, _)| *n == channels)
+        .map(|(_, layout)| *layout)
+}
+
+/// Optional integer env var that must be one of `allowed`.
+fn env_choice(name: &str, allowed: &[u32]) -> Result<Option<u32>, String> {
+    match env::var(name) {
+        Ok(v) => v
+            .parse::<u32>()
+            .ok()
+            .filter(|n| allowed.contains(n))
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}: {} (expected one of {:?})", name, v, allowed)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// What [`EncodeSettings::from_env`] returns with nothing set.
+    pub(crate) fn settings() -> EncodeSettings {
+        EncodeSettings {
+            video_codec: "libx264".to_string(),
+            preset: "medium".to_string(),
+            crf: 23,
+            audio_codec: "aac".to_string(),
+            audio_bitrate: "128k".to_string(),
+            pix_fmt: "yuv420p".to_string(),
+            color_profile: ColorProfile::Passthrough,
+            colorspace: None,
+            color_primaries: None,
+            color_trc: None,
+            fps_cap: None,
+            smooth_fps: None,
+            segment_secs: 4,
+            track_mode: TrackMode::Both,
+            normalize_audio: false,
+            threads: None,
+            audio_sample_rate: None,
+            audio_channels: None,
+            mp4_mode: Mp4Mode::Faststart,
+            subtitles: None,
+        }
+    }
+
+    #[test]
+    fn passthrough_only_sets_the_pixel_format() {
+        assert_eq!(settings().color_args(), ["-pix_fmt", "yuv420p"]);
+    }
+
+    #[test]
+    fn sdr_profile_tags_bt709() {
+        let sdr = EncodeSettings {
+            color_profile: ColorProfile::Sdr,
+            pix_fmt: "yuv420p10le".to_string(),
+            ..settings()
+        };
+        assert_eq!(
+            sdr.color_args(),
+            [
+                "-pix_fmt",
+                "yuv420p10le",
+                "-colorspace",
+                "bt709",
+                "-color_primaries",
+                "bt709",
+                "-color_trc",
+                "bt709",
+            ]
+        );
+    }
+
+    #[test]
+    fn explicit_color_flags_override_the_profile() {
+        let hdr = EncodeSettings {
+            color_profile: ColorProfile::Sdr,
+            color_trc: Some("smpte2084".to_string()),
+            ..settings()
+        };
+        let args = hdr.color_args();
+        assert_eq!(args[args.len() - 2..], ["-color_trc", "smpte2084"]);
+        assert!(args.contains(&"-colorspace".to_string()));
+
+        let tagged = EncodeSettings {
+            colorspace: Some("bt2020nc".to_string()),
+            ..settings()
+        };
+        assert_eq!(
+            tagged.color_args(),
+            ["-pix_fmt", "yuv420p", "-colorspace", "bt2020nc"]
+        );
+    }
+
+    #[test]
+    fn color_profile_parses_case_insensitively() {
+        assert_eq!(ColorProfile::parse("SDR"), Ok(ColorProfile::Sdr));
+        assert_eq!(
+            ColorProfile::parse("passthrough"),
+            Ok(ColorProfile::Passthrough)
+        );
+        assert!(ColorProfile::parse("hdr").is_err());
+    }
+
+    #[test]
+    fn fps_filter_only_when_the_source_exceeds_the_cap() {
+        let capped = EncodeSettings {
+            fps_cap: Some(30.0),
+            ..settings()
+        };
+        let rung = Rung::new("720p", 1280, 720, "2500k");
+        assert_eq!(capped.fps_limit(Some(59.94)), Some(30.0));
+        assert_eq!(
+            capped.video_args(&rung, Some(59.94))[1],
+            "fps=30,scale=1280:720"
+        );
+        for source_fps in [Some(30.0), Some(24.0), None] {
+            assert_eq!(capped.fps_limit(source_fps), None);
+            assert_eq!(capped.video_args(&rung, source_fps)[1], "scale=1280:720");
+        }
+        assert_eq!(settings().fps_limit(Some(120.0)), None);
+    }
+
+    fn planned(plan: &[(&Rung, bool)]) -> Vec<(String, bool)> {
+        plan.iter()
+            .map(|(rung, upscale)| (rung.name.clone(), *upscale))
+            .collect()
+    }
+
+    #[test]
+    fn skip_drops_rungs_taller_than_the_source() {
+        let ladder = default_ladder();
+        assert_eq!(
+            planned(&UpscalePolicy::Skip.plan(&ladder, 720)),
+            [("480p".to_string(), false), ("720p".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn skip_keeps_the_smallest_rung_when_all_are_upscales() {
+        let ladder = default_ladder();
+        assert_eq!(
+            planned(&UpscalePolicy::Skip.plan(&ladder, 360)),
+            [("480p".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn allow_and_flag_keep_every_rung_and_mark_upscales() {
+        let ladder = default_ladder();
+        for policy in [UpscalePolicy::Allow, UpscalePolicy::Flag] {
+            assert_eq!(
+                planned(&policy.plan(&ladder, 720)),
+                [
+                    ("480p".to_string(), false),
+                    ("720p".to_string(), false),
+                    ("1080p".to_string(), true),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn track_modes_copy_the_other_track() {
+        let rung = Rung::new("720p", 1280, 720, "2500k");
+        let both = settings();
+        assert_eq!(
+            both.video_args(&rung, None)[..4],
+            ["-vf", "scale=1280:720", "-c:v", "libx264"]
+        );
+        assert_eq!(both.audio_args(), ["-c:a", "aac", "-b:a", "128k"]);
+
+        let video_only = EncodeSettings {
+            track_mode: TrackMode::VideoOnly,
+            ..settings()
+        };
+        assert_eq!(
+            video_only.video_args(&rung, None)[2..4],
+            ["-c:v", "libx264"]
+        );
+        assert_eq!(video_only.audio_args(), ["-c:a", "copy"]);
+
+        let audio_only = EncodeSettings {
+            track_mode: TrackMode::AudioOnly,
+            ..settings()
+        };
+        assert_eq!(audio_only.video_args(&rung, None), ["-c:v", "copy"]);
+        assert_eq!(audio_only.audio_args(), ["-c:a", "aac", "-b:a", "128k"]);
+    }
+
+    #[test]
+    fn copied_tracks_must_fit_in_mp4() {
+        let video_only = EncodeSettings {
+            track_mode: TrackMode::VideoOnly,
+            ..settings()
+        };
+        assert_eq!(
+            video_only.copy_incompatibility(Some("prores"), Some("aac")),
+            None
+        );
+        assert_eq!(video_only.copy_incompatibility(Some("h264"), None), None);
+        assert!(
+            video_only
+                .copy_incompatibility(Some("h264"), Some("pcm_s16le"))
+                .is_some()
+        );
+
+        let audio_only = EncodeSettings {
+            track_mode: TrackMode::AudioOnly,
+            ..settings()
+        };
+        assert_eq!(
+            audio_only.copy_incompatibility(Some("hevc"), Some("pcm_s16le")),
+            None
+        );
+        assert!(
+            audio_only
+                .copy_incompatibility(Some("prores"), Some("aac"))
+                .is_some()
+        );
+
+        assert_eq!(
+            settings().copy_incompatibility(Some("prores"), Some("pcm_s16le")),
+            None
+        );
+    }
+
+    #[test]
+    fn loglevel_defaults_to_warning_and_is_validated() {
+        assert_eq!(parse_loglevel(None).unwrap(), "warning");
+        assert_eq!(parse_loglevel(Some("DEBUG")).unwrap(), "debug");
+        assert!(parse_loglevel(Some("loud")).is_err());
+    }
+
+    #[test]
+    fn banner_is_hidden_below_info() {
+        assert_eq!(
+            loglevel_args("error"),
+            ["-loglevel", "error", "-hide_banner"]
+        );
+        assert_eq!(
+            loglevel_args("warning"),
+            ["-loglevel", "warning", "-hide_banner"]
+        );
+        assert_eq!(loglevel_args("info"), ["-loglevel", "info"]);
+        assert_eq!(loglevel_args("trace"), ["-loglevel", "trace"]);
+    }
+
+    #[test]
+    fn threads_flag_only_when_set() {
+        assert!(settings().thread_args().is_empty());
+        let pinned = EncodeSettings {
+            threads: Some(4),
+            ..settings()
+        };
+        assert_eq!(pinned.thread_args(), ["-threads", "4"]);
+    }
+
+    #[test]
+    fn native_rung_rounds_to_the_nearest_standard_size() {
+        for (source_height, expected) in [
+            (144, "240p"),
+            (240, "240p"),
+            (300, "240p"),
+            (302, "360p"),
+            (360, "360p"),
+            (404, "360p"),
+            (576, "480p"),
+            (720, "720p"),
+            (1080, "1080p"),
+            (2160, "1080p"),
+        ] {
+            assert_eq!(
+                native_rung(source_height).name,
+                expected,
+                "{}",
+                source_height
+            );
+        }
+        let rung = native_rung(1088);
+        assert_eq!((rung.width, rung.height), (1920, 1080));
+    }
+
+    #[test]
+    fn audio_rate_and_layout_follow_the_codec() {
+        let downmix = EncodeSettings {
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            ..settings()
+        };
+        assert_eq!(
+            downmix.audio_args(),
+            [
+                "-af",
+                "aformat=channel_layouts=stereo",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "128k",
+                "-ar",
+                "48000",
+                "-ac",
+                "2",
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_is_fixed_ahead_of_loudnorm() {
+        let normalized = EncodeSettings {
+            audio_channels: Some(6),
+            normalize_audio: true,
+            ..settings()
+        };
+        assert_eq!(
+            normalized.audio_args()[..2],
+            ["-af", "aformat=channel_layouts=5.1,loudnorm"]
+        );
+        assert_eq!(channel_layout(1), Some("mono"));
+        assert_eq!(channel_layout(4), None);
+    }
+
+    #[test]
+    fn rungs_are_capped_only_above_the_source_bitrate() {
+        let rung = Rung::new("1080p", 1920, 1080, "5000k");
+        for (source_bps, expected) in [
+            (8_000_000, None),
+            (5_000_000, None),
+            (4_200_000, None),
+            (4_000_000, Some("4800k")),
+            (3_000_000, Some("3600k")),
+            (1_000_000, Some("1200k")),
+            (500, Some("1k")),
+        ] {
+            assert_eq!(
+                rung.capped_to_source(source_bps, 1.2)
+                    .map(|capped| capped.video_bitrate)
+                    .as_deref(),
+                expected,
+                "{}",
+                source_bps
+            );
+        }
+        let capped = rung.capped_to_source(1_000_000, 1.0).unwrap();
+        assert_eq!((capped.name.as_str(), capped.height), ("1080p", 1080));
+    }
+
+    #[test]
+    fn bitrates_parse_with_suffixes() {
+        assert_eq!(parse_bitrate("2500k"), Some(2_500_000));
+        assert_eq!(parse_bitrate("5M"), Some(5_000_000));
+        assert_eq!(parse_bitrate("1.5m"), Some(1_500_000));
+        assert_eq!(parse_bitrate(" 800000 "), Some(800_000));
+        for value in ["", "k", "fast", "0k", "-1k"] {
+            assert_eq!(parse_bitrate(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn minterpolate_only_when_requested_and_after_scaling() {
+        let rung = Rung::new("720p", 1280, 720, "2500k");
+        assert_eq!(rung.video_filter(None, None, None), "scale=1280:720");
+        assert_eq!(
+            rung.video_filter(None, Some(60.0), None),
+            "scale=1280:720,minterpolate=fps=60:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1"
+        );
+        let smooth = EncodeSettings {
+            smooth_fps: Some(50.0),
+            ..settings()
+        };
+        assert!(
+            smooth.video_args(&rung, None)[1].starts_with("scale=1280:720,minterpolate=fps=50:")
+        );
+        assert!(!settings().video_args(&rung, None)[1].contains("minterpolate"));
+    }
+
+    #[test]
+    fn concat_list_names_every_part_in_order() {
+        let parts = ["/work/part-001.mp4", "/work/part-002.mp4"].map(str::to_string);
+        assert_eq!(
+            concat_list(&parts),
+            "file '/work/part-001.mp4'\nfile '/work/part-002.mp4'\n"
+        );
+        assert_eq!(concat_list(&[]), "");
+    }
+
+    #[test]
+    fn concat_list_escapes_quotes() {
+        let parts = ["/work/it's here.mp4".to_string()];
+        assert_eq!(concat_list(&parts), "file '/work/it'\\''s here.mp4'\n");
+    }
+
+    #[test]
+    fn mp4_modes_parse_case_insensitively() {
+        assert_eq!(Mp4Mode::parse("faststart"), Ok(Mp4Mode::Faststart));
+        assert_eq!(Mp4Mode::parse("Fragmented"), Ok(Mp4Mode::Fragmented));
+        assert_eq!(Mp4Mode::parse("NONE"), Ok(Mp4Mode::None));
+        assert!(Mp4Mode::parse("fmp4").is_err());
+    }
+
+    #[test]
+    fn mp4_modes_map_to_movflags() {
+        let mut fragmented = settings();
+        fragmented.mp4_mode = Mp4Mode::Fragmented;
+        let mut plain = settings();
+        plain.mp4_mode = Mp4Mode::None;
+
+        assert_eq!(settings().movflags_args(), ["-movflags", "+faststart"]);
+        assert_eq!(
+            fragmented.movflags_args(),
+            ["-movflags", "+frag_keyframe+empty_moov"]
+        );
+        assert!(plain.movflags_args().is_empty());
+    }
+
+    #[test]
+    fn audio_codecs_parse_case_insensitively() {
+        assert_eq!(AudioCodec::parse("AAC"), Ok(AudioCodec::Aac));
+        assert_eq!(AudioCodec::parse("opus"), Ok(AudioCodec::Opus));
+        assert_eq!(AudioCodec::parse("Mp3"), Ok(AudioCodec::Mp3));
+        assert!(AudioCodec::parse("vorbis").is_err());
+    }
+
+    #[test]
+    fn mp3_is_rejected_in_dash_only() {
+        let mp3 = AudioCodec::Mp3.encoder();
+        assert!(audio_codec_incompatibility(OutputFormat::Dash, mp3).is_some());
+        assert!(audio_codec_incompatibility(OutputFormat::Dash, "mp3").is_some());
+        assert_eq!(audio_codec_incompatibility(OutputFormat::Mp4, mp3), None);
+        for codec in [AudioCodec::Aac, AudioCodec::Opus] {
+            assert_eq!(
+                audio_codec_incompatibility(OutputFormat::Dash, codec.encoder()),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn opus_is_limited_to_its_own_sample_rates() {
+        assert!(sample_rate_incompatibility(AudioCodec::Opus, Some(44100)).is_some());
+        assert_eq!(
+            sample_rate_incompatibility(AudioCodec::Opus, Some(48000)),
+            None
+        );
+        assert_eq!(sample_rate_incompatibility(AudioCodec::Opus, None), None);
+        assert_eq!(
+            sample_rate_incompatibility(AudioCodec::Aac, Some(44100)),
+            None
+        );
+    }
+
+    #[test]
+    fn plain_subtitle_paths_pass_through() {
+        assert_eq!(
+            subtitles_filter("/work/job-1/captions.srt"),
+            "subtitles=filename=/work/job-1/captions.srt"
+        );
+    }
+
+    #[test]
+    fn subtitle_paths_are_escaped_for_the_option_and_the_graph() {
+        assert_eq!(
+            subtitles_filter("/work/it's [take 2]: a,b;c.srt"),
+            r"subtitles=filename=/work/it\\\'s \[take 2\]\\: a\,b\;c.srt"
+        );
+        assert_eq!(
+            subtitles_filter(r"C:\subs\clip.srt"),
+            r"subtitles=filename=C\\:\\\\subs\\\\clip.srt"
+        );
+    }
+
+    #[test]
+    fn captions_are_burned_in_after_scaling() {
+        let rung = Rung::new("720p", 1280, 720, "2500k");
+        assert_eq!(
+            rung.video_filter(Some(30.0), None, Some("/work/a,b.srt")),
+            r"fps=30,scale=1280:720,subtitles=filename=/work/a\,b.srt"
+        );
+    }
+}
@@ -1,132 +1,1520 @@
+use aws_config::BehaviorVersion;
 use aws_sdk_s3::Client as S3Client;
-use aws_sdk_s3::primitives::ByteStream;
-use std::env;
-use std::fs::File;
-use std::io::Write;
+use aws_sdk_sns::Client as SnsClient;
+use std::collections::BTreeMap;
 use std::path::Path;
-use std::process::Command;
+use std::time::{Duration, Instant};
 
-const SOURCE_BUCKET: &str = "temp-video-storage-0342";
-const DEST_BUCKET: &str = "perm-video-storage-0342";
+use checksum::ChecksumMode;
+use config::{DEST_BUCKET, JobConfig, SOURCE_BUCKET};
+use destination::Encryption;
+use error::TranscodeError;
+use ffmpeg::{EncodeSettings, LadderMode, OutputFormat, Rung, TrackMode, UpscalePolicy};
+use mime::content_type_for;
+use notify::{CompletionNotice, JobStatus, PreviewReady};
+use profile::Profile;
+use progress::ProgressReporter;
+use s3::{
+    DestinationClients, ObjectHead, UploadOptions, delete_from_s3, download_from_s3,
+    upload_bytes_to_s3, upload_to_s3,
+};
+use storyboard::StoryboardSettings;
+use timing::JobTimings;
+use tokio::task::{JoinError, JoinSet};
+use transcoder::{CopyTranscoder, FfmpegTranscoder, Transcoder};
+use workspace::JobWorkspace;
+
+mod checksum;
+mod config;
+mod destination;
+mod error;
+#[cfg(test)]
+mod fake_s3;
+mod ffmpeg;
+mod mime;
+mod notify;
+mod preview;
+mod probe;
+mod profile;
+mod progress;
+mod s3;
+mod slug;
+mod storyboard;
+mod tags;
+mod timing;
+mod transcoder;
+mod workspace;
+
+/// How a job that ran to the end without an error finished.
+enum Outcome {
+    Transcoded,
+    /// Some rungs failed but at least one was uploaded; holds the failed
+    /// rung names.
+    Partial(Vec<String>),
+    /// The source was deliberately skipped; nothing was uploaded.
+    Rejected(String),
+}
+
+/// What a job records along the way for its completion notice, whatever
+/// the outcome.
+#[derive(Default)]
+struct JobReport {
+    timings: JobTimings,
+    /// SHA-256 of each uploaded rendition by rung, under `CHECKSUM_MODE`.
+    checksums: BTreeMap<String, String>,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let source_key = env::var("SOURCE_KEY").expect("SOURCE_KEY environment variable not set");
+    let mut job = JobConfig::from_env()?;
 
     println!("Starting transcoding job");
-    println!("Source: s3://{}/{}", SOURCE_BUCKET, source_key);
+    println!("Source: s3://{}/{}", SOURCE_BUCKET, job.source_key);
     println!("Destination: s3://{}", DEST_BUCKET);
+    println!("{}", job.summary());
+
+    let aws_config = aws_config::defaults(BehaviorVersion::latest())
+        .timeout_config(config::aws_timeouts()?)
+        .load()
+        .await;
+    let s3_client = S3Client::new(&aws_config);
+    let sns_client = SnsClient::new(&aws_config);
+    let dest_clients =
+        DestinationClients::resolve(&s3_client, &aws_config, job.destination.buckets()).await;
 
-    let config = aws_config::load_from_env().await;
-    let s3_client = S3Client::new(&config);
+    // A profile that can't be fetched falls back to the env settings; one
+    // that fetches but doesn't validate fails the job.
+    if let Ok(profile_key) = std::env::var("PROFILE_KEY") {
+        match profile::fetch(&s3_client, SOURCE_BUCKET, &profile_key).await {
+            Ok(bytes) => {
+                job.apply_profile(Profile::parse(&profile_key, &bytes)?)?;
+                println!("Applied profile s3://{}/{}", SOURCE_BUCKET, profile_key);
+                println!("{}", job.summary());
+            }
+            Err(e) => println!(
+                "{}",
+                serde_json::json!({
+                    "level": "warning",
+                    "event": "profile_unavailable",
+                    "profile_key": profile_key,
+                    "error": e.to_string(),
+                })
+            ),
+        }
+    }
+
+    let mut transcoder: Box<dyn Transcoder> = if job.fake_transcode {
+        println!("FAKE_TRANSCODE set: copying the source instead of encoding");
+        Box::new(CopyTranscoder)
+    } else {
+        Box::new(FfmpegTranscoder {
+            settings: job.encode.clone(),
+        })
+    };
+
+    // The source is read up front so even a failed job's notice carries
+    // its metadata.
+    let mut metadata = BTreeMap::new();
+    let mut title = None;
+    let mut report = JobReport::default();
+    let result = match head_source(&s3_client, &job).await {
+        Ok(head) => {
+            metadata = job.propagated_metadata(&head.metadata);
+            title = head.metadata.get("title").cloned();
+            if let Some(title) = &title {
+                println!("Title: {}", title);
+            }
+            run_job(
+                &s3_client,
+                &sns_client,
+                &job,
+                transcoder.as_mut(),
+                &dest_clients,
+                &head,
+                &mut report,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    // A missing source is final, so the job is rejected rather than failed
+    // and the driver doesn't retry it.
+    let result = match result {
+        Err(TranscodeError::SourceMissing { bucket, key }) => {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "level": "warning",
+                    "event": "source_missing",
+                    "bucket": bucket,
+                    "key": key,
+                })
+            );
+            Ok(Outcome::Rejected("source-missing".to_string()))
+        }
+        other => other,
+    };
+    let notice = match &result {
+        Ok(Outcome::Transcoded) => CompletionNotice::new(&job.source_key, JobStatus::Success, None),
+        Ok(Outcome::Partial(missing)) => CompletionNotice::new(
+            &job.source_key,
+            JobStatus::Partial,
+            Some(format!("rungs failed: {}", missing.join(", "))),
+        )
+        .with_missing_rungs(missing.clone()),
+        Ok(Outcome::Rejected(reason)) => {
+            CompletionNotice::new(&job.source_key, JobStatus::Rejected, Some(reason.clone()))
+        }
+        Err(e) => CompletionNotice::new(&job.source_key, JobStatus::Failed, Some(e.to_string()))
+            .with_category(e.category()),
+    }
+    .with_title(title)
+    .with_output_stem(job.sanitize_output_keys.then(|| job.output_stem()))
+    .with_metadata(metadata)
+    .with_timings(report.timings)
+    .with_checksums(report.checksums);
+    println!(
+        "{}",
+        serde_json::json!({
+            "level": "info",
+            "event": "job_timings",
+            "timings": notice.timings,
+        })
+    );
+    notify::emit(&sns_client, &notice).await;
+
+    // Written after everything else so consumers watching for it know the
+    // job is over. A failed write is logged; the job's own result stands.
+    if let Some(marker_key) = job.done_marker_key(&notice.status) {
+        let body = serde_json::to_vec(&notice)?;
+        let options = UploadOptions {
+            tagging: None,
+            storage_class: job.destination.storage_class_for(None),
+            encryption: &job.destination.encryption,
+            tenant: job.tenant.as_deref(),
+            metadata: None,
+        };
+        match upload_bytes_to_s3(
+            dest_clients.get(DEST_BUCKET),
+            DEST_BUCKET,
+            &marker_key,
+            body,
+            "application/json",
+            options,
+        )
+        .await
+        {
+            Ok(()) => println!("Wrote done marker s3://{}/{}", DEST_BUCKET, marker_key),
+            Err(e) => println!(
+                "{}",
+                serde_json::json!({
+                    "level": "error",
+                    "event": "done_marker_failed",
+                    "key": marker_key,
+                    "error": e.to_string(),
+                })
+            ),
+        }
+    }
+
+    match result? {
+        Outcome::Transcoded => println!("Transcoding job completed successfully"),
+        Outcome::Partial(missing) => println!(
+            "Transcoding job completed without rungs: {}",
+            missing.join(", ")
+        ),
+        Outcome::Rejected(reason) => println!("Transcoding job rejected: {}", reason),
+    }
+    Ok(())
+}
+
+async fn run_job(
+    s3_client: &S3Client,
+    sns_client: &SnsClient,
+    job: &JobConfig,
+    transcoder: &mut dyn Transcoder,
+    dest_clients: &DestinationClients,
+    source: &ObjectHead,
+    report: &mut JobReport,
+) -> Result<Outcome, TranscodeError> {
+    let timings = &mut report.timings;
+    let job_started = Instant::now();
+    let workspace = JobWorkspace::create(Path::new(&job.work_root))?;
+    println!("Working directory: {}", workspace.dir().display());
+
+    // Fail before downloading rather than with ENOSPC halfway through.
+    let need = job.disk_needed(source.size);
+    let have = workspace.available_bytes()?;
+    if need > have {
+        return Err(TranscodeError::InsufficientStorage { need, have });
+    }
+
+    // The `input-` prefix keeps a source named e.g. `preview.mp4` from
+    // colliding with the job's own intermediate files.
+    let input_name = job
+        .input_name_from_key
+        .then(|| workspace::sanitize_file_name(&job.source_stem()))
+        .flatten()
+        .map_or_else(|| "input".to_string(), |name| format!("input-{}", name));
+    let input_path = &workspace.file(&format!(
+        "{}.{}",
+        input_name,
+        mime::input_extension(&job.source_key, source.content_type.as_deref())
+    ));
+    // Started before the download so a slow download isn't mistaken for a
+    // stuck job.
+    let reporter = job.progress.as_ref().map(|settings| {
+        ProgressReporter::spawn(sns_client.clone(), settings.clone(), &job.source_key)
+    });
 
-    let input_path = "/tmp/input.mp4";
     println!("Downloading video from S3...");
-    download_from_s3(&s3_client, SOURCE_BUCKET, &source_key, input_path).await?;
+    let started = Instant::now();
+    let report_download = |percent: f64| {
+        if let Some(reporter) = &reporter {
+            reporter.report_download(percent);
+        }
+    };
+    if job.source_keys.len() > 1 {
+        if let Some(reason) =
+            join_parts(s3_client, job, &workspace, input_path, &report_download).await?
+        {
+            return Ok(Outcome::Rejected(reason));
+        }
+    } else {
+        download_from_s3(
+            s3_client,
+            SOURCE_BUCKET,
+            &job.source_key,
+            input_path,
+            reporter.as_ref().map(|_| &report_download as &dyn Fn(f64)),
+        )
+        .await?;
+    }
+    timings.since("download", started);
+
+    if !probe::has_video_stream(input_path)? {
+        println!(
+            "{}",
+            serde_json::json!({
+                "level": "warning",
+                "event": "empty_or_corrupt_source",
+                "source_key": job.source_key,
+                "bytes": std::fs::metadata(input_path)?.len(),
+            })
+        );
+        if job.delete_corrupt_source {
+            delete_sources(s3_client, job).await?;
+        }
+        return Ok(Outcome::Rejected("empty-or-corrupt".to_string()));
+    }
+
+    let duration = probe::probe_duration(input_path)?;
+    println!("Source duration: {:.1}s", duration);
+    if let Some(reason) = job.duration_rejection(duration) {
+        return Ok(Outcome::Rejected(reason));
+    }
+
+    // Stream copies only work if MP4 can carry the source's codec.
+    if job.encode.track_mode != TrackMode::Both {
+        let video_codec = probe::probe_codec(input_path, "v")?;
+        let audio_codec = probe::probe_codec(input_path, "a")?;
+        if let Some(reason) = job
+            .encode
+            .copy_incompatibility(video_codec.as_deref(), audio_codec.as_deref())
+        {
+            return Ok(Outcome::Rejected(reason));
+        }
+    }
+
+    // Only probed when a cap is configured.
+    let source_fps = match job.encode.fps_cap {
+        Some(_) => Some(probe::probe_frame_rate(input_path)?),
+        None => None,
+    };
+    if let Some(fps) = job.encode.fps_limit(source_fps) {
+        println!("Capping frame rate at {} fps", fps);
+    }
+    if let Some(fps) = job.encode.smooth_fps {
+        println!(
+            "{}",
+            serde_json::json!({
+                "level": "warning",
+                "event": "smooth_fps_enabled",
+                "fps": fps,
+                "message": "motion interpolation is CPU-heavy and slows encoding considerably",
+            })
+        );
+    }
+
+    let subtitles = if job.burn_subtitles {
+        fetch_subtitles(s3_client, job, &workspace).await?
+    } else {
+        None
+    };
+    if let Some(path) = &subtitles {
+        transcoder.burn_subtitles(path);
+    }
+
+    let stem = job.output_stem();
+
+    let metadata = job.propagated_metadata(&source.metadata);
+    let output_metadata = job.metadata_on_outputs.then_some(&metadata);
+
+    // Every output key starts with this.
+    let prefix = job.output_prefix(&stem);
+
+    let tagging = |rendition: &str| {
+        job.tags
+            .as_ref()
+            .map(|tags| tags.for_object(rendition, &stem))
+    };
+
+    // A quick proxy goes up before the full ladder so reviewers can start
+    // watching while the real renditions encode.
+    if job.preview_first {
+        let proxy_path = workspace.file("preview.mp4");
+        let proxy_key = job.key_layout.key(&prefix, "preview.mp4");
+        println!("Transcoding preview proxy...");
+        let started = Instant::now();
+        ffmpeg::transcode_proxy(input_path, &proxy_path, &job.encode, job.preview_first_secs)?;
+        timings.since("preview", started);
+        println!(
+            "Uploading preview proxy to s3://{}/{}",
+            DEST_BUCKET, proxy_key
+        );
+        let started = Instant::now();
+        upload_to_s3(
+            dest_clients.get(DEST_BUCKET),
+            DEST_BUCKET,
+            &proxy_key,
+            &proxy_path,
+            content_type_for(&proxy_key),
+            UploadOptions {
+                tagging: tagging("preview").as_deref(),
+                storage_class: job.destination.storage_class_for(None),
+                encryption: &job.destination.encryption,
+                tenant: job.tenant.as_deref(),
+                metadata: output_metadata,
+            },
+        )
+        .await?;
+        timings.since("upload_total", started);
+        std::fs::remove_file(&proxy_path)?;
+        notify::emit(
+            sns_client,
+            &PreviewReady::new(&job.source_key, DEST_BUCKET, &proxy_key),
+        )
+        .await;
+    }
 
-    let resolutions = vec![
-        ("480p", "scale=854:480", "1000k"),
-        ("720p", "scale=1280:720", "2500k"),
-        ("1080p", "scale=1920:1080", "5000k"),
-    ];
+    // Native mode rounds to the nearest standard size, which may be a few
+    // lines taller than the source, so the upscale policy doesn't apply.
+    let native_rung;
+    let plan: Vec<_> = if job.ladder_mode == LadderMode::Native {
+        let source_height = probe::probe_height(input_path)?;
+        native_rung = ffmpeg::native_rung(source_height);
+        println!(
+            "LADDER_MODE=native: encoding {} for the {}px source",
+            native_rung.name, source_height
+        );
+        vec![(&native_rung, false)]
+    } else if job.upscale_policy == UpscalePolicy::Allow {
+        job.ladder.iter().map(|rung| (rung, false)).collect()
+    } else {
+        let source_height = probe::probe_height(input_path)?;
+        let plan = job.upscale_policy.plan(&job.ladder, source_height);
+        for rung in &job.ladder {
+            if !plan.iter().any(|(planned, _)| planned.name == rung.name) {
+                println!(
+                    "Skipping {}: taller than the {}px source",
+                    rung.name, source_height
+                );
+            }
+        }
+        plan
+    };
+
+    // A copied video track can't be scaled, so every rung would be the same
+    // file; only the tallest planned rung is written.
+    let plan = if job.encode.track_mode == TrackMode::AudioOnly {
+        let tallest = plan.into_iter().max_by_key(|(rung, _)| rung.height);
+        if let Some((rung, _)) = tallest {
+            println!("AUDIO_ONLY copies the video; writing only {}", rung.name);
+        }
+        tallest.into_iter().collect()
+    } else {
+        plan
+    };
 
-    for (name, scale, bitrate) in resolutions {
-        let output_path = format!("/tmp/output_{}.mp4", name);
-        println!("Transcoding to {}...", name);
-        transcode_video(input_path, &output_path, scale, bitrate)?;
+    let source_bps = match job.bitrate_cap_factor {
+        Some(_) => probe::probe_bitrate(input_path)?,
+        None => None,
+    };
+    let capped: Vec<(Rung, bool)> = plan
+        .into_iter()
+        .map(|(rung, upscale)| {
+            let capped = source_bps
+                .zip(job.bitrate_cap_factor)
+                .and_then(|(bps, factor)| rung.capped_to_source(bps, factor));
+            match capped {
+                Some(capped) => {
+                    println!(
+                        "Capping {} at {} for the {} kb/s source",
+                        rung.name,
+                        capped.video_bitrate,
+                        source_bps.unwrap_or_default() / 1000
+                    );
+                    (capped, upscale)
+                }
+                None => (rung.clone(), upscale),
+            }
+        })
+        .collect();
+    let plan: Vec<(&Rung, bool)> = capped
+        .iter()
+        .map(|(rung, upscale)| (rung, *upscale))
+        .collect();
 
-        let dest_key = format!(
-            "{}/{}.mp4",
-            Path::new(&source_key)
-                .file_stem()
-                .unwrap()
-                .to_str()
-                .unwrap(),
-            name
+    let mut succeeded = 0;
+    let mut failed_rungs: Vec<(String, TranscodeError)> = Vec::new();
+    if job.output_format == OutputFormat::Dash {
+        let rungs: Vec<&Rung> = plan.iter().map(|(rung, _)| *rung).collect();
+        let dash_dir = workspace.file("dash");
+        let log_path = workspace.file("output_dash.log");
+        std::fs::create_dir_all(&dash_dir)?;
+        println!("Transcoding DASH ladder...");
+        let started = Instant::now();
+        let report = |position: f64| {
+            if let Some(reporter) = &reporter {
+                reporter.report("dash", 1, 1, position / duration * 100.0);
+            }
+        };
+        let settings = EncodeSettings {
+            subtitles: subtitles.clone(),
+            ..job.encode.clone()
+        };
+        let transcode_result = ffmpeg::transcode_dash(
+            input_path,
+            &dash_dir,
+            &rungs,
+            &settings,
+            source_fps,
+            job.upload_logs.then_some(log_path.as_str()),
+            reporter.as_ref().map(|_| &report as &dyn Fn(f64)),
         );
+        timings.since("dash", started);
+
+        // The log is missing if it couldn't be created; ffmpeg_log_unavailable
+        // was logged then.
+        if job.upload_logs && Path::new(&log_path).exists() {
+            let log_key = job.key_layout.key(&prefix, "logs/dash.log");
+            println!("Uploading ffmpeg log to s3://{}/{}", DEST_BUCKET, log_key);
+            upload_to_s3(
+                dest_clients.get(DEST_BUCKET),
+                DEST_BUCKET,
+                &log_key,
+                &log_path,
+                content_type_for(&log_key),
+                UploadOptions {
+                    tagging: tagging("log").as_deref(),
+                    storage_class: job.destination.storage_class_for(None),
+                    encryption: &job.destination.encryption,
+                    tenant: job.tenant.as_deref(),
+                    metadata: output_metadata,
+                },
+            )
+            .await?;
+            std::fs::remove_file(&log_path)?;
+        }
+        transcode_result?;
 
-        println!("Uploading {} to s3://{}/{}", name, DEST_BUCKET, dest_key);
-        upload_to_s3(&s3_client, DEST_BUCKET, &dest_key, &output_path).await?;
+        // Segments go up before the manifest so a player never sees an
+        // .mpd that points at objects which don't exist yet.
+        let mut files: Vec<_> = std::fs::read_dir(&dash_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort_by_key(|name| (name.ends_with(".mpd"), name.clone()));
+        println!(
+            "Uploading {} DASH files to s3://{}/{}/dash/",
+            files.len(),
+            DEST_BUCKET,
+            prefix
+        );
+        let started = Instant::now();
+        for name in files {
+            let path = format!("{}/{}", dash_dir, name);
+            let dest_key = job.key_layout.key(&prefix, &format!("dash/{}", name));
+            upload_to_s3(
+                dest_clients.get(DEST_BUCKET),
+                DEST_BUCKET,
+                &dest_key,
+                &path,
+                content_type_for(&dest_key),
+                UploadOptions {
+                    tagging: tagging("dash").as_deref(),
+                    storage_class: job.destination.storage_class_for(None),
+                    encryption: &job.destination.encryption,
+                    tenant: job.tenant.as_deref(),
+                    metadata: output_metadata,
+                },
+            )
+            .await?;
+            std::fs::remove_file(&path)?;
+        }
+        timings.since("upload_total", started);
+        println!("Completed DASH");
+    } else {
+        (succeeded, failed_rungs) = run_ladder(
+            job,
+            &*transcoder,
+            dest_clients,
+            &LadderInputs {
+                workspace: &workspace,
+                input_path,
+                prefix: &prefix,
+                stem: &stem,
+                output_metadata,
+                source_fps,
+                duration,
+                reporter: reporter.as_ref(),
+            },
+            &plan,
+            timings,
+            &mut report.checksums,
+        )
+        .await?;
+    }
+    if let Some(reporter) = reporter {
+        reporter.finish().await;
+    }
+    let missing_rungs = missing_rungs(succeeded, failed_rungs, job.require_all_rungs)?;
 
-        std::fs::remove_file(&output_path)?;
-        println!("Completed {}", name);
+    if let Some(settings) = &job.storyboard {
+        let started = Instant::now();
+        upload_storyboard(
+            dest_clients.get(DEST_BUCKET),
+            &workspace,
+            input_path,
+            &|path| job.key_layout.key(&prefix, path),
+            settings,
+            duration,
+            UploadOptions {
+                tagging: tagging("storyboard").as_deref(),
+                storage_class: job.destination.storage_class_for(None),
+                encryption: &job.destination.encryption,
+                tenant: job.tenant.as_deref(),
+                metadata: output_metadata,
+            },
+        )
+        .await?;
+        timings.since("storyboard", started);
+    }
+
+    if let Some(settings) = &job.preview_clip {
+        let clip_path = workspace.file(&format!("preview.{}", settings.format.extension()));
+        println!("Generating preview clip...");
+        let started = Instant::now();
+        preview::generate_clip(
+            input_path,
+            &clip_path,
+            &workspace.file("preview_palette.png"),
+            settings,
+            duration,
+        )?;
+        timings.since("clip", started);
+
+        let clip_key = job
+            .key_layout
+            .key(&prefix, &format!("preview.{}", settings.format.extension()));
+        println!(
+            "Uploading preview clip to s3://{}/{}",
+            DEST_BUCKET, clip_key
+        );
+        let started = Instant::now();
+        upload_to_s3(
+            dest_clients.get(DEST_BUCKET),
+            DEST_BUCKET,
+            &clip_key,
+            &clip_path,
+            content_type_for(&clip_key),
+            UploadOptions {
+                tagging: tagging("preview").as_deref(),
+                storage_class: job.destination.storage_class_for(None),
+                encryption: &job.destination.encryption,
+                tenant: job.tenant.as_deref(),
+                metadata: output_metadata,
+            },
+        )
+        .await?;
+        timings.since("upload_total", started);
+        std::fs::remove_file(&clip_path)?;
     }
 
     std::fs::remove_file(input_path)?;
-    println!("Transcoding job completed successfully");
-    Ok(())
+
+    // Every other step returns early on failure, so reaching this point with
+    // no missing rungs means all outputs are uploaded and the source is safe
+    // to remove. A partial job keeps it so the missing rungs can be redone.
+    if job.delete_source_on_success && missing_rungs.is_empty() {
+        delete_sources(s3_client, job).await?;
+    }
+
+    timings.since("total", job_started);
+    if missing_rungs.is_empty() {
+        Ok(Outcome::Transcoded)
+    } else {
+        Ok(Outcome::Partial(missing_rungs))
+    }
 }
 
-async fn download_from_s3(
-    client: &S3Client,
-    bucket: &str,
-    key: &str,
-    destination: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut object = client.get_object().bucket(bucket).key(key).send().await?;
-    let mut file = File::create(destination)?;
+/// The rungs a finished ladder is missing, or the first failure if that
+/// fails the job: when nothing succeeded, or `require_all` is set.
+fn missing_rungs(
+    succeeded: usize,
+    mut failed_rungs: Vec<(String, TranscodeError)>,
+    require_all: bool,
+) -> Result<Vec<String>, TranscodeError> {
+    if !failed_rungs.is_empty() && (succeeded == 0 || require_all) {
+        return Err(failed_rungs.swap_remove(0).1);
+    }
+    Ok(failed_rungs.into_iter().map(|(name, _)| name).collect())
+}
+
+/// What the rung loop takes from the rest of the job.
+#[derive(Clone, Copy)]
+struct LadderInputs<'a> {
+    workspace: &'a JobWorkspace,
+    input_path: &'a str,
+    /// Every output key starts with this.
+    prefix: &'a str,
+    stem: &'a str,
+    output_metadata: Option<&'a BTreeMap<String, String>>,
+    source_fps: Option<f64>,
+    /// Source duration in seconds, for progress percentages.
+    duration: f64,
+    reporter: Option<&'a ProgressReporter>,
+}
 
-    while let Some(bytes) = object.body.try_next().await? {
-        file.write_all(&bytes)?;
+/// Encodes each planned rung in turn and uploads it while the next one
+/// encodes. Returns how many rungs made it to S3 and the ones that didn't;
+/// only a failed existence check ends the loop early.
+async fn run_ladder(
+    job: &JobConfig,
+    transcoder: &dyn Transcoder,
+    dest_clients: &DestinationClients,
+    inputs: &LadderInputs<'_>,
+    plan: &[(&Rung, bool)],
+    timings: &mut JobTimings,
+    checksums: &mut BTreeMap<String, String>,
+) -> Result<(usize, Vec<(String, TranscodeError)>), TranscodeError> {
+    let LadderInputs {
+        workspace,
+        input_path,
+        prefix,
+        stem,
+        output_metadata,
+        source_fps,
+        duration,
+        reporter,
+    } = *inputs;
+    let tagging = |rendition: &str| {
+        job.tags
+            .as_ref()
+            .map(|tags| tags.for_object(rendition, stem))
+    };
+    let mut succeeded = 0;
+    let mut failed_rungs: Vec<(String, TranscodeError)> = Vec::new();
+    let mut uploads = JoinSet::new();
+    for (index, (rung, upscale)) in plan.iter().copied().enumerate() {
+        let name = &rung.name;
+        let dest_bucket = job.destination.bucket_for(name);
+        let Some(dest_key) = s3::resolve_existing(
+            dest_clients.get(dest_bucket),
+            dest_bucket,
+            &job.key_layout.key(prefix, &format!("{}.mp4", name)),
+            job.on_existing,
+        )
+        .await?
+        else {
+            println!("Skipping {}: already in s3://{}", name, dest_bucket);
+            succeeded += 1;
+            continue;
+        };
+        let output_path = workspace.file(&format!("output_{}.mp4", name));
+        // A failed rung doesn't stop the others; whether the job fails is
+        // decided once they've all had their turn.
+        let rung_result: Result<Option<String>, TranscodeError> = async {
+            let log_path = workspace.file(&format!("output_{}.log", name));
+            println!("Transcoding to {}...", name);
+            let started = Instant::now();
+            let report = |position: f64| {
+                if let Some(reporter) = &reporter {
+                    reporter.report(name, index + 1, plan.len(), position / duration * 100.0);
+                }
+            };
+            // Lets the uploads already in flight keep going meanwhile.
+            let transcode_result = tokio::task::block_in_place(|| {
+                transcoder.transcode(
+                    input_path,
+                    &output_path,
+                    rung,
+                    source_fps,
+                    job.upload_logs.then_some(log_path.as_str()),
+                    reporter.as_ref().map(|_| &report as &dyn Fn(f64)),
+                )
+            });
+            timings.since(&format!("rung_{}", name), started);
+
+            if job.upload_logs && Path::new(&log_path).exists() {
+                let log_key = job.key_layout.key(prefix, &format!("logs/{}.log", name));
+                println!("Uploading ffmpeg log to s3://{}/{}", DEST_BUCKET, log_key);
+                upload_to_s3(
+                    dest_clients.get(DEST_BUCKET),
+                    DEST_BUCKET,
+                    &log_key,
+                    &log_path,
+                    content_type_for(&log_key),
+                    UploadOptions {
+                        tagging: tagging("log").as_deref(),
+                        storage_class: job.destination.storage_class_for(None),
+                        encryption: &job.destination.encryption,
+                        tenant: job.tenant.as_deref(),
+                        metadata: output_metadata,
+                    },
+                )
+                .await?;
+                std::fs::remove_file(&log_path)?;
+            }
+            transcode_result?;
+
+            let mut rung_tagging = tagging(name);
+            if upscale && job.upscale_policy == UpscalePolicy::Flag {
+                println!("Flagging {} as an upscale", name);
+                rung_tagging = Some(tags::append_tag(rung_tagging, "upscale", "true"));
+            }
+            Ok(rung_tagging)
+        }
+        .await;
+        let rung_tagging = match rung_result {
+            Ok(rung_tagging) => rung_tagging,
+            Err(e) => {
+                log_rung_failure(name, &e);
+                let _ = std::fs::remove_file(&output_path);
+                failed_rungs.push((name.clone(), e));
+                continue;
+            }
+        };
+
+        if uploads.len() >= job.upload_concurrency
+            && let Some(joined) = uploads.join_next().await
+        {
+            record_upload(
+                joined,
+                &mut succeeded,
+                &mut failed_rungs,
+                timings,
+                checksums,
+            );
+        }
+        println!("Uploading {} to s3://{}/{}", name, dest_bucket, dest_key);
+        let upload = PendingUpload {
+            client: dest_clients.get(dest_bucket).clone(),
+            bucket: dest_bucket.to_string(),
+            key: dest_key,
+            path: output_path,
+            tagging: rung_tagging,
+            storage_class: job.destination.storage_class_for(Some(name)).to_string(),
+            encryption: job.destination.encryption.clone(),
+            tenant: job.tenant.clone(),
+            metadata: output_metadata.cloned(),
+            checksum_mode: job.checksum_mode,
+        };
+        let name = name.clone();
+        uploads.spawn(async move { (name, upload.run().await) });
+    }
+    while let Some(joined) = uploads.join_next().await {
+        record_upload(
+            joined,
+            &mut succeeded,
+            &mut failed_rungs,
+            timings,
+            checksums,
+        );
+    }
+    Ok((succeeded, failed_rungs))
+}
+
+/// A finished rendition waiting to go up. It owns everything the upload
+/// needs so the upload can run alongside the next rung's encode.
+struct PendingUpload {
+    client: S3Client,
+    bucket: String,
+    key: String,
+    path: String,
+    tagging: Option<String>,
+    storage_class: String,
+    encryption: Encryption,
+    tenant: Option<String>,
+    metadata: Option<BTreeMap<String, String>>,
+    checksum_mode: ChecksumMode,
+}
+
+/// How long a rendition took to upload, and its SHA-256 if one was taken.
+type UploadResult = Result<(Duration, Option<String>), TranscodeError>;
+
+impl PendingUpload {
+    /// Uploads and removes the rendition, returning how long the upload
+    /// took and, under `CHECKSUM_MODE`, the rendition's SHA-256.
+    async fn run(mut self) -> UploadResult {
+        let started = Instant::now();
+        let sha256 = match self.sha256().await {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                let _ = std::fs::remove_file(&self.path);
+                return Err(e.into());
+            }
+        };
+        if self.checksum_mode == ChecksumMode::Metadata
+            && let Some(hash) = &sha256
+        {
+            self.metadata
+                .get_or_insert_default()
+                .insert("sha256".to_string(), hash.clone());
+        }
+        let result = upload_to_s3(
+            &self.client,
+            &self.bucket,
+            &self.key,
+            &self.path,
+            content_type_for(&self.key),
+            UploadOptions {
+                tagging: self.tagging.as_deref(),
+                storage_class: &self.storage_class,
+                encryption: &self.encryption,
+                tenant: self.tenant.as_deref(),
+                metadata: self.metadata.as_ref(),
+            },
+        )
+        .await;
+        std::fs::remove_file(&self.path)?;
+        result?;
+        if self.checksum_mode == ChecksumMode::Sidecar
+            && let Some(hash) = &sha256
+        {
+            let sidecar_key = format!("{}.sha256", self.key);
+            upload_bytes_to_s3(
+                &self.client,
+                &self.bucket,
+                &sidecar_key,
+                checksum::sidecar_body(hash, &self.key).into_bytes(),
+                "text/plain",
+                UploadOptions {
+                    tagging: self.tagging.as_deref(),
+                    storage_class: &self.storage_class,
+                    encryption: &self.encryption,
+                    tenant: self.tenant.as_deref(),
+                    metadata: None,
+                },
+            )
+            .await?;
+        }
+        Ok((started.elapsed(), sha256))
     }
 
+    /// Hashes the rendition if `CHECKSUM_MODE` asks for it. The metadata has
+    /// to be known before the PUT starts, so the hash takes its own pass
+    /// over the file, which the encode just left in the page cache.
+    async fn sha256(&self) -> std::io::Result<Option<String>> {
+        if self.checksum_mode == ChecksumMode::None {
+            return Ok(None);
+        }
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || checksum::sha256_file(&path))
+            .await
+            .map_err(std::io::Error::other)?
+            .map(Some)
+    }
+}
+
+/// Books a finished rendition upload as a completed or failed rung.
+fn record_upload(
+    joined: Result<(String, UploadResult), JoinError>,
+    succeeded: &mut usize,
+    failed_rungs: &mut Vec<(String, TranscodeError)>,
+    timings: &mut JobTimings,
+    checksums: &mut BTreeMap<String, String>,
+) {
+    match joined {
+        Ok((name, Ok((elapsed, sha256)))) => {
+            timings.add("upload_total", elapsed);
+            if let Some(hash) = sha256 {
+                checksums.insert(name.clone(), hash);
+            }
+            *succeeded += 1;
+            println!("Completed {}", name);
+        }
+        Ok((name, Err(e))) => {
+            log_rung_failure(&name, &e);
+            failed_rungs.push((name, e));
+        }
+        // The task only panics on a bug, and then its rung can't be named.
+        Err(e) => failed_rungs.push(("unknown".to_string(), std::io::Error::other(e).into())),
+    }
+}
+
+fn log_rung_failure(name: &str, e: &TranscodeError) {
     println!(
-        "Downloaded video from S3 ({} bytes)",
-        std::fs::metadata(destination)?.len()
+        "{}",
+        serde_json::json!({
+            "level": "error",
+            "event": "rung_failed",
+            "rung": name,
+            "error": e.to_string(),
+        })
     );
+}
+
+/// Downloads the captions sidecar for `BURN_SUBTITLES`. A source without
+/// one is encoded as usual.
+async fn fetch_subtitles(
+    s3_client: &S3Client,
+    job: &JobConfig,
+    workspace: &JobWorkspace,
+) -> Result<Option<String>, TranscodeError> {
+    let key = job.subtitles_key();
+    let path = workspace.file("captions.srt");
+    match download_from_s3(s3_client, SOURCE_BUCKET, &key, &path, None).await {
+        Ok(()) => {
+            println!("Burning in captions from s3://{}/{}", SOURCE_BUCKET, key);
+            Ok(Some(path))
+        }
+        Err(TranscodeError::SourceMissing { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// HEADs the source. For joined parts the size is their total and the
+/// rest comes from the first part.
+async fn head_source(s3_client: &S3Client, job: &JobConfig) -> Result<ObjectHead, TranscodeError> {
+    let mut head = s3::head_object(s3_client, SOURCE_BUCKET, &job.source_key).await?;
+    for key in &job.source_keys[1..] {
+        head.size += s3::head_object(s3_client, SOURCE_BUCKET, key).await?.size;
+    }
+    Ok(head)
+}
+
+/// Downloads every part in `SOURCE_KEYS` and joins them into `output`.
+/// Parts that don't share codecs can't be joined without re-encoding, so
+/// they reject the job with the reason returned.
+async fn join_parts(
+    s3_client: &S3Client,
+    job: &JobConfig,
+    workspace: &JobWorkspace,
+    output: &str,
+    on_progress: &dyn Fn(f64),
+) -> Result<Option<String>, TranscodeError> {
+    let count = job.source_keys.len();
+    let mut parts = Vec::with_capacity(count);
+    for (i, key) in job.source_keys.iter().enumerate() {
+        let part_path = workspace.file(&format!(
+            "part-{:03}.{}",
+            i + 1,
+            mime::input_extension(key, None)
+        ));
+        println!(
+            "Downloading part {} of {}: s3://{}/{}",
+            i + 1,
+            count,
+            SOURCE_BUCKET,
+            key
+        );
+        download_from_s3(s3_client, SOURCE_BUCKET, key, &part_path, None).await?;
+        on_progress((i + 1) as f64 / count as f64 * 100.0);
+        parts.push(part_path);
+    }
+
+    let codecs = |part: &str| -> Result<_, TranscodeError> {
+        Ok((
+            probe::probe_codec(part, "v")?,
+            probe::probe_codec(part, "a")?,
+        ))
+    };
+    let expected = codecs(&parts[0])?;
+    for (key, part) in job.source_keys.iter().zip(&parts).skip(1) {
+        let found = codecs(part)?;
+        if found != expected {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "level": "warning",
+                    "event": "incompatible_source_parts",
+                    "first_key": job.source_key,
+                    "key": key,
+                    "expected": [expected.0, expected.1],
+                    "found": [found.0, found.1],
+                })
+            );
+            return Ok(Some("incompatible-parts".to_string()));
+        }
+    }
+
+    let list_path = workspace.file("concat.txt");
+    std::fs::write(&list_path, ffmpeg::concat_list(&parts))?;
+    println!("Joining {} parts", count);
+    ffmpeg::concat_parts(&list_path, output)?;
+    for part in &parts {
+        std::fs::remove_file(part)?;
+    }
+    std::fs::remove_file(&list_path)?;
+    Ok(None)
+}
+
+async fn delete_sources(s3_client: &S3Client, job: &JobConfig) -> Result<(), TranscodeError> {
+    for key in &job.source_keys {
+        println!("Deleting source s3://{}/{}", SOURCE_BUCKET, key);
+        delete_from_s3(s3_client, SOURCE_BUCKET, key).await?;
+    }
     Ok(())
 }
 
-async fn upload_to_s3(
+async fn upload_storyboard(
     client: &S3Client,
-    bucket: &str,
-    key: &str,
-    file_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let body = ByteStream::from_path(Path::new(file_path)).await?;
-    client
-        .put_object()
-        .bucket(bucket)
-        .key(key)
-        .body(body)
-        .content_type("video/mp4")
-        .send()
-        .await?;
+    workspace: &JobWorkspace,
+    input_path: &str,
+    output_key: &dyn Fn(&str) -> String,
+    settings: &StoryboardSettings,
+    duration: f64,
+    options: UploadOptions<'_>,
+) -> Result<(), TranscodeError> {
+    let interval = settings.effective_interval(duration);
+    if interval != settings.interval_secs {
+        println!(
+            "Storyboard interval stretched to {}s to fit a {}x{} grid",
+            interval, settings.columns, settings.rows
+        );
+    }
+
+    let sprite_key = output_key("storyboard/storyboard.jpg");
+    let vtt_key = output_key("storyboard/storyboard.vtt");
+    // The VTT sits next to the sprite and refers to it by file name.
+    let sprite_name = sprite_key.rsplit('/').next().unwrap_or(&sprite_key);
+
+    let sprite_path = &workspace.file("storyboard.jpg");
+    let vtt_path = &workspace.file("storyboard.vtt");
+    println!("Generating storyboard every {}s...", interval);
+    storyboard::generate_sprite(input_path, sprite_path, settings, interval)?;
+    std::fs::write(
+        vtt_path,
+        storyboard::build_vtt(sprite_name, settings, interval, duration),
+    )?;
+
+    println!(
+        "Uploading storyboard to s3://{}/{}",
+        DEST_BUCKET, sprite_key
+    );
+    upload_to_s3(
+        client,
+        DEST_BUCKET,
+        &sprite_key,
+        sprite_path,
+        content_type_for(&sprite_key),
+        options,
+    )
+    .await?;
+    upload_to_s3(
+        client,
+        DEST_BUCKET,
+        &vtt_key,
+        vtt_path,
+        content_type_for(&vtt_key),
+        options,
+    )
+    .await?;
+
+    std::fs::remove_file(sprite_path)?;
+    std::fs::remove_file(vtt_path)?;
     Ok(())
 }
 
-fn transcode_video(
-    input: &str,
-    output: &str,
-    scale: &str,
-    video_bitrate: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let status = Command::new("ffmpeg")
-        .args(&[
-            "-i",
-            input,
-            "-vf",
-            scale,
-            "-c:v",
-            "libx264",
-            "-b:v",
-            video_bitrate,
-            "-preset",
-            "medium",
-            "-crf",
-            "23",
-            "-c:a",
-            "aac",
-            "-b:a",
-            "128k",
-            "-movflags",
-            "+faststart",
-            "-y",
-            output,
-        ])
-        .status()?;
-    if !status.success() {
-        return Err(format!("FFmpeg failed with status: {}", status).into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::job;
+    use crate::destination::KeyLayout;
+    use crate::fake_s3::FakeS3;
+    use crate::s3::OnExisting;
+
+    /// Copies like [`CopyTranscoder`], except for the named rungs, which
+    /// fail.
+    struct FailingTranscoder(&'static [&'static str]);
+
+    impl Transcoder for FailingTranscoder {
+        fn transcode(
+            &self,
+            input: &str,
+            output: &str,
+            rung: &Rung,
+            source_fps: Option<f64>,
+            log_path: Option<&str>,
+            on_progress: Option<&dyn Fn(f64)>,
+        ) -> Result<(), TranscodeError> {
+            if self.0.contains(&rung.name.as_str()) {
+                return Err(std::io::Error::other("encoder crashed").into());
+            }
+            CopyTranscoder.transcode(input, output, rung, source_fps, log_path, on_progress)
+        }
+    }
+
+    /// The source bytes every test ladder starts from.
+    const SOURCE: &[u8] = b"source bytes";
+
+    /// A finished [`run_ladder`] over the whole ladder of `job`, with the
+    /// workspace it ran in still alive.
+    struct LadderRun {
+        workspace: JobWorkspace,
+        result: Result<(usize, Vec<(String, TranscodeError)>), TranscodeError>,
+        timings: JobTimings,
+        checksums: BTreeMap<String, String>,
+    }
+
+    async fn run_ladder_with(
+        job: &JobConfig,
+        transcoder: &dyn Transcoder,
+        s3: &FakeS3,
+    ) -> LadderRun {
+        let dest_clients = DestinationClients::single(s3.client());
+        let workspace = JobWorkspace::create(Path::new(&job.work_root)).unwrap();
+        let input_path = workspace.file("input.mp4");
+        std::fs::write(&input_path, SOURCE).unwrap();
+        let stem = job.output_stem();
+        let plan: Vec<(&Rung, bool)> = job.ladder.iter().map(|rung| (rung, false)).collect();
+        let mut timings = JobTimings::default();
+        let mut checksums = BTreeMap::new();
+        let result = run_ladder(
+            job,
+            transcoder,
+            &dest_clients,
+            &LadderInputs {
+                workspace: &workspace,
+                input_path: &input_path,
+                prefix: &job.output_prefix(&stem),
+                stem: &stem,
+                output_metadata: None,
+                source_fps: None,
+                duration: 10.0,
+                reporter: None,
+            },
+            &plan,
+            &mut timings,
+            &mut checksums,
+        )
+        .await;
+        LadderRun {
+            workspace,
+            result,
+            timings,
+            checksums,
+        }
+    }
+
+    /// File names left in `workspace`, sorted.
+    fn leftover_files(workspace: &JobWorkspace) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(workspace.dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ladder_uploads_every_rung_and_cleans_up() {
+        let s3 = FakeS3::start().await;
+        let run = run_ladder_with(&job("uploads/clip.mp4"), &CopyTranscoder, &s3).await;
+
+        let (succeeded, failed) = run.result.unwrap();
+        assert_eq!(succeeded, 3);
+        assert!(failed.is_empty());
+        assert_eq!(
+            s3.keys(),
+            [
+                "perm-video-storage-0342/clip/1080p.mp4",
+                "perm-video-storage-0342/clip/480p.mp4",
+                "perm-video-storage-0342/clip/720p.mp4",
+            ]
+        );
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/720p.mp4").as_deref(),
+            Some(SOURCE)
+        );
+        // Renditions are removed once uploaded; the source is the caller's.
+        assert_eq!(leftover_files(&run.workspace), ["input.mp4"]);
+        let timings = serde_json::to_value(&run.timings).unwrap();
+        let mut phases: Vec<&String> = timings.as_object().unwrap().keys().collect();
+        phases.sort();
+        assert_eq!(
+            phases,
+            [
+                "rung_1080p_ms",
+                "rung_480p_ms",
+                "rung_720p_ms",
+                "upload_total_ms"
+            ]
+        );
+        assert!(run.checksums.is_empty());
+
+        let dir = run.workspace.dir().to_path_buf();
+        drop(run.workspace);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flat_layouts_upload_next_to_the_prefix() {
+        let s3 = FakeS3::start().await;
+        let mut job = job("clip.mp4");
+        job.key_layout = KeyLayout::Flat;
+        let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+        assert_eq!(run.result.unwrap().0, 3);
+        assert_eq!(
+            s3.keys(),
+            [
+                "perm-video-storage-0342/clip_1080p.mp4",
+                "perm-video-storage-0342/clip_480p.mp4",
+                "perm-video-storage-0342/clip_720p.mp4",
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn existing_outputs_are_skipped_under_skip() {
+        let s3 = FakeS3::start().await;
+        s3.put(DEST_BUCKET, "clip/720p.mp4", b"earlier");
+        let mut job = job("clip.mp4");
+        job.on_existing = OnExisting::Skip;
+        let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+        assert_eq!(run.result.unwrap().0, 3);
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/720p.mp4").as_deref(),
+            Some(&b"earlier"[..])
+        );
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/480p.mp4").as_deref(),
+            Some(SOURCE)
+        );
+        assert_eq!(leftover_files(&run.workspace), ["input.mp4"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn existing_outputs_fail_the_job_under_fail() {
+        let s3 = FakeS3::start().await;
+        s3.put(DEST_BUCKET, "clip/720p.mp4", b"earlier");
+        let mut job = job("clip.mp4");
+        job.on_existing = OnExisting::Fail;
+        let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+        assert!(matches!(
+            run.result,
+            Err(TranscodeError::OutputExists { ref key, .. }) if key == "clip/720p.mp4"
+        ));
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/720p.mp4").as_deref(),
+            Some(&b"earlier"[..])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn existing_outputs_are_versioned_under_version() {
+        let s3 = FakeS3::start().await;
+        s3.put(DEST_BUCKET, "clip/720p.mp4", b"earlier");
+        let mut job = job("clip.mp4");
+        job.on_existing = OnExisting::Version;
+        let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+        assert_eq!(run.result.unwrap().0, 3);
+        let keys = s3.keys();
+        assert_eq!(keys.len(), 4);
+        assert!(keys.iter().any(|key| {
+            key.strip_prefix("perm-video-storage-0342/clip/720p-")
+                .and_then(|rest| rest.strip_suffix(".mp4"))
+                .is_some_and(|stamp| stamp.parse::<u64>().is_ok())
+        }));
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/720p.mp4").as_deref(),
+            Some(&b"earlier"[..])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_rungs_leave_the_others_uploaded() {
+        let s3 = FakeS3::start().await;
+        let run = run_ladder_with(&job("clip.mp4"), &FailingTranscoder(&["720p"]), &s3).await;
+
+        let (succeeded, failed) = run.result.unwrap();
+        assert_eq!(succeeded, 2);
+        let failed_names: Vec<&str> = failed.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(failed_names, ["720p"]);
+        assert_eq!(
+            s3.keys(),
+            [
+                "perm-video-storage-0342/clip/1080p.mp4",
+                "perm-video-storage-0342/clip/480p.mp4",
+            ]
+        );
+        assert_eq!(leftover_files(&run.workspace), ["input.mp4"]);
+    }
+
+    fn failures(names: &[&str]) -> Vec<(String, TranscodeError)> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), std::io::Error::other("failed").into()))
+            .collect()
+    }
+
+    #[test]
+    fn some_failed_rungs_make_a_partial_job() {
+        assert_eq!(
+            missing_rungs(3, failures(&[]), false).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            missing_rungs(2, failures(&["720p"]), false).unwrap(),
+            ["720p"]
+        );
+        assert_eq!(
+            missing_rungs(1, failures(&["720p", "1080p"]), false).unwrap(),
+            ["720p", "1080p"]
+        );
+    }
+
+    #[test]
+    fn no_successful_rung_or_requiring_all_fails_the_job() {
+        assert!(missing_rungs(0, failures(&["480p", "720p"]), false).is_err());
+        assert!(missing_rungs(2, failures(&["720p"]), true).is_err());
+        assert!(missing_rungs(3, failures(&[]), true).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_caption_sidecars_are_skipped() {
+        let s3 = FakeS3::start().await;
+        let job = job("acme/clip.mp4");
+        let workspace = JobWorkspace::create(Path::new(&job.work_root)).unwrap();
+
+        let fetched = fetch_subtitles(&s3.client(), &job, &workspace)
+            .await
+            .unwrap();
+        assert_eq!(fetched, None);
+
+        s3.put(
+            SOURCE_BUCKET,
+            "acme/clip.srt",
+            b"1\n00:00:00,000 --> 00:00:01,000\nHi\n",
+        );
+        let fetched = fetch_subtitles(&s3.client(), &job, &workspace)
+            .await
+            .unwrap();
+        let path = fetched.unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().ends_with("Hi\n"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn every_upload_completes_at_any_concurrency() {
+        for concurrency in [1, 2, 8] {
+            let s3 = FakeS3::start().await;
+            let mut job = job("clip.mp4");
+            job.upload_concurrency = concurrency;
+            let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+            let (succeeded, failed) = run.result.unwrap();
+            assert_eq!(succeeded, 3, "concurrency {}", concurrency);
+            assert!(failed.is_empty(), "concurrency {}", concurrency);
+            assert_eq!(s3.keys().len(), 3, "concurrency {}", concurrency);
+            assert_eq!(leftover_files(&run.workspace), ["input.mp4"]);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_uploads_are_reported_as_failed_rungs() {
+        for concurrency in [1, 2] {
+            let s3 = FakeS3::start().await;
+            s3.fail(DEST_BUCKET, "clip/480p.mp4");
+            let mut job = job("clip.mp4");
+            job.upload_concurrency = concurrency;
+            let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+            let (succeeded, failed) = run.result.unwrap();
+            assert_eq!(succeeded, 2, "concurrency {}", concurrency);
+            assert!(matches!(
+                failed.as_slice(),
+                [(name, TranscodeError::S3Upload { .. })] if name == "480p"
+            ));
+            assert_eq!(
+                s3.keys(),
+                [
+                    "perm-video-storage-0342/clip/1080p.mp4",
+                    "perm-video-storage-0342/clip/720p.mp4",
+                ]
+            );
+            // A rendition that didn't make it up is still removed.
+            assert_eq!(leftover_files(&run.workspace), ["input.mp4"]);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sidecar_checksums_are_uploaded_next_to_each_rendition() {
+        let s3 = FakeS3::start().await;
+        let mut job = job("clip.mp4");
+        job.checksum_mode = ChecksumMode::Sidecar;
+        let run = run_ladder_with(&job, &CopyTranscoder, &s3).await;
+
+        assert_eq!(run.result.unwrap().0, 3);
+        // Every rung is a copy of the same source, so they share a hash.
+        let expected = run.checksums["720p"].clone();
+        assert_eq!(expected.len(), 64);
+        assert_eq!(run.checksums.len(), 3);
+        assert!(run.checksums.values().all(|hash| *hash == expected));
+        assert_eq!(
+            s3.get(DEST_BUCKET, "clip/720p.mp4.sha256").as_deref(),
+            Some(format!("{}  720p.mp4\n", expected).as_bytes())
+        );
+        assert_eq!(s3.keys().len(), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rejected_sources_still_time_their_download() {
+        let s3 = FakeS3::start().await;
+        s3.put(SOURCE_BUCKET, "uploads/empty.mp4", b"");
+        let sns_client = SnsClient::from_conf(
+            aws_sdk_sns::Config::builder()
+                .behavior_version(aws_config::BehaviorVersion::latest())
+                .region(aws_sdk_sns::config::Region::new("us-east-1"))
+                .endpoint_url("http://127.0.0.1:1")
+                .build(),
+        );
+        let job = job("uploads/empty.mp4");
+        let source = ObjectHead {
+            size: 0,
+            content_type: None,
+            metadata: Default::default(),
+        };
+        let mut report = JobReport::default();
+
+        let outcome = run_job(
+            &s3.client(),
+            &sns_client,
+            &job,
+            &mut CopyTranscoder,
+            &DestinationClients::single(s3.client()),
+            &source,
+            &mut report,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(outcome, Outcome::Rejected(reason) if reason == "empty-or-corrupt"));
+        let timings = serde_json::to_value(&report.timings).unwrap();
+        assert!(timings["download_ms"].is_u64());
     }
-    Ok(())
 }
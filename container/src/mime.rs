@@ -0,0 +1,128 @@
+use std::path::Path;
+
+/// Source containers whose extension is kept on the downloaded copy, so
+/// ffmpeg picks the right demuxer for formats it can't sniff.
+const INPUT_TYPES: [(&str, &str); 10] = [
+    ("mp4", "video/mp4"),
+    ("m4v", "video/x-m4v"),
+    ("mov", "video/quicktime"),
+    ("mkv", "video/x-matroska"),
+    ("webm", "video/webm"),
+    ("avi", "video/x-msvideo"),
+    ("ts", "video/mp2t"),
+    ("mpg", "video/mpeg"),
+    ("flv", "video/x-flv"),
+    ("3gp", "video/3gpp"),
+];
+
+/// Extension for the downloaded source: the key's own if it's a known
+/// container, else one matching the object's `Content-Type`, else `mp4`.
+pub fn input_extension(key: &str, content_type: Option<&str>) -> &'static str {
+    let ext = Path::new(key)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    INPUT_TYPES
+        .iter()
+        .find(|(known, _)| *known == ext)
+        .or_else(|| INPUT_TYPES.iter().find(|(_, mime)| *mime == essence))
+        .map(|(known, _)| *known)
+        .unwrap_or("mp4")
+}
+
+/// MIME type for an output, from its file extension (or a bare extension).
+/// Unknown types fall back to `application/octet-stream` so browsers
+/// download rather than misinterpret them.
+pub fn content_type_for(path_or_ext: &str) -> &'static str {
+    let ext = Path::new(path_or_ext)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or(path_or_ext)
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "mpd" => "application/dash+xml",
+        "m4s" => "video/mp4",
+        "ts" => "video/mp2t",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "json" => "application/json",
+        "vtt" => "text/vtt",
+        "log" | "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_output_type_maps_to_its_mime_type() {
+        for (path, expected) in [
+            ("clip/720p.mp4", "video/mp4"),
+            ("clip/720p.M4V", "video/mp4"),
+            ("clip/preview.webm", "video/webm"),
+            ("clip/hls/index.m3u8", "application/vnd.apple.mpegurl"),
+            ("clip/dash/manifest.mpd", "application/dash+xml"),
+            ("clip/dash/chunk-0-00001.m4s", "video/mp4"),
+            ("clip/hls/segment0.ts", "video/mp2t"),
+            ("clip/audio.mp3", "audio/mpeg"),
+            ("clip/audio.m4a", "audio/mp4"),
+            ("clip/thumb.jpg", "image/jpeg"),
+            ("clip/thumb.jpeg", "image/jpeg"),
+            ("clip/storyboard/sprite.png", "image/png"),
+            ("clip/preview.gif", "image/gif"),
+            ("clip/_SUCCESS.json", "application/json"),
+            ("clip/storyboard/storyboard.vtt", "text/vtt"),
+            ("clip/logs/720p.log", "text/plain"),
+            ("clip/notes.txt", "text/plain"),
+            ("clip/720p.mp4.sha256", "application/octet-stream"),
+            ("clip/_SUCCESS", "application/octet-stream"),
+        ] {
+            assert_eq!(content_type_for(path), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn bare_extensions_map_too() {
+        assert_eq!(content_type_for("gif"), "image/gif");
+        assert_eq!(content_type_for("MP4"), "video/mp4");
+    }
+
+    #[test]
+    fn input_extension_prefers_a_known_key_extension() {
+        assert_eq!(
+            input_extension("uploads/clip.MOV", Some("video/mp4")),
+            "mov"
+        );
+        assert_eq!(input_extension("a.b/clip.mkv", None), "mkv");
+        assert_eq!(input_extension("clip.3gp", None), "3gp");
+    }
+
+    #[test]
+    fn input_extension_falls_back_to_the_content_type() {
+        assert_eq!(input_extension("upload-1234", Some("video/webm")), "webm");
+        assert_eq!(
+            input_extension("clip.bin", Some("Video/QuickTime; charset=binary")),
+            "mov"
+        );
+        assert_eq!(
+            input_extension("clip", Some("application/octet-stream")),
+            "mp4"
+        );
+        assert_eq!(input_extension("clip.exe", None), "mp4");
+        assert_eq!(input_extension("", None), "mp4");
+    }
+}
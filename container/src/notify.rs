@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use aws_sdk_sns::Client as SnsClient;
+use serde::Serialize;
+
+use crate::timing::JobTimings;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Success,
+    /// Some rungs failed; the rest were uploaded.
+    Partial,
+    Rejected,
+    Failed,
+}
+
+/// Emitted once at the end of every job, whatever the outcome.
+#[derive(Clone, Debug, Serialize)]
+pub struct CompletionNotice {
+    pub event: &'static str,
+    pub source_key: String,
+    /// The `title` the source was uploaded with, else its key.
+    pub title: String,
+    /// The slug the source's stem became in output keys, under
+    /// `SANITIZE_OUTPUT_KEYS`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_stem: Option<String>,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Machine-readable failure class, e.g. `corrupt-input`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<&'static str>,
+    /// `auto` or `manual`, as passed by the driver in `LAUNCH_TRIGGER`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    /// Allowlisted user metadata from the source object.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "JobTimings::is_empty")]
+    pub timings: JobTimings,
+    /// SHA-256 of each uploaded rendition by rung, under `CHECKSUM_MODE`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+    /// Rungs that failed in a `partial` job.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_rungs: Vec<String>,
+}
+
+impl CompletionNotice {
+    pub fn new(source_key: &str, status: JobStatus, reason: Option<String>) -> Self {
+        Self {
+            event: "job_complete",
+            source_key: source_key.to_string(),
+            title: source_key.to_string(),
+            output_stem: None,
+            status,
+            reason,
+            category: None,
+            trigger: env::var("LAUNCH_TRIGGER").ok(),
+            metadata: BTreeMap::new(),
+            timings: JobTimings::default(),
+            checksums: BTreeMap::new(),
+            missing_rungs: Vec::new(),
+        }
+    }
+
+    pub fn with_category(mut self, category: Option<&'static str>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        if let Some(title) = title {
+            self.title = title;
+        }
+        self
+    }
+
+    pub fn with_output_stem(mut self, stem: Option<String>) -> Self {
+        self.output_stem = stem;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_timings(mut self, timings: JobTimings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    pub fn with_checksums(mut self, checksums: BTreeMap<String, String>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    pub fn with_missing_rungs(mut self, rungs: Vec<String>) -> Self {
+        self.missing_rungs = rungs;
+        self
+    }
+}
+
+/// Emitted as soon as the quick proxy from `PREVIEW_FIRST` is uploaded.
+#[derive(Clone, Debug, Serialize)]
+pub struct PreviewReady {
+    pub event: &'static str,
+    pub source_key: String,
+    pub bucket: String,
+    pub key: String,
+}
+
+impl PreviewReady {
+    pub fn new(source_key: &str, bucket: &str, key: &str) -> Self {
+        Self {
+            event: "preview_ready",
+            source_key: source_key.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        }
+    }
+}
+
+/// Logs the event as a JSON line and, when `COMPLETION_TOPIC_ARN` is set,
+/// publishes it to SNS. Publishing failures are logged, not fatal.
+pub async fn emit<T: Serialize>(sns_client: &SnsClient, event: &T) {
+    let body = match serde_json::to_string(event) {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("Failed to serialize notification: {}", e);
+            return;
+        }
+    };
+    println!("{}", body);
+
+    if let Ok(topic_arn) = env::var("COMPLETION_TOPIC_ARN")
+        && let Err(e) = sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(body)
+            .send()
+            .await
+    {
+        eprintln!("Failed to publish notification: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notices_are_titled_by_key_unless_given_a_title() {
+        let notice = CompletionNotice::new("upload-1234.mp4", JobStatus::Success, None);
+        assert_eq!(notice.title, "upload-1234.mp4");
+        let notice = notice.with_title(None);
+        assert_eq!(notice.title, "upload-1234.mp4");
+        let notice = notice.with_title(Some("Launch keynote".to_string()));
+        assert_eq!(notice.title, "Launch keynote");
+    }
+}
@@ -0,0 +1,233 @@
+use std::env;
+
+use crate::error::TranscodeError;
+use crate::ffmpeg;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipFormat {
+    Gif,
+    Mp4,
+}
+
+impl ClipFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+/// Short looping clip used for social previews.
+#[derive(Clone, Debug)]
+pub struct PreviewClipSettings {
+    pub format: ClipFormat,
+    pub start_secs: f64,
+    pub length_secs: f64,
+    pub width: u32,
+    pub fps: u32,
+}
+
+impl PreviewClipSettings {
+    pub fn from_env() -> Result<Self, String> {
+        let format = match env::var("PREVIEW_CLIP_FORMAT").as_deref() {
+            Ok("gif") | Err(_) => ClipFormat::Gif,
+            Ok("mp4") => ClipFormat::Mp4,
+            Ok(other) => return Err(format!("Unknown PREVIEW_CLIP_FORMAT: {}", other)),
+        };
+        Ok(Self {
+            format,
+            start_secs: parse_secs("PREVIEW_CLIP_START", 0.0)?,
+            length_secs: parse_secs("PREVIEW_CLIP_LENGTH", 3.0)?,
+            width: 480,
+            fps: 12,
+        })
+    }
+
+    /// Start and length clamped so the clip lies entirely within the source.
+    pub fn clamp_to(&self, duration_secs: f64) -> (f64, f64) {
+        let length = self.length_secs.min(duration_secs).max(0.0);
+        let start = self
+            .start_secs
+            .clamp(0.0, (duration_secs - length).max(0.0));
+        (start, length)
+    }
+}
+
+fn parse_secs(name: &str, default: f64) -> Result<f64, String> {
+    match env::var(name) {
+        Ok(v) => v
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .ok_or_else(|| format!("Invalid {}: {}", name, v)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// The two ffmpeg invocations for an optimized GIF: the first generates a
+/// palette tuned to the clip, the second maps the clip onto it.
+pub fn gif_commands(
+    input: &str,
+    palette: &str,
+    output: &str,
+    start: f64,
+    length: f64,
+    width: u32,
+    fps: u32,
+) -> [Vec<String>; 2] {
+    let filters = format!("fps={},scale={}:-1:flags=lanczos", fps, width);
+    let start = format!("{:.3}", start);
+    let length = format!("{:.3}", length);
+    let palettegen = [
+        "-ss",
+        &start,
+        "-t",
+        &length,
+        "-i",
+        input,
+        "-vf",
+        &format!("{},palettegen", filters),
+        "-y",
+        palette,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let paletteuse = [
+        "-ss",
+        &start,
+        "-t",
+        &length,
+        "-i",
+        input,
+        "-i",
+        palette,
+        "-lavfi",
+        &format!("{} [x]; [x][1:v] paletteuse", filters),
+        "-loop",
+        "0",
+        "-y",
+        output,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    [palettegen, paletteuse]
+}
+
+fn mp4_command(input: &str, output: &str, start: f64, length: f64, width: u32) -> Vec<String> {
+    [
+        "-ss",
+        &format!("{:.3}", start),
+        "-t",
+        &format!("{:.3}", length),
+        "-i",
+        input,
+        "-vf",
+        &format!("scale={}:-2", width),
+        "-an",
+        "-c:v",
+        "libx264",
+        "-pix_fmt",
+        "yuv420p",
+        "-movflags",
+        "+faststart",
+        "-y",
+        output,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+pub fn generate_clip(
+    input: &str,
+    output: &str,
+    palette: &str,
+    settings: &PreviewClipSettings,
+    duration_secs: f64,
+) -> Result<(), TranscodeError> {
+    let (start, length) = settings.clamp_to(duration_secs);
+    let commands = match settings.format {
+        ClipFormat::Gif => gif_commands(
+            input,
+            palette,
+            output,
+            start,
+            length,
+            settings.width,
+            settings.fps,
+        )
+        .to_vec(),
+        ClipFormat::Mp4 => vec![mp4_command(input, output, start, length, settings.width)],
+    };
+    for args in commands {
+        ffmpeg::run_ffmpeg(&args, None, None)?;
+    }
+    if settings.format == ClipFormat::Gif {
+        let _ = std::fs::remove_file(palette);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_is_a_palettegen_then_paletteuse_pass_over_the_same_span() {
+        let [palettegen, paletteuse] =
+            gif_commands("in.mp4", "palette.png", "out.gif", 2.0, 3.5, 480, 12);
+        assert_eq!(
+            palettegen,
+            [
+                "-ss",
+                "2.000",
+                "-t",
+                "3.500",
+                "-i",
+                "in.mp4",
+                "-vf",
+                "fps=12,scale=480:-1:flags=lanczos,palettegen",
+                "-y",
+                "palette.png",
+            ]
+        );
+        assert_eq!(
+            paletteuse,
+            [
+                "-ss",
+                "2.000",
+                "-t",
+                "3.500",
+                "-i",
+                "in.mp4",
+                "-i",
+                "palette.png",
+                "-lavfi",
+                "fps=12,scale=480:-1:flags=lanczos [x]; [x][1:v] paletteuse",
+                "-loop",
+                "0",
+                "-y",
+                "out.gif",
+            ]
+        );
+    }
+
+    #[test]
+    fn clip_is_clamped_to_the_source() {
+        let settings = PreviewClipSettings {
+            format: ClipFormat::Gif,
+            start_secs: 8.0,
+            length_secs: 3.0,
+            width: 480,
+            fps: 12,
+        };
+        assert_eq!(settings.clamp_to(60.0), (8.0, 3.0));
+        // Pulled back so the whole clip fits.
+        assert_eq!(settings.clamp_to(10.0), (7.0, 3.0));
+        // Shorter than the clip: all of it.
+        assert_eq!(settings.clamp_to(2.0), (0.0, 2.0));
+    }
+}
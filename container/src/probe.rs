@@ -0,0 +1,180 @@
+use std::process::Command;
+
+use crate::error::TranscodeError;
+
+/// Returns the source duration in seconds as reported by ffprobe.
+pub fn probe_duration(input: &str) -> Result<f64, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Probe(format!(
+            "exited with status: {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| TranscodeError::Probe(format!("unexpected duration: {}", stdout.trim())))
+}
+
+/// Overall bitrate of the source in bits per second, from the container.
+/// `None` when ffprobe can't tell, e.g. for some raw streams.
+pub fn probe_bitrate(input: &str) -> Result<Option<u64>, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=bit_rate",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Probe(format!(
+            "exited with status: {}",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|bps| *bps > 0))
+}
+
+/// Returns the frame rate of the first video stream, parsed from ffprobe's
+/// `r_frame_rate` fraction (e.g. `60000/1001`).
+pub fn probe_frame_rate(input: &str) -> Result<f64, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=r_frame_rate",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Probe(format!(
+            "exited with status: {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_frame_rate(stdout.trim())
+        .ok_or_else(|| TranscodeError::Probe(format!("unexpected frame rate: {}", stdout.trim())))
+}
+
+fn parse_frame_rate(value: &str) -> Option<f64> {
+    let rate = match value.split_once('/') {
+        Some((num, den)) => {
+            let den = den.parse::<f64>().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num.parse::<f64>().ok()? / den
+        }
+        None => value.parse::<f64>().ok()?,
+    };
+    (rate.is_finite() && rate > 0.0).then_some(rate)
+}
+
+/// Returns the height in pixels of the first video stream.
+pub fn probe_height(input: &str) -> Result<u32, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=height",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Probe(format!(
+            "exited with status: {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| TranscodeError::Probe(format!("unexpected height: {}", stdout.trim())))
+}
+
+/// False for an empty file or one ffprobe can't find a video stream in,
+/// as left behind by a client that disconnected mid-upload.
+pub fn has_video_stream(input: &str) -> Result<bool, TranscodeError> {
+    if std::fs::metadata(input)?.len() == 0 {
+        return Ok(false);
+    }
+    Ok(matches!(probe_codec(input, "v"), Ok(Some(_))))
+}
+
+/// Returns the codec of the first stream of a type (`v` or `a`), or `None`
+/// if the source has no such stream.
+pub fn probe_codec(input: &str, stream: &str) -> Result<Option<String>, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            &format!("{}:0", stream),
+            "-show_entries",
+            "stream=codec_name",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Probe(format!(
+            "exited with status: {}",
+            output.status
+        )));
+    }
+    let codec = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!codec.is_empty()).then_some(codec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::JobWorkspace;
+
+    #[test]
+    fn empty_sources_have_no_video_stream() {
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        let empty = workspace.file("input.mp4");
+        std::fs::write(&empty, b"").unwrap();
+        assert!(!has_video_stream(&empty).unwrap());
+    }
+
+    #[test]
+    fn a_missing_source_is_an_error_not_empty() {
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        assert!(has_video_stream(&workspace.file("never-downloaded.mp4")).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use aws_sdk_s3::Client as S3Client;
+use serde::Deserialize;
+
+use crate::error::TranscodeError;
+use crate::ffmpeg::{OutputFormat, Rung};
+
+/// An encoding profile (`PROFILE_KEY`), stored as JSON or YAML in the source
+/// bucket. Every field is optional; anything left out keeps its env/default
+/// value. Unknown fields are rejected so a typo can't silently do nothing.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub rungs: Option<Vec<Rung>>,
+    pub video_codec: Option<String>,
+    pub preset: Option<String>,
+    pub crf: Option<u32>,
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<String>,
+    pub output_format: Option<OutputFormat>,
+}
+
+impl Profile {
+    /// Parses a profile, as YAML when the key ends in `.yaml`/`.yml` and as
+    /// JSON otherwise.
+    pub fn parse(key: &str, bytes: &[u8]) -> Result<Self, String> {
+        let profile: Self = if key.ends_with(".yaml") || key.ends_with(".yml") {
+            serde_yaml::from_slice(bytes).map_err(|e| format!("Invalid profile {}: {}", key, e))?
+        } else {
+            serde_json::from_slice(bytes).map_err(|e| format!("Invalid profile {}: {}", key, e))?
+        };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(rungs) = &self.rungs {
+            if rungs.is_empty() {
+                return Err("Profile rungs must not be empty".to_string());
+            }
+            let mut names = HashSet::new();
+            for rung in rungs {
+                if !names.insert(rung.name.as_str()) {
+                    return Err(format!("Duplicate rung in profile: {}", rung.name));
+                }
+                // libx264 with yuv420p needs even dimensions.
+                if rung.width == 0
+                    || rung.height == 0
+                    || rung.width % 2 != 0
+                    || rung.height % 2 != 0
+                {
+                    return Err(format!(
+                        "Rung {} needs a non-zero, even width and height",
+                        rung.name
+                    ));
+                }
+                if rung.video_bitrate.is_empty() {
+                    return Err(format!("Rung {} has no video_bitrate", rung.name));
+                }
+            }
+        }
+        if self.crf.is_some_and(|crf| crf > 51) {
+            return Err("Profile crf must be between 0 and 51".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Downloads the raw profile object.
+pub async fn fetch(client: &S3Client, bucket: &str, key: &str) -> Result<Vec<u8>, TranscodeError> {
+    let object = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| TranscodeError::S3Download {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            source: Box::new(e.into()),
+        })?;
+    Ok(object.body.collect().await?.to_vec())
+}
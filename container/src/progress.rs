@@ -0,0 +1,128 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+use aws_sdk_sns::Client as SnsClient;
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
+
+/// Periodic progress publishing, enabled by `PROGRESS_TOPIC_ARN`.
+#[derive(Clone, Debug)]
+pub struct ProgressSettings {
+    pub topic_arn: String,
+    /// Minimum time between two published updates (`PROGRESS_INTERVAL_SECS`).
+    pub interval: Duration,
+}
+
+impl ProgressSettings {
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(topic_arn) = env::var("PROGRESS_TOPIC_ARN") else {
+            return Ok(None);
+        };
+        let interval_secs = match env::var("PROGRESS_INTERVAL_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or_else(|| format!("Invalid PROGRESS_INTERVAL_SECS: {}", v))?,
+            Err(_) => 10,
+        };
+        Ok(Some(Self {
+            topic_arn,
+            interval: Duration::from_secs(interval_secs),
+        }))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProgressEvent {
+    pub event: &'static str,
+    pub source_key: String,
+    /// `downloading` or `transcoding`.
+    pub phase: &'static str,
+    pub rung: String,
+    /// 1-based position of `rung` in the ladder.
+    pub rung_index: usize,
+    pub rung_count: usize,
+    /// Progress through the current rung, or through the download.
+    pub percent: f64,
+    pub elapsed_secs: u64,
+}
+
+/// Forwards progress from the (blocking) ffmpeg loop to a background task
+/// that publishes to SNS at most once per interval.
+pub struct ProgressReporter {
+    source_key: String,
+    started: Instant,
+    sender: UnboundedSender<ProgressEvent>,
+    publisher: JoinHandle<()>,
+}
+
+impl ProgressReporter {
+    pub fn spawn(sns_client: SnsClient, settings: ProgressSettings, source_key: &str) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ProgressEvent>();
+        let publisher = tokio::spawn(async move {
+            let mut last_published: Option<Instant> = None;
+            while let Some(event) = receiver.recv().await {
+                let due = last_published.is_none_or(|at| at.elapsed() >= settings.interval);
+                if !due && event.percent < 100.0 {
+                    continue;
+                }
+                last_published = Some(Instant::now());
+                let Ok(body) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if let Err(e) = sns_client
+                    .publish()
+                    .topic_arn(&settings.topic_arn)
+                    .message(body)
+                    .send()
+                    .await
+                {
+                    eprintln!("Failed to publish progress: {:?}", e);
+                }
+            }
+        });
+        Self {
+            source_key: source_key.to_string(),
+            started: Instant::now(),
+            sender,
+            publisher,
+        }
+    }
+
+    /// Progress through the source download, before any rung starts.
+    pub fn report_download(&self, percent: f64) {
+        self.send("downloading", "", 0, 0, percent);
+    }
+
+    pub fn report(&self, rung: &str, rung_index: usize, rung_count: usize, percent: f64) {
+        self.send("transcoding", rung, rung_index, rung_count, percent);
+    }
+
+    fn send(
+        &self,
+        phase: &'static str,
+        rung: &str,
+        rung_index: usize,
+        rung_count: usize,
+        percent: f64,
+    ) {
+        let _ = self.sender.send(ProgressEvent {
+            event: "progress",
+            source_key: self.source_key.clone(),
+            phase,
+            rung: rung.to_string(),
+            rung_index,
+            rung_count,
+            percent: percent.clamp(0.0, 100.0),
+            elapsed_secs: self.started.elapsed().as_secs(),
+        });
+    }
+
+    /// Waits for queued updates to be published.
+    pub async fn finish(self) {
+        drop(self.sender);
+        let _ = self.publisher.await;
+    }
+}
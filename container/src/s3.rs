@@ -0,0 +1,563 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use aws_config::SdkConfig;
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::StorageClass;
+use serde::Serialize;
+
+use crate::config::{SOURCE_BUCKET, env_flag};
+use crate::destination::Encryption;
+use crate::error::TranscodeError;
+
+/// S3 clients for the destination buckets. A bucket in another region than
+/// the default client gets its own client, so uploads go straight to the
+/// right endpoint instead of failing with a redirect.
+pub struct DestinationClients {
+    default: S3Client,
+    /// Only buckets outside the default client's region.
+    regional: HashMap<String, S3Client>,
+}
+
+impl DestinationClients {
+    /// Looks each bucket's region up once. A bucket whose region can't be
+    /// read (e.g. no `s3:GetBucketLocation`) uses the default client.
+    pub async fn resolve<'a>(
+        default: &S3Client,
+        config: &SdkConfig,
+        buckets: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        let client_region = default
+            .config()
+            .region()
+            .map(|region| region.to_string())
+            .unwrap_or_default();
+        let mut regional = HashMap::new();
+        for bucket in buckets {
+            if regional.contains_key(bucket) {
+                continue;
+            }
+            let region = match default.get_bucket_location().bucket(bucket).send().await {
+                Ok(out) => bucket_region(out.location_constraint().map(|c| c.as_str())),
+                Err(e) => {
+                    eprintln!("Could not look up the region of {}: {:?}", bucket, e);
+                    continue;
+                }
+            };
+            if region == client_region {
+                continue;
+            }
+            println!(
+                "{}",
+                serde_json::json!({
+                    "level": "warning",
+                    "event": "cross_region_destination",
+                    "bucket": bucket,
+                    "bucket_region": region,
+                    "client_region": client_region,
+                })
+            );
+            let conf = aws_sdk_s3::config::Builder::from(config)
+                .region(Region::new(region))
+                .build();
+            regional.insert(bucket.to_string(), S3Client::from_conf(conf));
+        }
+        Self {
+            default: default.clone(),
+            regional,
+        }
+    }
+
+    pub fn get(&self, bucket: &str) -> &S3Client {
+        self.regional.get(bucket).unwrap_or(&self.default)
+    }
+
+    /// Every bucket through `client`, without looking any regions up.
+    #[cfg(test)]
+    pub fn single(client: S3Client) -> Self {
+        Self {
+            default: client,
+            regional: HashMap::new(),
+        }
+    }
+}
+
+/// Region from a `GetBucketLocation` constraint, which is empty for
+/// us-east-1 and the legacy `EU` for eu-west-1.
+pub fn bucket_region(constraint: Option<&str>) -> String {
+    match constraint.unwrap_or_default() {
+        "" => "us-east-1".to_string(),
+        "EU" => "eu-west-1".to_string(),
+        region => region.to_string(),
+    }
+}
+
+/// What a HEAD request says about an object.
+#[derive(Clone, Debug)]
+pub struct ObjectHead {
+    pub size: u64,
+    pub content_type: Option<String>,
+    /// User metadata, keys lowercased and without the `x-amz-meta-` prefix.
+    pub metadata: HashMap<String, String>,
+}
+
+pub async fn head_object(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+) -> Result<ObjectHead, TranscodeError> {
+    let head = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.as_service_error().is_some_and(|e| e.is_not_found()) {
+                return missing(bucket, key);
+            }
+            TranscodeError::S3Download {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                source: Box::new(e.into()),
+            }
+        })?;
+    Ok(ObjectHead {
+        size: head.content_length().unwrap_or(0).max(0) as u64,
+        content_type: head.content_type().map(str::to_string),
+        metadata: head.metadata().cloned().unwrap_or_default(),
+    })
+}
+
+/// Download progress is logged each time it passes a multiple of this.
+const DOWNLOAD_LOG_STEP: u64 = 5;
+
+/// Turns a stream of byte counts into whole-percent milestones, one per
+/// `step`, so progress can be logged without a line per chunk.
+struct PercentThrottle {
+    step: u64,
+    next: u64,
+}
+
+impl PercentThrottle {
+    fn new(step: u64) -> Self {
+        Self { step, next: step }
+    }
+
+    /// The percentage reached, if it passed the next milestone.
+    fn crossed(&mut self, done: u64, total: u64) -> Option<u64> {
+        if total == 0 {
+            return None;
+        }
+        let percent = (done.min(total) * 100) / total;
+        if percent < self.next {
+            return None;
+        }
+        self.next = (percent / self.step + 1) * self.step;
+        Some(percent)
+    }
+}
+
+fn missing(bucket: &str, key: &str) -> TranscodeError {
+    TranscodeError::SourceMissing {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+    }
+}
+
+/// Streams an object to `destination`, logging progress every few percent
+/// and passing the same milestones to `on_progress`.
+pub async fn download_from_s3(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    destination: &str,
+    on_progress: Option<&dyn Fn(f64)>,
+) -> Result<(), TranscodeError> {
+    let mut object = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.as_service_error().is_some_and(|e| e.is_no_such_key()) {
+                return missing(bucket, key);
+            }
+            TranscodeError::S3Download {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                source: Box::new(e.into()),
+            }
+        })?;
+    let mut file = File::create(destination)?;
+    let total = object.content_length().unwrap_or(0).max(0) as u64;
+    let mut throttle = PercentThrottle::new(DOWNLOAD_LOG_STEP);
+    let mut done: u64 = 0;
+
+    while let Some(bytes) = object.body.try_next().await? {
+        file.write_all(&bytes)?;
+        done += bytes.len() as u64;
+        if let Some(percent) = throttle.crossed(done, total) {
+            println!("Downloaded {}% ({} of {} bytes)", percent, done, total);
+            if let Some(on_progress) = on_progress {
+                on_progress(percent as f64);
+            }
+        }
+    }
+
+    println!(
+        "Downloaded video from S3 ({} bytes)",
+        std::fs::metadata(destination)?.len()
+    );
+    Ok(())
+}
+
+/// What to do when a rendition's key is already taken (`ON_EXISTING`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnExisting {
+    Overwrite,
+    /// Leave the existing object and don't encode the rung.
+    Skip,
+    /// Fail the job before encoding the rung.
+    Fail,
+    /// Write alongside it under a timestamped key.
+    Version,
+}
+
+impl OnExisting {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("ON_EXISTING") {
+            Ok(v) => match v.to_ascii_lowercase().as_str() {
+                "overwrite" => Ok(Self::Overwrite),
+                "skip" => Ok(Self::Skip),
+                "fail" => Ok(Self::Fail),
+                "version" => Ok(Self::Version),
+                _ => Err(format!("Unknown ON_EXISTING: {}", v)),
+            },
+            Err(_) => Ok(Self::Overwrite),
+        }
+    }
+}
+
+/// The key to write a rendition to under `policy`, or `None` to skip it.
+/// Only `Overwrite` avoids the HEAD request.
+pub async fn resolve_existing(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    policy: OnExisting,
+) -> Result<Option<String>, TranscodeError> {
+    if policy == OnExisting::Overwrite || !object_exists(client, bucket, key).await? {
+        return Ok(Some(key.to_string()));
+    }
+    match policy {
+        OnExisting::Overwrite => Ok(Some(key.to_string())),
+        OnExisting::Skip => Ok(None),
+        OnExisting::Fail => Err(TranscodeError::OutputExists {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        }),
+        OnExisting::Version => {
+            let stamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            Ok(Some(versioned_key(key, stamp)))
+        }
+    }
+}
+
+/// `a/1080p.mp4` → `a/1080p-<stamp>.mp4`. The suffix goes before the
+/// extension so the content type still follows from the key.
+pub fn versioned_key(key: &str, stamp: u64) -> String {
+    let name_start = key.rfind('/').map_or(0, |i| i + 1);
+    match key[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{}-{}{}", &key[..dot], stamp, &key[dot..])
+        }
+        _ => format!("{}-{}", key, stamp),
+    }
+}
+
+async fn object_exists(client: &S3Client, bucket: &str, key: &str) -> Result<bool, TranscodeError> {
+    match client.head_object().bucket(bucket).key(key).send().await {
+        Ok(_) => Ok(true),
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+        Err(e) => Err(TranscodeError::S3Download {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            source: Box::new(e.into()),
+        }),
+    }
+}
+
+/// Per-object settings for an upload beyond its content.
+#[derive(Clone, Copy, Debug)]
+pub struct UploadOptions<'a> {
+    /// URL-encoded `x-amz-tagging` value.
+    pub tagging: Option<&'a str>,
+    pub storage_class: &'a str,
+    pub encryption: &'a Encryption,
+    /// Under `ENFORCE_TENANT_PREFIX`, the only prefix keys may be written to.
+    pub tenant: Option<&'a str>,
+    /// User metadata copied from the source.
+    pub metadata: Option<&'a BTreeMap<String, String>>,
+}
+
+pub async fn upload_to_s3(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    file_path: &str,
+    content_type: &str,
+    options: UploadOptions<'_>,
+) -> Result<(), TranscodeError> {
+    let body = ByteStream::from_path(Path::new(file_path)).await?;
+    put_object(client, bucket, key, body, content_type, options).await
+}
+
+/// Like `upload_to_s3`, for a small object built in memory.
+pub async fn upload_bytes_to_s3(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    bytes: Vec<u8>,
+    content_type: &str,
+    options: UploadOptions<'_>,
+) -> Result<(), TranscodeError> {
+    put_object(
+        client,
+        bucket,
+        key,
+        ByteStream::from(bytes),
+        content_type,
+        options,
+    )
+    .await
+}
+
+async fn put_object(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    body: ByteStream,
+    content_type: &str,
+    options: UploadOptions<'_>,
+) -> Result<(), TranscodeError> {
+    if let Some(tenant) = options.tenant
+        && !key.starts_with(&format!("{}/", tenant))
+    {
+        return Err(TranscodeError::OutsideTenant {
+            key: key.to_string(),
+            tenant: tenant.to_string(),
+        });
+    }
+    let (sse, kms_key_id) = options.encryption.params();
+    client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(body)
+        .content_type(content_type)
+        .set_tagging(options.tagging.map(str::to_string))
+        .storage_class(StorageClass::from(options.storage_class))
+        .set_server_side_encryption(sse)
+        .set_ssekms_key_id(kms_key_id.map(str::to_string))
+        .set_metadata(
+            options
+                .metadata
+                .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect()),
+        )
+        .send()
+        .await
+        .map_err(|e| TranscodeError::S3Upload {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            source: Box::new(e.into()),
+        })?;
+    Ok(())
+}
+
+/// Safety rail in front of every S3 delete. An empty or root prefix is
+/// always refused, and anything outside the intake bucket (i.e. permanent
+/// outputs) needs `ALLOW_DESTRUCTIVE=true` as well.
+pub fn confirm_destructive(bucket: &str, prefix: &str) -> Result<(), TranscodeError> {
+    let refuse = |reason| {
+        Err(TranscodeError::DestructiveRefused {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            reason,
+        })
+    };
+    if prefix.trim_matches('/').is_empty() {
+        return refuse("empty or root prefix");
+    }
+    if bucket != SOURCE_BUCKET && !env_flag("ALLOW_DESTRUCTIVE") {
+        return refuse("permanent bucket; set ALLOW_DESTRUCTIVE=true to allow");
+    }
+    Ok(())
+}
+
+pub async fn delete_from_s3(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+) -> Result<(), TranscodeError> {
+    confirm_destructive(bucket, key)?;
+    client
+        .delete_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| TranscodeError::S3Delete {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            source: Box::new(e.into()),
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_s3::FakeS3;
+    use crate::workspace::JobWorkspace;
+
+    #[tokio::test]
+    async fn uploads_outside_the_tenant_are_refused() {
+        let s3 = FakeS3::start().await;
+        let options = UploadOptions {
+            tagging: None,
+            storage_class: "STANDARD",
+            encryption: &Encryption::None,
+            tenant: Some("acme"),
+            metadata: None,
+        };
+        for key in [
+            "other/clip/720p.mp4",
+            "acme-evil/clip/720p.mp4",
+            "clip/720p.mp4",
+        ] {
+            let result = upload_bytes_to_s3(
+                &s3.client(),
+                "perm",
+                key,
+                b"x".to_vec(),
+                "video/mp4",
+                options,
+            )
+            .await;
+            assert!(
+                matches!(result, Err(TranscodeError::OutsideTenant { .. })),
+                "{}",
+                key
+            );
+        }
+        assert!(s3.keys().is_empty());
+
+        upload_bytes_to_s3(
+            &s3.client(),
+            "perm",
+            "acme/clip/720p.mp4",
+            b"x".to_vec(),
+            "video/mp4",
+            options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(s3.keys(), ["perm/acme/clip/720p.mp4"]);
+    }
+
+    #[test]
+    fn throttle_reports_each_step_once() {
+        let mut throttle = PercentThrottle::new(5);
+        let total = 1000;
+        let reported: Vec<u64> = (1..=total)
+            .step_by(7)
+            .chain([total])
+            .filter_map(|done| throttle.crossed(done, total))
+            .collect();
+        assert_eq!(reported.first(), Some(&5));
+        assert_eq!(reported.last(), Some(&100));
+        assert_eq!(reported.len(), 20);
+        assert!(reported.windows(2).all(|w| w[1] / 5 > w[0] / 5));
+    }
+
+    #[test]
+    fn throttle_jumps_past_skipped_steps() {
+        let mut throttle = PercentThrottle::new(5);
+        assert_eq!(throttle.crossed(3, 100), None);
+        assert_eq!(throttle.crossed(37, 100), Some(37));
+        assert_eq!(throttle.crossed(39, 100), None);
+        assert_eq!(throttle.crossed(40, 100), Some(40));
+        assert_eq!(throttle.crossed(150, 100), Some(100));
+        assert_eq!(throttle.crossed(100, 100), None);
+        assert_eq!(PercentThrottle::new(5).crossed(10, 0), None);
+    }
+
+    #[test]
+    fn bucket_locations_normalize_to_region_names() {
+        assert_eq!(bucket_region(None), "us-east-1");
+        assert_eq!(bucket_region(Some("")), "us-east-1");
+        assert_eq!(bucket_region(Some("EU")), "eu-west-1");
+        assert_eq!(bucket_region(Some("ap-south-1")), "ap-south-1");
+        assert_eq!(bucket_region(Some("us-west-2")), "us-west-2");
+    }
+
+    #[test]
+    fn versioned_keys_keep_the_extension_last() {
+        assert_eq!(versioned_key("clip/1080p.mp4", 42), "clip/1080p-42.mp4");
+        assert_eq!(
+            versioned_key("a.b/clip/dash/manifest.mpd", 7),
+            "a.b/clip/dash/manifest-7.mpd"
+        );
+        assert_eq!(versioned_key("clip/_SUCCESS", 7), "clip/_SUCCESS-7");
+        assert_eq!(versioned_key("clip/.hidden", 7), "clip/.hidden-7");
+        assert_eq!(versioned_key("clip_720p.mp4", 7), "clip_720p-7.mp4");
+    }
+
+    #[tokio::test]
+    async fn missing_sources_are_told_apart_from_failed_requests() {
+        let s3 = FakeS3::start().await;
+        s3.put("uploads", "gone-flaky.mp4", b"video");
+        s3.fail("uploads", "gone-flaky.mp4");
+        let client = s3.client();
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        let destination = workspace.file("input.mp4");
+
+        let missing = download_from_s3(&client, "uploads", "gone.mp4", &destination, None).await;
+        assert!(matches!(
+            missing,
+            Err(TranscodeError::SourceMissing { ref key, .. }) if key == "gone.mp4"
+        ));
+        let missing = head_object(&client, "uploads", "gone.mp4").await;
+        assert!(matches!(missing, Err(TranscodeError::SourceMissing { .. })));
+
+        let flaky =
+            download_from_s3(&client, "uploads", "gone-flaky.mp4", &destination, None).await;
+        assert!(matches!(flaky, Err(TranscodeError::S3Download { .. })));
+        let flaky = head_object(&client, "uploads", "gone-flaky.mp4").await;
+        assert!(matches!(flaky, Err(TranscodeError::S3Download { .. })));
+    }
+
+    #[tokio::test]
+    async fn downloads_write_the_whole_object() {
+        let s3 = FakeS3::start().await;
+        s3.put("uploads", "clip.mp4", b"video bytes");
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        let destination = workspace.file("input.mp4");
+
+        download_from_s3(&s3.client(), "uploads", "clip.mp4", &destination, None)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), b"video bytes");
+    }
+}
@@ -0,0 +1,129 @@
+use sha2::{Digest, Sha256};
+
+/// Longest slug kept, matching the limit on workspace file names.
+const MAX_SLUG_CHARS: usize = 100;
+
+/// Rewrites a file stem for `SANITIZE_OUTPUT_KEYS`: whitespace becomes `-`,
+/// accented Latin letters lose their accents, and anything else outside
+/// `[A-Za-z0-9._-]` is dropped. Runs of `-` collapse to one. A stem with
+/// nothing left, e.g. one written entirely in CJK, becomes `video-` plus
+/// the start of its SHA-256 so different sources still get different keys.
+pub fn slugify(stem: &str) -> String {
+    let mut slug = String::with_capacity(stem.len());
+    for c in stem.chars() {
+        if c.is_whitespace() || c == '-' {
+            if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        } else if c.is_ascii_alphanumeric() || matches!(c, '.' | '_') {
+            slug.push(c);
+        } else if let Some(folded) = fold_latin(c) {
+            slug.push_str(folded);
+        }
+    }
+    let slug: String = slug
+        .trim_matches('-')
+        .trim_start_matches('.')
+        .chars()
+        .take(MAX_SLUG_CHARS)
+        .collect();
+    if slug.is_empty() {
+        let digest: String = Sha256::digest(stem.as_bytes())
+            .iter()
+            .take(6)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        return format!("video-{}", digest);
+    }
+    slug
+}
+
+/// ASCII spelling of the Latin-1 and Latin Extended-A letters common in
+/// European file names.
+fn fold_latin(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ç' | 'ć' | 'č' => "c",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'ď' | 'đ' | 'ð' => "d",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'Ğ' => "G",
+        'ğ' => "g",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'İ' => "I",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'ı' => "i",
+        'Ł' => "L",
+        'ł' => "l",
+        'Ñ' | 'Ń' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ň' => "n",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ř' => "R",
+        'ř' => "r",
+        'Ś' | 'Š' | 'Ş' => "S",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'Ť' | 'Ţ' => "T",
+        'ť' | 'ţ' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' => "U",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_become_single_dashes() {
+        assert_eq!(slugify("my holiday clip"), "my-holiday-clip");
+        assert_eq!(slugify("  a \t - b  "), "a-b");
+        assert_eq!(slugify("already-a_slug.v2"), "already-a_slug.v2");
+    }
+
+    #[test]
+    fn accents_are_folded_and_other_symbols_dropped() {
+        assert_eq!(slugify("Café Crème"), "Cafe-Creme");
+        assert_eq!(slugify("Straße Łódź"), "Strasse-Lodz");
+        assert_eq!(slugify("clip (final) #2!"), "clip-final-2");
+        assert_eq!(slugify("..hidden"), "hidden");
+    }
+
+    #[test]
+    fn stems_with_nothing_left_get_a_distinct_fallback() {
+        let japanese = slugify("日本語の動画");
+        let chinese = slugify("中文视频");
+        for slug in [&japanese, &chinese] {
+            let hex = slug.strip_prefix("video-").unwrap();
+            assert_eq!(hex.len(), 12);
+            assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+        }
+        assert_ne!(japanese, chinese);
+        assert_eq!(slugify("日本語の動画"), japanese);
+        assert!(slugify("").starts_with("video-"));
+    }
+
+    #[test]
+    fn mixed_scripts_keep_the_latin_part() {
+        assert_eq!(slugify("東京 trip 2024"), "trip-2024");
+    }
+
+    #[test]
+    fn long_stems_are_capped() {
+        assert_eq!(slugify(&"a".repeat(150)).len(), MAX_SLUG_CHARS);
+        assert_eq!(slugify(&"é".repeat(150)), "e".repeat(MAX_SLUG_CHARS));
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::fmt::Write as _;
+
+use crate::error::TranscodeError;
+use crate::ffmpeg;
+
+/// Layout of the scrubbing sprite sheet.
+#[derive(Clone, Debug)]
+pub struct StoryboardSettings {
+    pub interval_secs: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub thumb_width: u32,
+    pub thumb_height: u32,
+}
+
+impl StoryboardSettings {
+    pub fn from_env() -> Result<Self, String> {
+        let interval_secs = match env::var("STORYBOARD_INTERVAL") {
+            Ok(v) => v
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid STORYBOARD_INTERVAL: {}", v))?,
+            Err(_) => 10,
+        };
+        let (columns, rows) = match env::var("STORYBOARD_GRID") {
+            Ok(v) => parse_grid(&v)?,
+            Err(_) => (10, 10),
+        };
+        Ok(Self {
+            interval_secs,
+            columns,
+            rows,
+            thumb_width: 160,
+            thumb_height: 90,
+        })
+    }
+
+    /// Interval actually used for a source of the given length. It is
+    /// stretched when needed so that every frame fits on the one sheet.
+    pub fn effective_interval(&self, duration_secs: f64) -> u32 {
+        let capacity = (self.columns * self.rows) as f64;
+        let needed = (duration_secs / capacity).ceil() as u32;
+        self.interval_secs.max(needed)
+    }
+}
+
+fn parse_grid(value: &str) -> Result<(u32, u32), String> {
+    let err = || format!("Invalid STORYBOARD_GRID (expected COLSxROWS): {}", value);
+    let (cols, rows) = value.split_once(['x', 'X']).ok_or_else(err)?;
+    let cols = cols.trim().parse::<u32>().map_err(|_| err())?;
+    let rows = rows.trim().parse::<u32>().map_err(|_| err())?;
+    if cols == 0 || rows == 0 {
+        return Err(err());
+    }
+    Ok((cols, rows))
+}
+
+/// Renders the sprite sheet with ffmpeg's `fps` and `tile` filters.
+pub fn generate_sprite(
+    input: &str,
+    output: &str,
+    settings: &StoryboardSettings,
+    interval_secs: u32,
+) -> Result<(), TranscodeError> {
+    let (w, h) = (settings.thumb_width, settings.thumb_height);
+    let filter = format!(
+        "fps=1/{interval},scale={w}:{h}:force_original_aspect_ratio=decrease,\
+         pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,tile={cols}x{rows}",
+        interval = interval_secs,
+        cols = settings.columns,
+        rows = settings.rows,
+    );
+    let args = [
+        "-i",
+        input,
+        "-vf",
+        filter.as_str(),
+        "-frames:v",
+        "1",
+        "-q:v",
+        "5",
+        "-y",
+        output,
+    ];
+    ffmpeg::run_ffmpeg(&args.map(str::to_string), None, None)
+}
+
+/// WebVTT cues mapping each interval to its tile in `sprite_name`.
+pub fn build_vtt(
+    sprite_name: &str,
+    settings: &StoryboardSettings,
+    interval_secs: u32,
+    duration_secs: f64,
+) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    let frames = (duration_secs / interval_secs as f64).ceil() as u32;
+    let frames = frames.min(settings.columns * settings.rows);
+    for i in 0..frames {
+        let start = (i * interval_secs) as f64;
+        let end = (((i + 1) * interval_secs) as f64).min(duration_secs);
+        let x = (i % settings.columns) * settings.thumb_width;
+        let y = (i / settings.columns) * settings.thumb_height;
+        let _ = writeln!(
+            vtt,
+            "{} --> {}\n{}#xywh={},{},{},{}\n",
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            sprite_name,
+            x,
+            y,
+            settings.thumb_width,
+            settings.thumb_height
+        );
+    }
+    vtt
+}
+
+fn vtt_timestamp(secs: f64) -> String {
+    let millis = (secs * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
@@ -0,0 +1,182 @@
+use std::env;
+
+use serde::Serialize;
+
+use crate::config::env_flag;
+
+/// S3 allows at most this many tags per object.
+const MAX_TAGS: usize = 10;
+
+/// Tags the job sets itself. `upscale` is only added to flagged rungs but
+/// is reserved so it always fits under the limit.
+const RESERVED_KEYS: [&str; 3] = ["rendition", "source-stem", "upscale"];
+
+/// Object tags written alongside each upload so bucket lifecycle rules can
+/// target renditions, logs and previews separately.
+#[derive(Clone, Debug, Serialize)]
+pub struct OutputTags {
+    pub static_tags: Vec<(String, String)>,
+}
+
+impl OutputTags {
+    /// Tagging is on when `TAG_OUTPUTS` is set or `DEST_TAGS` supplies
+    /// static tags. It stays opt-in because it needs `s3:PutObjectTagging`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match env::var("DEST_TAGS") {
+            Ok(v) => Ok(Some(Self {
+                static_tags: parse_tags(&v)?,
+            })),
+            Err(_) if env_flag("TAG_OUTPUTS") => Ok(Some(Self {
+                static_tags: Vec::new(),
+            })),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The URL-encoded `x-amz-tagging` value for one uploaded object.
+    pub fn for_object(&self, rendition: &str, stem: &str) -> String {
+        let stem = sanitize_value(stem);
+        [("rendition", rendition), ("source-stem", stem.as_str())]
+            .into_iter()
+            .chain(
+                self.static_tags
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str())),
+            )
+            .map(|(k, v)| format!("{}={}", url_encode(k), url_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+/// Adds one more tag to an optional `x-amz-tagging` value.
+pub fn append_tag(tagging: Option<String>, key: &str, value: &str) -> String {
+    let tag = format!("{}={}", url_encode(key), url_encode(value));
+    match tagging {
+        Some(tagging) => format!("{}&{}", tagging, tag),
+        None => tag,
+    }
+}
+
+/// Parses `team=media,env=prod` into key/value pairs, applying S3's tag
+/// rules so a bad value fails at startup rather than on the first upload.
+pub fn parse_tags(value: &str) -> Result<Vec<(String, String)>, String> {
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid DEST_TAGS entry (expected key=value): {}", entry))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() || key.chars().count() > 128 {
+            return Err(format!("Invalid tag key in DEST_TAGS: {:?}", key));
+        }
+        if value.chars().count() > 256 {
+            return Err(format!("Tag value too long in DEST_TAGS: {}", key));
+        }
+        if !key.chars().all(is_tag_char) || !value.chars().all(is_tag_char) {
+            return Err(format!(
+                "Unsupported character in DEST_TAGS entry: {}",
+                entry
+            ));
+        }
+        if key.starts_with("aws:") {
+            return Err(format!("Tag keys may not use the aws: prefix: {}", key));
+        }
+        if RESERVED_KEYS.contains(&key) {
+            return Err(format!("Tag key {} is set automatically", key));
+        }
+        if tags.iter().any(|(existing, _)| existing == key) {
+            return Err(format!("Duplicate tag key in DEST_TAGS: {}", key));
+        }
+        tags.push((key.to_string(), value.to_string()));
+    }
+    if tags.len() + RESERVED_KEYS.len() > MAX_TAGS {
+        return Err(format!(
+            "DEST_TAGS has {} tags; at most {} are allowed",
+            tags.len(),
+            MAX_TAGS - RESERVED_KEYS.len()
+        ));
+    }
+    Ok(tags)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || "+-=._:/@".contains(c)
+}
+
+/// File stems can contain anything, so characters S3 rejects in tag values
+/// are replaced rather than failing the upload.
+fn sanitize_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if is_tag_char(c) { c } else { '_' })
+        .take(256)
+        .collect()
+}
+
+fn url_encode(value: &str) -> String {
+    let mut out = String::new();
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_parse_in_order() {
+        assert_eq!(
+            parse_tags(" team=media , env=prod,empty=,").unwrap(),
+            [
+                ("team".to_string(), "media".to_string()),
+                ("env".to_string(), "prod".to_string()),
+                ("empty".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(
+            parse_tags("path=a/b:c@d").unwrap(),
+            [("path".to_string(), "a/b:c@d".to_string())]
+        );
+    }
+
+    #[test]
+    fn invalid_tags_are_rejected() {
+        let too_many = (0..8).map(|i| format!("k{}=v", i)).collect::<Vec<_>>();
+        for value in [
+            "team",
+            "=media",
+            "team=me&dia",
+            "aws:createdBy=me",
+            "rendition=720p",
+            "team=a,team=b",
+            &too_many.join(","),
+            &format!("team={}", "x".repeat(257)),
+        ] {
+            assert!(parse_tags(value).is_err(), "{}", value);
+        }
+        assert_eq!(parse_tags(&too_many[..7].join(",")).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn object_tagging_is_url_encoded() {
+        let tags = OutputTags {
+            static_tags: parse_tags("team=media ops").unwrap(),
+        };
+        assert_eq!(
+            tags.for_object("720p", "my clip#1"),
+            "rendition=720p&source-stem=my%20clip_1&team=media%20ops"
+        );
+        assert_eq!(
+            append_tag(Some(tags.for_object("720p", "a")), "upscale", "true"),
+            "rendition=720p&source-stem=a&team=media%20ops&upscale=true"
+        );
+        assert_eq!(append_tag(None, "upscale", "true"), "upscale=true");
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Wall-clock milliseconds spent in each phase of a job, keyed like
+/// `download_ms` or `rung_720p_ms`.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct JobTimings(BTreeMap<String, u64>);
+
+impl JobTimings {
+    /// Adds `elapsed` to `phase`, so repeated steps such as uploads
+    /// accumulate into one total.
+    pub fn add(&mut self, phase: &str, elapsed: Duration) {
+        *self.0.entry(format!("{}_ms", phase)).or_default() += elapsed.as_millis() as u64;
+    }
+
+    /// Adds the time since `started` to `phase`.
+    pub fn since(&mut self, phase: &str, started: Instant) {
+        self.add(phase, started.elapsed());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_phases_accumulate() {
+        let mut timings = JobTimings::default();
+        assert!(timings.is_empty());
+        timings.add("download", Duration::from_millis(1500));
+        timings.add("upload_total", Duration::from_millis(200));
+        timings.add("upload_total", Duration::from_millis(300));
+        timings.since("rung_720p", Instant::now());
+
+        let summary = serde_json::to_value(&timings).unwrap();
+        assert_eq!(summary["download_ms"], 1500);
+        assert_eq!(summary["upload_total_ms"], 500);
+        assert!(summary["rung_720p_ms"].is_u64());
+        assert_eq!(summary.as_object().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::error::TranscodeError;
+use crate::ffmpeg::{self, EncodeSettings, Rung};
+
+/// Produces one rendition of the source. The rung loop only talks to this,
+/// so the rest of the job can run without ffmpeg.
+pub trait Transcoder {
+    fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        rung: &Rung,
+        source_fps: Option<f64>,
+        log_path: Option<&str>,
+        on_progress: Option<&dyn Fn(f64)>,
+    ) -> Result<(), TranscodeError>;
+
+    /// Burns the captions at `path` into every rendition from here on.
+    /// Transcoders that don't encode ignore it.
+    fn burn_subtitles(&mut self, _path: &str) {}
+}
+
+/// The real encoder.
+pub struct FfmpegTranscoder {
+    pub settings: EncodeSettings,
+}
+
+impl Transcoder for FfmpegTranscoder {
+    fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        rung: &Rung,
+        source_fps: Option<f64>,
+        log_path: Option<&str>,
+        on_progress: Option<&dyn Fn(f64)>,
+    ) -> Result<(), TranscodeError> {
+        ffmpeg::transcode_video(
+            input,
+            output,
+            rung,
+            &self.settings,
+            source_fps,
+            log_path,
+            on_progress,
+        )
+    }
+
+    fn burn_subtitles(&mut self, path: &str) {
+        self.settings.subtitles = Some(path.to_string());
+    }
+}
+
+/// Copies the source to every output path instead of encoding
+/// (`FAKE_TRANSCODE=true`). Exercises download, upload and cleanup end to
+/// end in seconds; the uploads are not real renditions.
+pub struct CopyTranscoder;
+
+impl Transcoder for CopyTranscoder {
+    fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        _rung: &Rung,
+        _source_fps: Option<f64>,
+        log_path: Option<&str>,
+        _on_progress: Option<&dyn Fn(f64)>,
+    ) -> Result<(), TranscodeError> {
+        std::fs::copy(input, output)?;
+        if let Some(log_path) = log_path {
+            std::fs::write(log_path, "fake transcode: copied input\n")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A per-job scratch directory. Every intermediate and output file lives
+/// beneath it, so concurrent jobs (or rungs) on one host never share a path.
+/// The directory and anything left in it are removed on drop.
+#[derive(Debug)]
+pub struct JobWorkspace {
+    dir: PathBuf,
+}
+
+impl JobWorkspace {
+    pub fn create(root: &Path) -> io::Result<Self> {
+        let dir = root.join(format!("job-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Free space on the workspace's filesystem, in bytes, as reported by
+    /// `df`.
+    pub fn available_bytes(&self) -> io::Result<u64> {
+        let output = Command::new("df").arg("-Pk").arg(&self.dir).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "df exited with status: {}",
+                output.status
+            )));
+        }
+        // POSIX format: a header line, then
+        // `Filesystem 1024-blocks Used Available Capacity Mounted-on`.
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .nth(1)
+            .and_then(|line| line.split_whitespace().nth(3))
+            .and_then(|kib| kib.parse::<u64>().ok())
+            .map(|kib| kib * 1024)
+            .ok_or_else(|| io::Error::other("unexpected df output"))
+    }
+
+    /// Path for a file named `name` inside the workspace.
+    pub fn file(&self, name: &str) -> String {
+        self.dir.join(name).to_string_lossy().into_owned()
+    }
+}
+
+/// Longest name kept by [`sanitize_file_name`], well under filesystem limits
+/// once a prefix and extension are added.
+const MAX_NAME_CHARS: usize = 100;
+
+/// Reduces an arbitrary name from an object key to something safe as a
+/// single file name: only the last path segment is kept, anything outside
+/// `[A-Za-z0-9._-]` becomes `_`, and leading dots are dropped so the result
+/// can't be `..` or hidden. Returns `None` if nothing usable is left.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let last = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let cleaned: String = last
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .skip_while(|c| *c == '.')
+        .take(MAX_NAME_CHARS)
+        .collect();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+impl Drop for JobWorkspace {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            eprintln!("Failed to remove workspace {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspaces_never_share_paths() {
+        let root = std::env::temp_dir();
+        let a = JobWorkspace::create(&root).unwrap();
+        let b = JobWorkspace::create(&root).unwrap();
+        assert_ne!(a.dir(), b.dir());
+        assert_ne!(a.file("output_720p.mp4"), b.file("output_720p.mp4"));
+        assert!(Path::new(&a.file("x")).starts_with(a.dir()));
+    }
+
+    #[test]
+    fn dropping_a_workspace_removes_it() {
+        let workspace = JobWorkspace::create(&std::env::temp_dir()).unwrap();
+        fs::write(workspace.file("output_480p.mp4"), b"leftover").unwrap();
+        let dir = workspace.dir().to_path_buf();
+        drop(workspace);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn file_names_are_reduced_to_a_safe_last_segment() {
+        for (name, expected) in [
+            ("clip", Some("clip")),
+            (
+                "uploads/2024/My Clip (final).mov",
+                Some("My_Clip__final_.mov"),
+            ),
+            ("..\\..\\etc\\passwd", Some("passwd")),
+            ("../../.bashrc", Some("bashrc")),
+            ("caf\u{e9}", Some("caf_")),
+            ("..", None),
+            ("uploads/", None),
+            ("", None),
+        ] {
+            assert_eq!(sanitize_file_name(name).as_deref(), expected, "{}", name);
+        }
+        assert_eq!(
+            sanitize_file_name(&"a".repeat(300)).unwrap().len(),
+            MAX_NAME_CHARS
+        );
+    }
+}
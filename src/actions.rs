@@ -0,0 +1,260 @@
+use crossterm::event::KeyCode;
+
+/// Every user-triggerable action. The key bindings, the status-line help and
+/// the command palette are all derived from this list so they stay in sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveTop,
+    MoveBottom,
+    PageUp,
+    PageDown,
+    SwitchPanel,
+    Launch,
+    ToggleChecked,
+    CheckAll,
+    ClearChecked,
+    LaunchChecked,
+    DeleteChecked,
+    RaisePriority,
+    LowerPriority,
+    Replay,
+    ExportMessage,
+    ExportInventory,
+    ExportReport,
+    TogglePause,
+    ToggleDebug,
+    ToggleCompact,
+    CycleBucketFilter,
+    ToggleHelp,
+    CommandPalette,
+    Quit,
+}
+
+/// Headings for the help overlay, in display order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Navigation,
+    JobActions,
+    TaskManagement,
+    View,
+}
+
+impl Category {
+    pub const ALL: &'static [Category] = &[
+        Category::Navigation,
+        Category::JobActions,
+        Category::TaskManagement,
+        Category::View,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Navigation => "Navigation",
+            Category::JobActions => "Job actions",
+            Category::TaskManagement => "Task management",
+            Category::View => "View",
+        }
+    }
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveTop,
+        Action::MoveBottom,
+        Action::PageUp,
+        Action::PageDown,
+        Action::SwitchPanel,
+        Action::Launch,
+        Action::ToggleChecked,
+        Action::CheckAll,
+        Action::ClearChecked,
+        Action::LaunchChecked,
+        Action::DeleteChecked,
+        Action::RaisePriority,
+        Action::LowerPriority,
+        Action::Replay,
+        Action::ExportMessage,
+        Action::ExportInventory,
+        Action::ExportReport,
+        Action::TogglePause,
+        Action::ToggleDebug,
+        Action::ToggleCompact,
+        Action::CycleBucketFilter,
+        Action::ToggleHelp,
+        Action::CommandPalette,
+        Action::Quit,
+    ];
+
+    /// The subset shown in the one-line status help; `?` lists the rest.
+    pub const STATUS_LINE: &'static [Action] = &[
+        Action::SwitchPanel,
+        Action::Launch,
+        Action::ToggleHelp,
+        Action::CommandPalette,
+        Action::Quit,
+    ];
+
+    pub fn key(self) -> KeyCode {
+        match self {
+            Action::MoveUp => KeyCode::Up,
+            Action::MoveDown => KeyCode::Down,
+            Action::MoveTop => KeyCode::Home,
+            Action::MoveBottom => KeyCode::End,
+            Action::PageUp => KeyCode::PageUp,
+            Action::PageDown => KeyCode::PageDown,
+            Action::SwitchPanel => KeyCode::Tab,
+            Action::Launch => KeyCode::Enter,
+            Action::ToggleChecked => KeyCode::Char(' '),
+            Action::CheckAll => KeyCode::Char('a'),
+            Action::ClearChecked => KeyCode::Char('n'),
+            Action::LaunchChecked => KeyCode::Char('L'),
+            Action::DeleteChecked => KeyCode::Char('X'),
+            Action::RaisePriority => KeyCode::Char('+'),
+            Action::LowerPriority => KeyCode::Char('-'),
+            Action::Replay => KeyCode::Char('r'),
+            Action::ExportMessage => KeyCode::Char('e'),
+            Action::ExportInventory => KeyCode::Char('I'),
+            Action::ExportReport => KeyCode::Char('R'),
+            Action::TogglePause => KeyCode::Char('p'),
+            Action::ToggleDebug => KeyCode::Char('d'),
+            Action::ToggleCompact => KeyCode::Char('c'),
+            Action::CycleBucketFilter => KeyCode::Char('b'),
+            Action::ToggleHelp => KeyCode::Char('?'),
+            Action::CommandPalette => KeyCode::Char(':'),
+            Action::Quit => KeyCode::Char('q'),
+        }
+    }
+
+    pub fn key_label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "↑",
+            Action::MoveDown => "↓",
+            Action::MoveTop => "Home",
+            Action::MoveBottom => "End",
+            Action::PageUp => "PgUp",
+            Action::PageDown => "PgDn",
+            Action::SwitchPanel => "Tab",
+            Action::Launch => "Enter",
+            Action::ToggleChecked => "Space",
+            Action::CheckAll => "a",
+            Action::ClearChecked => "n",
+            Action::LaunchChecked => "L",
+            Action::DeleteChecked => "X",
+            Action::RaisePriority => "+",
+            Action::LowerPriority => "-",
+            Action::Replay => "r",
+            Action::ExportMessage => "e",
+            Action::ExportInventory => "I",
+            Action::ExportReport => "R",
+            Action::TogglePause => "p",
+            Action::ToggleDebug => "d",
+            Action::ToggleCompact => "c",
+            Action::CycleBucketFilter => "b",
+            Action::ToggleHelp => "?",
+            Action::CommandPalette => ":",
+            Action::Quit => "q",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move selection up",
+            Action::MoveDown => "Move selection down",
+            Action::MoveTop => "Jump to the first job",
+            Action::MoveBottom => "Jump to the last job",
+            Action::PageUp => "Move selection up a page",
+            Action::PageDown => "Move selection down a page",
+            Action::SwitchPanel => "Switch between pending and recent jobs",
+            Action::Launch => "Transcode the selected pending job",
+            Action::ToggleChecked => "Check or uncheck the selected pending job",
+            Action::CheckAll => "Check every listed pending job",
+            Action::ClearChecked => "Uncheck every pending job",
+            Action::LaunchChecked => "Transcode all checked jobs",
+            Action::DeleteChecked => "Delete all checked jobs from SQS",
+            Action::RaisePriority => "Raise the selected job's priority",
+            Action::LowerPriority => "Lower the selected job's priority",
+            Action::Replay => "Re-transcode the selected recent job",
+            Action::ExportMessage => "Export the selected job's SQS message to a file",
+            Action::ExportInventory => "Export S3 size and type of every pending job to a file",
+            Action::ExportReport => "Export launched jobs to a CSV report",
+            Action::TogglePause => "Pause or resume pulling from SQS",
+            Action::ToggleDebug => "Show raw SQS message bodies",
+            Action::ToggleCompact => "Toggle the compact layout",
+            Action::CycleBucketFilter => "Show one bucket's pending jobs, cycling through them",
+            Action::ToggleHelp => "Show all keybindings",
+            Action::CommandPalette => "Open the command palette",
+            Action::Quit => "Quit",
+        }
+    }
+
+    pub fn category(self) -> Category {
+        match self {
+            Action::MoveUp
+            | Action::MoveDown
+            | Action::MoveTop
+            | Action::MoveBottom
+            | Action::PageUp
+            | Action::PageDown
+            | Action::SwitchPanel => Category::Navigation,
+            Action::RaisePriority
+            | Action::LowerPriority
+            | Action::ToggleChecked
+            | Action::CheckAll
+            | Action::ClearChecked
+            | Action::DeleteChecked
+            | Action::ExportMessage
+            | Action::ExportInventory
+            | Action::ExportReport => Category::JobActions,
+            Action::Launch | Action::LaunchChecked | Action::Replay | Action::TogglePause => {
+                Category::TaskManagement
+            }
+            Action::ToggleDebug
+            | Action::ToggleCompact
+            | Action::CycleBucketFilter
+            | Action::ToggleHelp
+            | Action::CommandPalette
+            | Action::Quit => Category::View,
+        }
+    }
+
+    pub fn from_key(code: KeyCode) -> Option<Action> {
+        Action::ALL.iter().copied().find(|a| a.key() == code)
+    }
+}
+
+/// Actions whose description fuzzy-matches `query`, best match first.
+pub fn palette_matches(query: &str) -> Vec<Action> {
+    let mut scored: Vec<(i32, Action)> = Action::ALL
+        .iter()
+        .filter(|a| **a != Action::CommandPalette)
+        .filter_map(|a| fuzzy_score(query, a.description()).map(|score| (score, *a)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, a)| a).collect()
+}
+
+/// Subsequence match: every query character must appear in order. Runs of
+/// consecutive matches and matches at word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (pos..text.len()).find(|&i| text[i] == q)?;
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || text[found - 1] == ' ' {
+            score += 3;
+        }
+        last_match = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
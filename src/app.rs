@@ -0,0 +1,990 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::capacity;
+use crate::debug::{self, RawBody};
+use crate::throughput::Throughput;
+
+/// How many launched jobs are kept for the recent-jobs panel.
+pub const COMPLETED_CAPACITY: usize = 50;
+
+/// Most toasts shown at once; older ones are dropped first.
+const MAX_TOASTS: usize = 4;
+
+/// How long before expiry a toast starts to fade.
+pub const TOAST_FADE: Duration = Duration::from_secs(1);
+
+/// Window for the intake rate shown in the header.
+const INTAKE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Launch urgency. Pending jobs are kept ordered highest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Uploads under a `priority/` prefix jump the queue.
+    pub fn from_key(key: &str) -> Self {
+        if key.starts_with("priority/") {
+            Priority::High
+        } else {
+            Priority::Normal
+        }
+    }
+
+    pub fn raised(self) -> Self {
+        match self {
+            Priority::Low => Priority::Normal,
+            _ => Priority::High,
+        }
+    }
+
+    pub fn lowered(self) -> Self {
+        match self {
+            Priority::High => Priority::Normal,
+            _ => Priority::Low,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "norm",
+            Priority::High => "HIGH",
+        }
+    }
+}
+
+/// An SQS queue being polled, labelled for display.
+#[derive(Debug)]
+pub struct QueueSource {
+    pub url: String,
+    pub label: String,
+}
+
+impl QueueSource {
+    /// Labels the queue by its name, the last segment of the URL.
+    pub fn new(url: &str) -> Self {
+        let label = url.trim_end_matches('/').rsplit('/').next().unwrap_or(url);
+        Self {
+            url: url.to_string(),
+            label: label.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VideoMessage {
+    pub bucket: String,
+    pub key: String,
+    pub receipt_handle: String,
+    /// The queue the message was received from, and must be deleted from.
+    pub source: Arc<QueueSource>,
+    pub priority: Priority,
+    /// Set for FIFO queues. Jobs sharing a group are launched one at a time.
+    pub message_group_id: Option<String>,
+    /// Earlier tries at this job: SQS redeliveries before it was received
+    /// (from `ApproximateReceiveCount`) plus launches that failed or timed
+    /// out. Zero on the first try.
+    pub attempts: u32,
+    /// The SQS body the job was parsed from, kept for exports. Jobs adopted
+    /// from running tasks have none.
+    pub raw_body: Option<Arc<str>>,
+    /// The `title` the source was uploaded with, looked up after the job
+    /// arrives.
+    pub title: Option<String>,
+}
+
+impl VideoMessage {
+    /// Bucket and key, which together name the source object; keys alone
+    /// repeat across buckets.
+    pub fn object_id(&self) -> (String, String) {
+        (self.bucket.clone(), self.key.clone())
+    }
+
+    /// What to show for the job: its title, else its key.
+    pub fn display_name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.key)
+    }
+}
+
+/// Last known state of a launched ECS task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Launched but not yet seen running.
+    Starting,
+    Running,
+    /// Stopped on its own, with the transcoder container's exit code.
+    Stopped {
+        exit_code: Option<i32>,
+    },
+    /// Stopped for exceeding `--task-timeout`.
+    TimedOut,
+}
+
+impl TaskStatus {
+    /// Whether the task can still change state and is worth polling.
+    pub fn is_active(self) -> bool {
+        matches!(self, TaskStatus::Starting | TaskStatus::Running)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskStatus::Starting => "starting",
+            TaskStatus::Running => "running",
+            TaskStatus::Stopped { exit_code: Some(0) } => "done",
+            TaskStatus::Stopped { .. } => "failed",
+            TaskStatus::TimedOut => "timed out",
+        }
+    }
+}
+
+/// What started a launch, recorded for auditing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaunchTrigger {
+    /// Launched by the driver itself, e.g. `--drain-on-start`.
+    Auto,
+    /// Launched by an operator.
+    Manual,
+}
+
+impl LaunchTrigger {
+    /// Passed to the container as `LAUNCH_TRIGGER`.
+    pub fn label(self) -> &'static str {
+        match self {
+            LaunchTrigger::Auto => "auto",
+            LaunchTrigger::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(LaunchTrigger::Auto),
+            "manual" => Some(LaunchTrigger::Manual),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> &'static str {
+        match self {
+            LaunchTrigger::Auto => "A",
+            LaunchTrigger::Manual => "M",
+        }
+    }
+}
+
+/// A job whose ECS task was launched and whose SQS message was consumed.
+#[derive(Clone, Debug)]
+pub struct CompletedJob {
+    pub job: VideoMessage,
+    pub task_arn: Option<String>,
+    pub log_url: Option<String>,
+    pub launched_at: SystemTime,
+    /// When the task was first seen stopped.
+    pub finished_at: Option<SystemTime>,
+    pub status: TaskStatus,
+    /// Unknown for adopted tasks launched by an older driver.
+    pub trigger: Option<LaunchTrigger>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastLevel {
+    Success,
+    Info,
+    Error,
+}
+
+impl ToastLevel {
+    /// Errors stay up long enough to be read; confirmations get out of the
+    /// way quickly.
+    pub fn duration(self) -> Duration {
+        match self {
+            ToastLevel::Success => Duration::from_secs(3),
+            ToastLevel::Info => Duration::from_secs(4),
+            ToastLevel::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+/// Short-lived feedback shown in a corner of the screen.
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub message: String,
+    pub level: ToastLevel,
+    pub expires_at: Instant,
+}
+
+impl Toast {
+    pub fn is_fading(&self) -> bool {
+        self.expires_at.saturating_duration_since(Instant::now()) <= TOAST_FADE
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Focus {
+    Pending,
+    Completed,
+}
+
+/// An action waiting on a y/n answer from the confirmation modal.
+#[derive(Clone, Debug)]
+pub enum ConfirmAction {
+    Replay(VideoMessage),
+    /// Delete every checked pending job's SQS message.
+    DeleteChecked(usize),
+}
+
+impl ConfirmAction {
+    pub fn prompt(&self) -> String {
+        match self {
+            ConfirmAction::Replay(job) => format!(
+                "Re-transcode {} / {}? This launches a new Fargate task.",
+                job.bucket, job.key
+            ),
+            ConfirmAction::DeleteChecked(count) => format!(
+                "Delete {} checked job(s) from SQS? They will not be transcoded.",
+                count
+            ),
+        }
+    }
+}
+
+/// Open command palette: the typed filter and the highlighted match.
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+/// Job counts since startup, for `/metrics`.
+#[derive(Debug, Default)]
+pub struct JobTotals {
+    pub launched: u64,
+    pub succeeded: u64,
+    /// Tasks that failed, timed out or stopped without an exit code.
+    pub failed: u64,
+}
+
+#[derive(Debug)]
+pub struct AppState {
+    pub messages: Vec<VideoMessage>,
+    pub selected: usize,
+    /// When set, only pending jobs from this bucket are listed.
+    pub bucket_filter: Option<String>,
+    /// Bucket and key of pending jobs ticked for a bulk launch or delete.
+    pub checked: HashSet<(String, String)>,
+    pub completed: VecDeque<CompletedJob>,
+    pub completed_selected: usize,
+    pub focus: Focus,
+    pub confirm: Option<ConfirmAction>,
+    pub palette: Option<Palette>,
+    pub raw_bodies: VecDeque<RawBody>,
+    pub show_debug: bool,
+    pub show_help: bool,
+    /// Dense single-line rows and no chrome, for small terminals.
+    pub compact: bool,
+    pub debug_selected: usize,
+    /// Rows the focused list showed on the last draw; PageUp/PageDown move
+    /// by this much.
+    pub page_size: usize,
+    pub last_queue_activity: Instant,
+    /// FIFO message groups with a launch currently in progress.
+    pub in_flight_groups: HashSet<String>,
+    /// Shared with the poller, which checks it without taking the state lock.
+    pub paused: Arc<AtomicBool>,
+    /// Arrival times of jobs received in the last minute, across all pollers.
+    pub intake: VecDeque<Instant>,
+    /// Oldest first.
+    pub toasts: VecDeque<Toast>,
+    pub throughput: Throughput,
+    pub totals: JobTotals,
+    /// First launch since the last successful completion, if any. The
+    /// completion watchdog measures from here.
+    pub unanswered_since: Option<Instant>,
+    /// Set by the watchdog once it has alerted; cleared by any success.
+    pub stalled: bool,
+    /// Until when launches are held because the cluster was at
+    /// `--cluster-task-ceiling`.
+    pub held_until: Option<Instant>,
+    /// Where message exports are written.
+    pub export_dir: PathBuf,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+            selected: 0,
+            bucket_filter: None,
+            checked: HashSet::new(),
+            completed: VecDeque::new(),
+            completed_selected: 0,
+            focus: Focus::Pending,
+            confirm: None,
+            palette: None,
+            raw_bodies: VecDeque::new(),
+            show_debug: false,
+            show_help: false,
+            compact: false,
+            debug_selected: 0,
+            page_size: 10,
+            last_queue_activity: Instant::now(),
+            in_flight_groups: HashSet::new(),
+            paused: Arc::new(AtomicBool::new(false)),
+            intake: VecDeque::new(),
+            toasts: VecDeque::new(),
+            throughput: Throughput::new(),
+            totals: JobTotals::default(),
+            unanswered_since: None,
+            stalled: false,
+            held_until: None,
+            export_dir: PathBuf::from("."),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_paused(&self) {
+        self.paused.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn push_raw_body(&mut self, body: &str, parsed: bool) {
+        if self.raw_bodies.len() == debug::RAW_BODY_CAPACITY {
+            self.raw_bodies.pop_front();
+        }
+        self.raw_bodies.push_back(RawBody {
+            body: body.to_string(),
+            parsed,
+        });
+        self.debug_selected = self.raw_bodies.len() - 1;
+    }
+
+    pub fn debug_next(&mut self) {
+        if !self.raw_bodies.is_empty() {
+            self.debug_selected = (self.debug_selected + 1).min(self.raw_bodies.len() - 1);
+        }
+    }
+
+    pub fn debug_previous(&mut self) {
+        self.debug_selected = self.debug_selected.saturating_sub(1);
+    }
+
+    pub fn record_launch(&mut self) {
+        self.throughput.record_launch();
+        self.totals.launched += 1;
+        self.unanswered_since.get_or_insert_with(Instant::now);
+    }
+
+    /// How long jobs have been launched without any of them succeeding,
+    /// once that exceeds `window`.
+    pub fn stalled_for(&self, window: Duration) -> Option<Duration> {
+        self.unanswered_since
+            .map(|since| since.elapsed())
+            .filter(|elapsed| *elapsed > window)
+    }
+
+    pub fn push_toast(&mut self, message: impl Into<String>, level: ToastLevel) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            message: message.into(),
+            level,
+            expires_at: Instant::now() + level.duration(),
+        });
+    }
+
+    /// Drops expired toasts and returns how long until the next one starts
+    /// fading or expires, so the UI knows when to redraw.
+    pub fn expire_toasts(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        self.toasts.retain(|toast| toast.expires_at > now);
+        self.toasts
+            .iter()
+            .map(|toast| {
+                let remaining = toast.expires_at - now;
+                if remaining > TOAST_FADE {
+                    remaining - TOAST_FADE
+                } else {
+                    remaining
+                }
+            })
+            .min()
+    }
+
+    pub fn record_intake(&mut self) {
+        let now = Instant::now();
+        while self
+            .intake
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > INTAKE_WINDOW)
+        {
+            self.intake.pop_front();
+        }
+        self.intake.push_back(now);
+    }
+
+    /// Jobs received over the last minute.
+    pub fn intake_per_minute(&self) -> usize {
+        self.intake
+            .iter()
+            .filter(|t| t.elapsed() <= INTAKE_WINDOW)
+            .count()
+    }
+
+    /// Holds auto-launches until the cluster's task count is next
+    /// refreshed.
+    pub fn hold_for_capacity(&mut self) {
+        self.held_until = Some(Instant::now() + capacity::CACHE_TTL);
+    }
+
+    /// Whether launches are being held for cluster capacity.
+    pub fn is_holding(&self) -> bool {
+        self.held_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Moves a pending job for the same object over to `newer`, a later
+    /// delivery of its message, e.g. to another concurrent receiver. SQS
+    /// invalidates the older receipt handle on redelivery, so only the
+    /// newest can still delete the message. Returns whether one was pending.
+    pub fn refresh_pending(&mut self, newer: &VideoMessage) -> bool {
+        let Some(pending) = self
+            .messages
+            .iter_mut()
+            .find(|m| m.bucket == newer.bucket && m.key == newer.key)
+        else {
+            return false;
+        };
+        pending.receipt_handle = newer.receipt_handle.clone();
+        pending.attempts = newer.attempts;
+        pending.source = Arc::clone(&newer.source);
+        pending.raw_body = newer.raw_body.clone();
+        true
+    }
+
+    /// Records the title looked up for a pending job.
+    pub fn set_title(&mut self, bucket: &str, key: &str, title: String) {
+        for m in &mut self.messages {
+            if m.bucket == bucket && m.key == key {
+                m.title = Some(title.clone());
+            }
+        }
+    }
+
+    /// Whether the bucket filter lets a pending job through.
+    pub fn is_visible(&self, m: &VideoMessage) -> bool {
+        self.bucket_filter
+            .as_ref()
+            .is_none_or(|bucket| *bucket == m.bucket)
+    }
+
+    /// Indices into `messages` of the jobs the bucket filter lets through.
+    pub fn visible_indices(&self) -> Vec<usize> {
+        (0..self.messages.len())
+            .filter(|&i| self.is_visible(&self.messages[i]))
+            .collect()
+    }
+
+    /// The selected pending job, unless the bucket filter hides it.
+    pub fn selected_message(&self) -> Option<&VideoMessage> {
+        self.messages
+            .get(self.selected)
+            .filter(|m| self.is_visible(m))
+    }
+
+    /// Steps the bucket filter through the buckets of the pending jobs in
+    /// name order, then back to showing every bucket.
+    pub fn cycle_bucket_filter(&mut self) {
+        let buckets: BTreeSet<&str> = self.messages.iter().map(|m| m.bucket.as_str()).collect();
+        let next = match &self.bucket_filter {
+            None => buckets.first().copied(),
+            Some(current) => buckets.into_iter().find(|b| *b > current.as_str()),
+        };
+        self.bucket_filter = next.map(str::to_string);
+        self.select_visible();
+    }
+
+    /// Moves the pending selection onto a job the bucket filter shows: the
+    /// nearest one below, else the last one above.
+    fn select_visible(&mut self) {
+        let visible = self.visible_indices();
+        if let Some(&index) = visible
+            .iter()
+            .find(|&&i| i >= self.selected)
+            .or(visible.last())
+        {
+            self.selected = index;
+        }
+    }
+
+    /// Inserts behind every pending job of the same or higher priority, so
+    /// the list stays a priority queue that is FIFO within each level.
+    pub fn push_message(&mut self, m: VideoMessage) {
+        self.last_queue_activity = Instant::now();
+        let index = self
+            .messages
+            .iter()
+            .position(|existing| existing.priority < m.priority)
+            .unwrap_or(self.messages.len());
+        self.messages.insert(index, m);
+        if self.messages.len() == 1 {
+            self.selected = 0;
+        } else if index <= self.selected {
+            self.selected += 1;
+        }
+        if self.selected_message().is_none() {
+            self.select_visible();
+        }
+    }
+
+    /// Changes the selected job's priority and re-queues it, keeping the
+    /// selection on the same job.
+    pub fn reprioritize_selected(&mut self, change: fn(Priority) -> Priority) {
+        if self.selected >= self.messages.len() {
+            return;
+        }
+        let mut job = self.messages.remove(self.selected);
+        job.priority = change(job.priority);
+        let index = self
+            .messages
+            .iter()
+            .position(|existing| existing.priority < job.priority)
+            .unwrap_or(self.messages.len());
+        self.messages.insert(index, job);
+        self.selected = index;
+    }
+
+    pub fn remove_selected(&mut self) -> Option<VideoMessage> {
+        if self.messages.is_empty() {
+            return None;
+        }
+        if self.selected >= self.messages.len() {
+            self.selected = self.messages.len() - 1;
+        }
+        let job = self.messages.remove(self.selected);
+        self.selected = self.selected.min(self.messages.len().saturating_sub(1));
+        self.select_visible();
+        Some(job)
+    }
+
+    pub fn toggle_checked_selected(&mut self) {
+        if let Some(job) = self.messages.get(self.selected) {
+            let id = job.object_id();
+            if !self.checked.remove(&id) {
+                self.checked.insert(id);
+            }
+        }
+    }
+
+    /// Checks every pending job the bucket filter shows.
+    pub fn check_all(&mut self) {
+        self.checked = self
+            .messages
+            .iter()
+            .filter(|m| self.is_visible(m))
+            .map(VideoMessage::object_id)
+            .collect();
+    }
+
+    /// Removes every checked job that can be launched now, then clears the
+    /// checks. Returns the jobs to launch, in queue order so priorities and
+    /// FIFO groups are respected, and why the others were skipped.
+    pub fn take_checked_launchable(&mut self) -> (Vec<VideoMessage>, Vec<String>) {
+        let checked = std::mem::take(&mut self.checked);
+        let previous = self.selected;
+        let mut launched = Vec::new();
+        let mut skipped = Vec::new();
+        let mut index = 0;
+        while index < self.messages.len() {
+            if !checked.contains(&self.messages[index].object_id()) {
+                index += 1;
+                continue;
+            }
+            self.selected = index;
+            match self.take_launchable_selected() {
+                // The next job has moved up into this index.
+                Ok(Some(job)) => launched.push(job),
+                Ok(None) => index += 1,
+                Err(reason) => {
+                    skipped.push(reason);
+                    index += 1;
+                }
+            }
+        }
+        self.selected = previous.min(self.messages.len().saturating_sub(1));
+        self.select_visible();
+        (launched, skipped)
+    }
+
+    /// Removes every checked job and clears the checks.
+    pub fn remove_checked(&mut self) -> Vec<VideoMessage> {
+        let checked = std::mem::take(&mut self.checked);
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.messages)
+            .into_iter()
+            .partition(|m| checked.contains(&m.object_id()));
+        self.messages = kept;
+        self.selected = self.selected.min(self.messages.len().saturating_sub(1));
+        self.select_visible();
+        removed
+    }
+
+    /// Removes the selected job for launch, unless another job from the same
+    /// FIFO message group is still being launched or a task for the same key
+    /// is still running.
+    pub fn take_launchable_selected(&mut self) -> Result<Option<VideoMessage>, String> {
+        if let Some(group) = self
+            .messages
+            .get(self.selected)
+            .and_then(|job| job.message_group_id.as_ref())
+            && self.in_flight_groups.contains(group)
+        {
+            return Err(format!(
+                "message group {} already has a job in flight",
+                group
+            ));
+        }
+        if let Some(job) = self.messages.get(self.selected)
+            && let Some(running) = self.active_task_for(&job.key)
+        {
+            return Err(format!(
+                "{} is already running as {}",
+                job.key,
+                running.task_arn.as_deref().unwrap_or("an unstarted task")
+            ));
+        }
+        let job = self.remove_selected();
+        if let Some(group) = job.as_ref().and_then(|job| job.message_group_id.clone()) {
+            self.in_flight_groups.insert(group);
+        }
+        Ok(job)
+    }
+
+    /// Removes the first pending job that can be launched now, skipping
+    /// any that [`take_launchable_selected`](Self::take_launchable_selected)
+    /// would refuse.
+    pub fn take_next_launchable(&mut self) -> Option<VideoMessage> {
+        let previous = self.selected;
+        let mut taken = None;
+        for index in 0..self.messages.len() {
+            self.selected = index;
+            if let Ok(Some(job)) = self.take_launchable_selected() {
+                taken = Some(job);
+                break;
+            }
+        }
+        self.selected = previous.min(self.messages.len().saturating_sub(1));
+        self.select_visible();
+        taken
+    }
+
+    pub fn finish_launch(&mut self, job: &VideoMessage) {
+        if let Some(group) = &job.message_group_id {
+            self.in_flight_groups.remove(group);
+        }
+    }
+
+    /// Records a launched job, newest first, dropping the oldest finished
+    /// jobs beyond [`COMPLETED_CAPACITY`]. Tasks still starting or running
+    /// are kept whatever the count, since the monitor, the timeouts and the
+    /// concurrency cap all work from this list.
+    pub fn push_completed(&mut self, job: CompletedJob) {
+        self.completed.push_front(job);
+        while self.completed.len() > COMPLETED_CAPACITY {
+            let Some(oldest_finished) = self.completed.iter().rposition(|c| !c.status.is_active())
+            else {
+                break;
+            };
+            self.completed.remove(oldest_finished);
+        }
+        if self.completed_selected >= self.completed.len() {
+            self.completed_selected = self.completed.len().saturating_sub(1);
+        }
+    }
+
+    /// A launched task for `key` that has not stopped yet.
+    pub fn active_task_for(&self, key: &str) -> Option<&CompletedJob> {
+        self.completed
+            .iter()
+            .find(|c| c.status.is_active() && c.job.key == key)
+    }
+
+    /// Matches a delivery to the task adopted at startup for the same key,
+    /// if one is still waiting for its message, and returns that task's
+    /// status. A running task takes over the message, to settle when it
+    /// stops. Either way the task is claimed, so no later delivery matches it.
+    pub fn claim_for_adopted(&mut self, delivery: &VideoMessage) -> Option<TaskStatus> {
+        let adopted = self.completed.iter_mut().find(|c| {
+            c.job.receipt_handle.is_empty() && c.job.bucket.is_empty() && c.job.key == delivery.key
+        })?;
+        adopted.job = VideoMessage {
+            title: adopted.job.title.take(),
+            ..delivery.clone()
+        };
+        Some(adopted.status)
+    }
+
+    /// Launched tasks that haven't stopped yet.
+    pub fn active_task_count(&self) -> usize {
+        self.completed
+            .iter()
+            .filter(|c| c.status.is_active())
+            .count()
+    }
+
+    /// Tasks the status monitor should still poll.
+    pub fn active_task_arns(&self) -> Vec<String> {
+        self.completed
+            .iter()
+            .filter(|c| c.status.is_active())
+            .filter_map(|c| c.task_arn.clone())
+            .collect()
+    }
+
+    /// The job whose task would change status, before the change is
+    /// recorded.
+    pub fn job_for_status_change(
+        &self,
+        task_arn: &str,
+        status: TaskStatus,
+    ) -> Option<VideoMessage> {
+        self.completed
+            .iter()
+            .find(|c| c.task_arn.as_deref() == Some(task_arn) && c.status != status)
+            .map(|c| c.job.clone())
+    }
+
+    /// Jobs of running tasks whose SQS messages are held until the task
+    /// stops.
+    pub fn held_messages(&self) -> Vec<VideoMessage> {
+        self.completed
+            .iter()
+            .filter(|c| c.status.is_active() && !c.job.receipt_handle.is_empty())
+            .map(|c| c.job.clone())
+            .collect()
+    }
+
+    /// Launched tasks that haven't stopped yet, newest first.
+    pub fn running_jobs(&self) -> Vec<CompletedJob> {
+        self.completed
+            .iter()
+            .filter(|c| c.status.is_active())
+            .cloned()
+            .collect()
+    }
+
+    pub fn task(&self, task_arn: &str) -> Option<&CompletedJob> {
+        self.completed
+            .iter()
+            .find(|c| c.task_arn.as_deref() == Some(task_arn))
+    }
+
+    /// Records a task's new status, returning its job when it changed.
+    pub fn set_task_status(&mut self, task_arn: &str, status: TaskStatus) -> Option<VideoMessage> {
+        let done = self
+            .completed
+            .iter_mut()
+            .find(|c| c.task_arn.as_deref() == Some(task_arn))?;
+        if done.status == status {
+            return None;
+        }
+        done.status = status;
+        if !status.is_active() && done.finished_at.is_none() {
+            done.finished_at = Some(SystemTime::now());
+        }
+        if !status.is_active() && status != (TaskStatus::Stopped { exit_code: Some(0) }) {
+            self.totals.failed += 1;
+        }
+        if status == (TaskStatus::Stopped { exit_code: Some(0) }) {
+            self.totals.succeeded += 1;
+            self.throughput.record_completion();
+            self.unanswered_since = None;
+            self.stalled = false;
+        }
+        Some(done.job.clone())
+    }
+
+    pub fn selected_completed(&self) -> Option<&CompletedJob> {
+        self.completed.get(self.completed_selected)
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Pending => Focus::Completed,
+            Focus::Completed => Focus::Pending,
+        };
+    }
+
+    pub fn next(&mut self) {
+        self.move_selection(|position, _| position + 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.move_selection(|position, _| position.saturating_sub(1));
+    }
+
+    pub fn select_first(&mut self) {
+        self.move_selection(|_, _| 0);
+    }
+
+    pub fn select_last(&mut self) {
+        self.move_selection(|_, len| len - 1);
+    }
+
+    pub fn page_down(&mut self) {
+        let page = self.page_size.max(1);
+        self.move_selection(|selected, _| selected.saturating_add(page));
+    }
+
+    pub fn page_up(&mut self) {
+        let page = self.page_size.max(1);
+        self.move_selection(|selected, _| selected.saturating_sub(page));
+    }
+
+    /// Moves the focused list's selection to row `to(position, len)` of
+    /// what the list shows, kept in range. Does nothing for an empty list.
+    fn move_selection(&mut self, to: impl FnOnce(usize, usize) -> usize) {
+        match self.focus {
+            Focus::Pending => {
+                let visible = self.visible_indices();
+                let Some(last) = visible.len().checked_sub(1) else {
+                    return;
+                };
+                let position = visible
+                    .iter()
+                    .position(|&i| i >= self.selected)
+                    .unwrap_or(last);
+                self.selected = visible[to(position, visible.len()).min(last)];
+            }
+            Focus::Completed => {
+                let len = self.completed.len();
+                if len > 0 {
+                    self.completed_selected = to(self.completed_selected, len).min(len - 1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(bucket: &str, key: &str) -> VideoMessage {
+        VideoMessage {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            receipt_handle: "receipt".to_string(),
+            source: Arc::new(QueueSource::new(
+                "https://sqs.us-east-1.amazonaws.com/123456789012/uploads",
+            )),
+            priority: Priority::Normal,
+            message_group_id: None,
+            attempts: 0,
+            raw_body: None,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn jobs_without_a_title_show_their_key() {
+        let mut m = message("uploads", "upload-1234.mp4");
+        assert_eq!(m.display_name(), "upload-1234.mp4");
+        m.title = Some("Launch keynote".to_string());
+        assert_eq!(m.display_name(), "Launch keynote");
+    }
+
+    #[test]
+    fn titles_are_set_on_the_matching_job_only() {
+        let mut st = AppState::new();
+        st.push_message(message("uploads", "a.mp4"));
+        st.push_message(message("other", "a.mp4"));
+        st.set_title("uploads", "a.mp4", "Keynote".to_string());
+
+        let titles: Vec<_> = st.messages.iter().map(|m| m.display_name()).collect();
+        assert_eq!(titles, ["Keynote", "a.mp4"]);
+    }
+
+    #[test]
+    fn checked_jobs_launch_in_priority_order() {
+        let mut st = AppState::new();
+        for (key, priority) in [
+            ("low.mp4", Priority::Low),
+            ("normal.mp4", Priority::Normal),
+            ("high.mp4", Priority::High),
+            ("unchecked.mp4", Priority::High),
+        ] {
+            st.push_message(VideoMessage {
+                priority,
+                ..message("uploads", key)
+            });
+        }
+        st.check_all();
+        st.checked
+            .remove(&("uploads".to_string(), "unchecked.mp4".to_string()));
+
+        let (launched, skipped) = st.take_checked_launchable();
+        let keys: Vec<&str> = launched.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["high.mp4", "normal.mp4", "low.mp4"]);
+        assert!(skipped.is_empty());
+        assert!(st.checked.is_empty());
+        let left: Vec<&str> = st.messages.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(left, ["unchecked.mp4"]);
+    }
+
+    #[test]
+    fn checked_jobs_launch_one_per_fifo_group_in_order() {
+        let mut st = AppState::new();
+        for key in ["first.mp4", "second.mp4"] {
+            st.push_message(VideoMessage {
+                message_group_id: Some("tenant-a".to_string()),
+                ..message("uploads", key)
+            });
+        }
+        st.check_all();
+
+        let (launched, skipped) = st.take_checked_launchable();
+        let keys: Vec<&str> = launched.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["first.mp4"]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(st.messages[0].key, "second.mp4");
+    }
+
+    fn adopted(key: &str, status: TaskStatus) -> CompletedJob {
+        CompletedJob {
+            job: VideoMessage {
+                bucket: String::new(),
+                receipt_handle: String::new(),
+                ..message("", key)
+            },
+            task_arn: Some("arn:task".to_string()),
+            log_url: None,
+            launched_at: SystemTime::now(),
+            finished_at: None,
+            status,
+            trigger: None,
+        }
+    }
+
+    #[test]
+    fn adopted_tasks_claim_one_delivery_for_their_key() {
+        let mut st = AppState::new();
+        st.push_completed(adopted("a.mp4", TaskStatus::Running));
+
+        assert_eq!(st.claim_for_adopted(&message("uploads", "b.mp4")), None);
+        assert_eq!(
+            st.claim_for_adopted(&message("uploads", "a.mp4")),
+            Some(TaskStatus::Running)
+        );
+        assert_eq!(st.completed[0].job.bucket, "uploads");
+        assert_eq!(st.completed[0].job.receipt_handle, "receipt");
+        assert_eq!(st.claim_for_adopted(&message("uploads", "a.mp4")), None);
+    }
+}
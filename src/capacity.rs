@@ -0,0 +1,143 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use aws_sdk_ecs::Client as EcsClient;
+
+/// How long a cluster task count is trusted before ECS is asked again.
+pub const CACHE_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct Load {
+    /// Running plus pending tasks, and when ECS reported them.
+    cached: Option<(Instant, usize)>,
+    /// Launches that hold a slot but whose task hasn't started yet.
+    reserved: usize,
+}
+
+/// The cluster's running plus pending task count, cached for [`CACHE_TTL`]
+/// so a burst of launches makes one `describe_clusters` call, and the
+/// launches still on their way to it.
+#[derive(Debug, Default)]
+pub struct ClusterLoad {
+    load: Mutex<Load>,
+}
+
+impl ClusterLoad {
+    pub async fn task_count(
+        &self,
+        ecs_client: &EcsClient,
+        cluster_name: &str,
+    ) -> Result<usize, aws_sdk_ecs::Error> {
+        let cached = self.load.lock().unwrap().cached;
+        if let Some((at, count)) = cached
+            && at.elapsed() < CACHE_TTL
+        {
+            return Ok(count);
+        }
+        let out = ecs_client
+            .describe_clusters()
+            .clusters(cluster_name)
+            .send()
+            .await?;
+        let count = out.clusters().first().map_or(0, |c| {
+            (c.running_tasks_count() + c.pending_tasks_count()).max(0) as usize
+        });
+        self.load.lock().unwrap().cached = Some((Instant::now(), count));
+        Ok(count)
+    }
+
+    /// Reserves a slot for one launch if the cluster's tasks plus the
+    /// launches already holding one are under `ceiling`. A reservation ends
+    /// with [`record_launch`](Self::record_launch) or
+    /// [`release`](Self::release).
+    pub async fn reserve(
+        &self,
+        ecs_client: &EcsClient,
+        cluster_name: &str,
+        ceiling: usize,
+    ) -> Result<bool, aws_sdk_ecs::Error> {
+        self.task_count(ecs_client, cluster_name).await?;
+        Ok(self.try_reserve(ceiling))
+    }
+
+    /// Checks and takes a slot under one lock, so concurrent launches can't
+    /// all see the same free slot.
+    fn try_reserve(&self, ceiling: usize) -> bool {
+        let mut load = self.load.lock().unwrap();
+        let count = load.cached.map_or(0, |(_, count)| count);
+        if count + load.reserved >= ceiling {
+            return false;
+        }
+        load.reserved += 1;
+        true
+    }
+
+    /// Gives back the slot of a launch that failed.
+    pub fn release(&self) {
+        let mut load = self.load.lock().unwrap();
+        load.reserved = load.reserved.saturating_sub(1);
+    }
+
+    /// Turns a reserved slot into a counted task, so launches before the
+    /// next refresh see it.
+    pub fn record_launch(&self) {
+        let mut load = self.load.lock().unwrap();
+        load.reserved = load.reserved.saturating_sub(1);
+        if let Some((_, count)) = load.cached.as_mut() {
+            *count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_with(count: usize) -> ClusterLoad {
+        let load = ClusterLoad::default();
+        load.load.lock().unwrap().cached = Some((Instant::now(), count));
+        load
+    }
+
+    #[test]
+    fn concurrent_reservations_stop_at_the_ceiling() {
+        const LAUNCHES: usize = 8;
+        let load = load_with(0);
+        let reserved = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..LAUNCHES)
+                .map(|_| scope.spawn(|| load.try_reserve(LAUNCHES - 1)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .filter(|&reserved| reserved)
+                .count()
+        });
+        assert_eq!(reserved, LAUNCHES - 1);
+    }
+
+    #[test]
+    fn running_tasks_count_against_the_ceiling() {
+        let load = load_with(2);
+        assert!(load.try_reserve(3));
+        assert!(!load.try_reserve(3));
+    }
+
+    #[test]
+    fn failed_launches_give_their_slot_back() {
+        let load = load_with(0);
+        assert!(load.try_reserve(1));
+        assert!(!load.try_reserve(1));
+        load.release();
+        assert!(load.try_reserve(1));
+    }
+
+    #[test]
+    fn started_tasks_keep_their_slot() {
+        let load = load_with(0);
+        assert!(load.try_reserve(1));
+        load.record_launch();
+        assert!(!load.try_reserve(1));
+        assert_eq!(load.load.lock().unwrap().reserved, 0);
+    }
+}
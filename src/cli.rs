@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::launch::{SOURCE_BUCKET, parse_env_var, parse_task_tag};
+
+/// Interactive SQS → ECS launcher for the transcoding pipeline.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// SQS queue to watch. Repeat to watch several queues at once; each
+    /// job is labelled with the queue it came from.
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://sqs.us-east-1.amazonaws.com/091049244748/video-pipeline-queue-0342"
+    )]
+    pub queue_url: Vec<String>,
+
+    /// Concurrent receive loops per queue, for queues that fill faster than
+    /// one receiver can drain them.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub poll_concurrency: u16,
+
+    /// AWS region. Defaults to the region in the first --queue-url, then to
+    /// the usual AWS environment and profile settings.
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// Exit after this many seconds with no keypresses and no queue activity.
+    /// Zero disables the idle timeout.
+    #[arg(long, value_name = "SECS", default_value_t = 0)]
+    pub idle_timeout: u64,
+
+    /// Stop tasks that have been running longer than this many seconds and
+    /// put their job back on the pending list. Zero disables the timeout.
+    #[arg(long, value_name = "SECS", default_value_t = 0)]
+    pub task_timeout: u64,
+
+    /// Times a job whose task fails or times out is redelivered by SQS
+    /// before it is given up on and sent to --dlq-url.
+    #[arg(long, value_name = "COUNT", default_value_t = 2)]
+    pub max_retries: u32,
+
+    /// Alert when jobs have been launched but none has succeeded for this
+    /// many seconds. Zero disables the watchdog.
+    #[arg(long, value_name = "SECS", default_value_t = 0)]
+    pub deadman_window: u64,
+
+    /// SNS topic for watchdog alerts. Without it alerts only go to the log
+    /// and the header.
+    #[arg(long, value_name = "ARN")]
+    pub alert_topic_arn: Option<String>,
+
+    /// Extra environment variable for the transcoder container, as
+    /// KEY=VALUE. Repeatable.
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    pub env: Vec<(String, String)>,
+
+    /// Tag for every launched task, as KEY=VALUE, e.g. a cost center.
+    /// Repeatable. `source-key` and `bucket` are always set.
+    #[arg(long = "task-tag", value_name = "KEY=VALUE", value_parser = parse_task_tag)]
+    pub task_tags: Vec<(String, String)>,
+
+    /// Let transcoder tasks delete from the permanent output buckets
+    /// (sets ALLOW_DESTRUCTIVE in the container). Off by default.
+    #[arg(long)]
+    pub allow_destructive: bool,
+
+    /// Before the UI starts, receive everything already waiting on the
+    /// queues and launch it, then continue interactively.
+    #[arg(long)]
+    pub drain_on_start: bool,
+
+    /// Upper bound on jobs launched by --drain-on-start.
+    #[arg(long, value_name = "COUNT", default_value_t = 20)]
+    pub drain_limit: usize,
+
+    /// Directory for message (`e`), inventory (`I`) and CSV report (`R`)
+    /// exports.
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub export_dir: PathBuf,
+
+    /// Run without the UI: launch every job as it arrives and log JSON
+    /// lines to stdout. SIGTERM stops intake and exits once launched tasks
+    /// have stopped.
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Most tasks --daemon keeps launching or running at once.
+    #[arg(long, value_name = "COUNT", default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_running: u64,
+
+    /// With --daemon, write a CSV report of launched jobs to --export-dir
+    /// this often. Reports are built from job-history.csv there, which
+    /// every finished job is appended to.
+    #[arg(long, value_name = "SECS", requires = "daemon", value_parser = clap::value_parser!(u64).range(1..))]
+    pub report_interval: Option<u64>,
+
+    /// Start in the compact layout (toggle with `c`).
+    #[arg(long)]
+    pub compact: bool,
+
+    /// URL of the dead-letter queue behind the first --queue-url. Jobs that
+    /// exhaust --max-retries are sent here. Without it their messages are
+    /// left for the queue's own redrive policy.
+    #[arg(long, value_name = "URL")]
+    pub dlq_url: Option<String>,
+
+    /// Move messages from the dead-letter queue back onto the main queue,
+    /// report how many were moved, and exit without starting the UI.
+    #[arg(long)]
+    pub redrive_dlq: bool,
+
+    /// Upper bound on messages moved by --redrive-dlq.
+    #[arg(long, value_name = "COUNT", default_value_t = 100)]
+    pub redrive_limit: usize,
+
+    /// Serve a read-only dashboard of pending, running and completed jobs
+    /// on this port, with the same data as JSON at `/api/jobs` and
+    /// Prometheus metrics at `/metrics`. Works with --daemon too.
+    #[arg(long, value_name = "PORT")]
+    pub web_port: Option<u16>,
+
+    /// Hold launches while the cluster has this many running plus pending
+    /// tasks, rather than launching tasks that would wait for capacity.
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    pub cluster_task_ceiling: Option<u64>,
+
+    /// Fargate CPU/memory by source size, as `min_mib:cpu:memory` entries
+    /// separated by commas. Sources are sized with an S3 HEAD before launch.
+    #[arg(long, value_name = "TIERS")]
+    pub resource_tiers: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Launch a task for one object right away, bypassing SQS, then exit.
+    /// For backfills and re-runs after encoding changes.
+    Reprocess {
+        /// Source object key.
+        #[arg(long)]
+        key: String,
+
+        /// Source bucket. The transcoder only reads from its own source
+        /// bucket, so any other is refused.
+        #[arg(long, default_value = SOURCE_BUCKET)]
+        bucket: String,
+
+        /// Run the transcoder binary on this machine instead of launching
+        /// a task. Needs ffmpeg and AWS credentials here.
+        #[arg(long)]
+        local: bool,
+
+        /// Transcoder binary for --local.
+        #[arg(
+            long,
+            value_name = "PATH",
+            default_value = "video-transcoder",
+            requires = "local"
+        )]
+        transcoder_bin: PathBuf,
+    },
+}
+
+/// Region named in an SQS queue URL's host. Understands the standard
+/// `sqs.<region>.amazonaws.com`, the legacy `<region>.queue.amazonaws.com`
+/// and VPC endpoint `vpce-….sqs.<region>.vpce.amazonaws.com` forms.
+pub fn region_from_queue_url(url: &str) -> Option<String> {
+    let host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', ':'])
+        .next()?;
+    let labels: Vec<&str> = host.split('.').collect();
+    let region = match labels.iter().position(|label| *label == "sqs") {
+        Some(i) => labels.get(i + 1)?,
+        None if labels.get(1) == Some(&"queue") => labels.first()?,
+        None => return None,
+    };
+    // e.g. us-east-1, ap-southeast-2, us-gov-west-1
+    let looks_like_region = region.split('-').count() >= 3
+        && region.split('-').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        })
+        && region.ends_with(|c: char| c.is_ascii_digit());
+    looks_like_region.then(|| region.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_from_standard_queue_urls() {
+        assert_eq!(
+            region_from_queue_url("https://sqs.eu-west-1.amazonaws.com/123456789012/videos")
+                .as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            region_from_queue_url("https://sqs.us-gov-west-1.amazonaws.com/123456789012/q")
+                .as_deref(),
+            Some("us-gov-west-1")
+        );
+        assert_eq!(
+            region_from_queue_url("https://ap-southeast-2.queue.amazonaws.com/123456789012/q")
+                .as_deref(),
+            Some("ap-southeast-2")
+        );
+    }
+
+    #[test]
+    fn region_from_vpc_endpoint_urls() {
+        assert_eq!(
+            region_from_queue_url(
+                "https://vpce-0a1b2c3d4e5f-abcdefgh.sqs.us-east-2.vpce.amazonaws.com/123456789012/videos"
+            )
+            .as_deref(),
+            Some("us-east-2")
+        );
+    }
+
+    #[test]
+    fn urls_without_a_region_give_none() {
+        for url in [
+            "http://localhost:4566/000000000000/videos",
+            "https://sqs.amazonaws.com/123456789012/q",
+            "https://sqs.not_a_region.amazonaws.com/1/q",
+            "not a url",
+        ] {
+            assert_eq!(region_from_queue_url(url), None, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+use crate::app::{AppState, CompletedJob, LaunchTrigger, ToastLevel};
+use crate::launch::Launcher;
+use crate::report;
+
+/// How often the daemon re-checks for work when nothing wakes it.
+const TICK: Duration = Duration::from_secs(5);
+
+/// Runs the pipeline without a terminal: launches pending jobs as they
+/// arrive, keeping at most `max_running` tasks launching or running, and
+/// logs what happens as JSON lines on stdout. A job's message is kept
+/// hidden while its task runs and deleted only once the task succeeds;
+/// retries, the DLQ, the watchdog and `--web-port`'s `/metrics` are the
+/// same background tasks the TUI uses.
+///
+/// On SIGTERM or Ctrl-C intake stops and the daemon returns once every
+/// launched task has stopped and its message has been deleted or released.
+/// Jobs still pending were never launched and their messages never
+/// deleted, so SQS hands them out again.
+///
+/// With `report_every`, a CSV report of launched jobs is written to the
+/// export directory that often.
+pub async fn run(
+    state: Arc<Mutex<AppState>>,
+    launcher: Launcher,
+    notify: Arc<Notify>,
+    max_running: usize,
+    report_every: Option<Duration>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut draining = false;
+    let mut next_report = report_every.map(|every| Instant::now() + every);
+    log(json!({ "event": "daemon_started", "max_running": max_running }));
+
+    loop {
+        {
+            let mut st = state.lock().unwrap();
+            if let (Some(every), Some(due)) = (report_every, next_report)
+                && Instant::now() >= due
+            {
+                next_report = Some(due + every);
+                write_report(st.running_jobs(), st.export_dir.clone());
+            }
+            for toast in std::mem::take(&mut st.toasts) {
+                log(json!({
+                    "event": "notice",
+                    "level": match toast.level {
+                        ToastLevel::Error => "error",
+                        ToastLevel::Success | ToastLevel::Info => "info",
+                    },
+                    "message": toast.message,
+                }));
+            }
+
+            if draining {
+                if launcher.running_count(&st) == 0 {
+                    log(json!({
+                        "event": "daemon_stopped",
+                        "pending": st.messages.len(),
+                    }));
+                    return Ok(());
+                }
+            } else if !st.is_holding() {
+                while launcher.running_count(&st) < max_running {
+                    let Some(job) = st.take_next_launchable() else {
+                        break;
+                    };
+                    log(json!({
+                        "event": "launching",
+                        "bucket": job.bucket,
+                        "key": job.key,
+                        "queue": job.source.label,
+                        "attempt": job.attempts + 1,
+                    }));
+                    launcher.spawn(job, Arc::clone(&state), LaunchTrigger::Auto);
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = notify.notified() => {}
+            _ = sleep(TICK) => {}
+            _ = sigterm.recv(), if !draining => {
+                draining = start_drain(&state, &launcher, "SIGTERM");
+            }
+            _ = tokio::signal::ctrl_c(), if !draining => {
+                draining = start_drain(&state, &launcher, "SIGINT");
+            }
+        }
+    }
+}
+
+/// Writes the CSV report in the background so the launch loop never waits
+/// on the disk.
+fn write_report(running: Vec<CompletedJob>, dir: PathBuf) {
+    tokio::spawn(async move {
+        match report::write(&running, &dir).await {
+            Ok((path, jobs)) => log(json!({
+                "event": "report_written",
+                "path": path.display().to_string(),
+                "jobs": jobs,
+            })),
+            Err(e) => log(json!({
+                "event": "notice",
+                "level": "error",
+                "message": format!("Report failed: {}", e),
+            })),
+        }
+    });
+}
+
+/// Stops the pollers so no new work is taken on.
+fn start_drain(state: &Mutex<AppState>, launcher: &Launcher, signal: &str) -> bool {
+    let st = state.lock().unwrap();
+    st.paused.store(true, Ordering::Relaxed);
+    log(json!({
+        "event": "draining",
+        "signal": signal,
+        "running": launcher.running_count(&st),
+    }));
+    true
+}
+
+fn log(event: serde_json::Value) {
+    println!("{}", event);
+}
@@ -0,0 +1,82 @@
+use serde_json::{Value, json};
+
+use crate::app::VideoMessage;
+
+/// How many raw SQS bodies are retained for the debug overlay.
+pub const RAW_BODY_CAPACITY: usize = 20;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Debug)]
+pub struct RawBody {
+    pub body: String,
+    pub parsed: bool,
+}
+
+/// Pretty-prints a raw message body for display, masking anything that looks
+/// like a credential. Bodies that aren't valid JSON are shown as-is.
+pub fn pretty_redacted(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| body.to_string())
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Everything known about a job's message, for attaching to a bug report.
+/// The raw body is redacted the same way as in the debug overlay.
+pub fn export_message(job: &VideoMessage) -> Value {
+    let raw_body = job
+        .raw_body
+        .as_deref()
+        .map(|body| match serde_json::from_str::<Value>(body) {
+            Ok(mut value) => {
+                redact(&mut value);
+                value
+            }
+            Err(_) => Value::String(body.to_string()),
+        });
+    json!({
+        "queue_url": job.source.url,
+        "receipt_handle": job.receipt_handle,
+        "message_group_id": job.message_group_id,
+        "bucket": job.bucket,
+        "key": job.key,
+        "title": job.title,
+        "priority": job.priority.label(),
+        "attempts": job.attempts,
+        "raw_body": raw_body,
+    })
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if is_secret_field(k) {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    [
+        "secret",
+        "token",
+        "password",
+        "signature",
+        "accesskey",
+        "credential",
+    ]
+    .iter()
+    .any(|needle| name.contains(needle))
+}
@@ -0,0 +1,51 @@
+/// Failures while turning queue messages into ECS tasks.
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("failed to parse S3 event: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("unsupported EventBridge event: {0}")]
+    UnsupportedEvent(String),
+
+    #[error("invalid task configuration: {0}")]
+    TaskConfig(#[from] aws_sdk_ecs::error::BuildError),
+
+    #[error("ECS run_task failed: {0}")]
+    AwsRunTask(#[source] Box<aws_sdk_ecs::Error>),
+
+    #[error("invalid task resources: {0}")]
+    Resources(String),
+
+    #[error("SQS delete_message failed: {0}")]
+    SqsDelete(#[source] Box<aws_sdk_sqs::Error>),
+
+    #[error("SQS change_message_visibility failed: {0}")]
+    SqsVisibility(#[source] Box<aws_sdk_sqs::Error>),
+
+    #[error("SQS redrive failed: {0}")]
+    SqsRedrive(#[source] Box<aws_sdk_sqs::Error>),
+
+    #[error("invalid SQS message: {0}")]
+    SqsMessage(#[source] aws_sdk_sqs::error::BuildError),
+
+    #[error("SQS dead-letter send failed: {0}")]
+    SqsDeadLetter(#[source] Box<aws_sdk_sqs::Error>),
+
+    #[error("ECS started no task: {0}")]
+    NoTaskStarted(String),
+
+    #[error("S3 head_object failed: {0}")]
+    S3Head(#[source] Box<aws_sdk_s3::Error>),
+
+    #[error("s3://{bucket}/{key} does not exist")]
+    SourceMissing { bucket: String, key: String },
+
+    #[error("{0} is not the source bucket the transcoder reads from")]
+    ForeignBucket(String),
+
+    #[error("could not run {0}: {1}")]
+    LocalTranscoder(String, #[source] std::io::Error),
+
+    #[error("--redrive-dlq needs a dead-letter queue; pass --dlq-url")]
+    MissingDlqUrl,
+}
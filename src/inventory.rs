@@ -0,0 +1,75 @@
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::primitives::DateTimeFormat;
+use futures_util::StreamExt;
+use futures_util::stream;
+use serde::Serialize;
+
+use crate::app::VideoMessage;
+
+/// HEAD requests in flight at once during an inventory export.
+const INVENTORY_CONCURRENCY: usize = 8;
+
+/// One pending job's source object, as reported by S3.
+#[derive(Debug, Serialize)]
+pub struct InventoryRow {
+    pub bucket: String,
+    pub key: String,
+    pub size: Option<i64>,
+    pub content_type: Option<String>,
+    /// RFC 3339.
+    pub last_modified: Option<String>,
+    /// Set instead of the fields above when the lookup failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// HEADs every job's source with bounded concurrency, keeping the jobs'
+/// order. `on_progress` is called with the number done after each lookup.
+pub async fn collect(
+    s3_client: S3Client,
+    jobs: Vec<VideoMessage>,
+    on_progress: impl Fn(usize),
+) -> Vec<InventoryRow> {
+    let mut rows = Vec::with_capacity(jobs.len());
+    // Owned futures, so the stream holds no borrows across the caller's
+    // spawned task.
+    let mut lookups = stream::iter(jobs)
+        .map(|job| {
+            let s3_client = s3_client.clone();
+            async move { lookup(&s3_client, &job).await }
+        })
+        .buffered(INVENTORY_CONCURRENCY);
+    while let Some(row) = lookups.next().await {
+        rows.push(row);
+        on_progress(rows.len());
+    }
+    rows
+}
+
+async fn lookup(s3_client: &S3Client, job: &VideoMessage) -> InventoryRow {
+    let mut row = InventoryRow {
+        bucket: job.bucket.clone(),
+        key: job.key.clone(),
+        size: None,
+        content_type: None,
+        last_modified: None,
+        error: None,
+    };
+    match s3_client
+        .head_object()
+        .bucket(&job.bucket)
+        .key(&job.key)
+        .send()
+        .await
+    {
+        Ok(head) => {
+            row.size = head.content_length();
+            row.content_type = head.content_type().map(str::to_string);
+            row.last_modified = head
+                .last_modified()
+                .and_then(|at| at.fmt(DateTimeFormat::DateTime).ok());
+        }
+        Err(e) => row.error = Some(aws_sdk_s3::Error::from(e).to_string()),
+    }
+    row
+}
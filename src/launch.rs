@@ -0,0 +1,667 @@
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use aws_sdk_ecs::Client as EcsClient;
+use aws_sdk_ecs::types::{
+    AssignPublicIp, AwsVpcConfiguration, ContainerOverride, KeyValuePair, LaunchType,
+    NetworkConfiguration, Tag, TaskOverride,
+};
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sqs::Client as SqsClient;
+use aws_sdk_sqs::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_sqs::operation::delete_message::DeleteMessageError;
+use tokio::sync::{Notify, OnceCell};
+
+use crate::app::{AppState, CompletedJob, LaunchTrigger, TaskStatus, ToastLevel, VideoMessage};
+use crate::capacity::ClusterLoad;
+use crate::error::PipelineError;
+use crate::logs::LogConfig;
+use crate::resources::{self, ResourceTier, TaskResources};
+
+/// The only bucket the transcoder reads sources from.
+pub const SOURCE_BUCKET: &str = "temp-video-storage-0342";
+
+/// Where the transcoder writes each source's outputs, under its file stem.
+pub const OUTPUT_BUCKET: &str = "perm-video-storage-0342";
+
+/// Name of the transcoder container in the task definition.
+pub const CONTAINER_NAME: &str = "video-transcoder";
+
+/// Variables the launcher sets itself; `--env` may not override them.
+pub const RESERVED_ENV: [&str; 7] = [
+    "SOURCE_KEY",
+    "LAUNCH_TRIGGER",
+    "ALLOW_DESTRUCTIVE",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_REGION",
+    "AWS_SESSION_TOKEN",
+];
+
+/// Parses a `--env KEY=VALUE` argument. Only the first `=` splits, so values
+/// may contain `=` themselves.
+pub fn parse_env_var(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", arg))?;
+    let valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(format!(
+            "{:?} is not a valid environment variable name",
+            name
+        ));
+    }
+    if RESERVED_ENV.contains(&name) {
+        return Err(format!(
+            "{} is set by the launcher and cannot be overridden",
+            name
+        ));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Tags the launcher sets on every task itself; `--task-tag` may not
+/// override them.
+pub const RESERVED_TAGS: [&str; 2] = ["source-key", "bucket"];
+
+/// ECS allows 50 tags per task, two of which are [`RESERVED_TAGS`].
+pub const MAX_TASK_TAGS: usize = 48;
+
+const MAX_TAG_KEY_LEN: usize = 128;
+const MAX_TAG_VALUE_LEN: usize = 256;
+
+/// Parses a `--task-tag KEY=VALUE` argument, checking both halves against
+/// the characters and lengths ECS accepts.
+pub fn parse_task_tag(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", arg))?;
+    if key.is_empty() || key.chars().count() > MAX_TAG_KEY_LEN {
+        return Err(format!(
+            "tag key must be 1-{} characters, got {:?}",
+            MAX_TAG_KEY_LEN, key
+        ));
+    }
+    if value.chars().count() > MAX_TAG_VALUE_LEN {
+        return Err(format!(
+            "tag value for {} is longer than {} characters",
+            key, MAX_TAG_VALUE_LEN
+        ));
+    }
+    if let Some(c) = key.chars().chain(value.chars()).find(|c| !is_tag_char(*c)) {
+        return Err(format!("{:?} is not allowed in a tag: {:?}", c, arg));
+    }
+    if key.to_ascii_lowercase().starts_with("aws:") {
+        return Err(format!("tag keys starting with aws: are reserved: {}", key));
+    }
+    if RESERVED_TAGS.contains(&key) {
+        return Err(format!(
+            "{} is set by the launcher and cannot be overridden",
+            key
+        ));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Letters, digits, spaces and `+ - = . _ : / @`, the set ECS accepts in
+/// tag keys and values.
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || "+-=._:/@".contains(c)
+}
+
+/// A tag value made from an S3 name, with characters ECS rejects replaced
+/// and the length capped.
+fn tag_value(text: &str) -> String {
+    text.chars()
+        .map(|c| if is_tag_char(c) { c } else { '_' })
+        .take(MAX_TAG_VALUE_LEN)
+        .collect()
+}
+
+/// How long a launched job's message is hidden at a time. The task monitor
+/// renews it well before it lapses, for as long as the task runs.
+pub const VISIBILITY_EXTENSION: Duration = Duration::from_secs(300);
+
+/// Region the clients were built for, which may come from the queue URL
+/// rather than the environment.
+pub fn client_region(ecs_client: &EcsClient) -> String {
+    ecs_client
+        .config()
+        .region()
+        .map(|region| region.to_string())
+        .or_else(|| env::var("AWS_REGION").ok())
+        .unwrap_or_else(|| "us-east-1".to_string())
+}
+
+/// A launch's standing under `--cluster-task-ceiling`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ClusterSlot {
+    /// The cluster is at the ceiling; the job goes back to pending.
+    Full,
+    /// Holds one of the remaining slots until the task starts or fails.
+    Reserved,
+    /// No ceiling is set, or the count couldn't be read.
+    Unchecked,
+}
+
+/// Everything needed to turn a [`VideoMessage`] into a running ECS task.
+#[derive(Clone)]
+pub struct Launcher {
+    pub ecs_client: EcsClient,
+    pub sqs_client: SqsClient,
+    pub s3_client: S3Client,
+    pub cluster_name: String,
+    pub task_definition: String,
+    /// Woken whenever a launch changes what the UI shows.
+    pub notify: Arc<Notify>,
+    /// Looked up from the task definition on first launch.
+    pub log_config: Arc<OnceCell<Option<LogConfig>>>,
+    /// CPU/memory overrides chosen by source size.
+    pub resource_tiers: Arc<Vec<ResourceTier>>,
+    /// Extra container environment from `--env`.
+    pub extra_env: Arc<Vec<(String, String)>>,
+    /// Extra task tags from `--task-tag`.
+    pub task_tags: Arc<Vec<(String, String)>>,
+    /// Launches spawned but not yet finished, successfully or not.
+    pub launching: Arc<AtomicUsize>,
+    /// Most running plus pending tasks the cluster may have before
+    /// launches are held (`--cluster-task-ceiling`).
+    pub cluster_ceiling: Option<usize>,
+    pub cluster_load: Arc<ClusterLoad>,
+}
+
+impl Launcher {
+    /// CloudWatch console link for a launched task, if the task definition
+    /// logs through awslogs.
+    pub async fn log_url(&self, task_arn: &str) -> Option<String> {
+        let config = self
+            .log_config
+            .get_or_init(|| async {
+                let region = client_region(&self.ecs_client);
+                LogConfig::fetch(
+                    &self.ecs_client,
+                    &self.task_definition,
+                    CONTAINER_NAME,
+                    &region,
+                )
+                .await
+            })
+            .await
+            .as_ref()?;
+        Some(config.console_url(&config.stream_name(CONTAINER_NAME, task_arn)))
+    }
+
+    /// Task size for a job, from the source object's size. `None` keeps the
+    /// task definition's defaults.
+    pub async fn resources_for(&self, job: &VideoMessage) -> Option<TaskResources> {
+        let size = resources::source_size(&self.s3_client, &job.bucket, &job.key).await?;
+        resources::tier_for(&self.resource_tiers, size)
+    }
+
+    /// Takes a slot under `--cluster-task-ceiling` for one launch. A failed
+    /// lookup lets the launch through rather than stalling the pipeline.
+    async fn reserve_cluster_slot(&self) -> ClusterSlot {
+        let Some(ceiling) = self.cluster_ceiling else {
+            return ClusterSlot::Unchecked;
+        };
+        match self
+            .cluster_load
+            .reserve(&self.ecs_client, &self.cluster_name, ceiling)
+            .await
+        {
+            Ok(true) => ClusterSlot::Reserved,
+            Ok(false) => ClusterSlot::Full,
+            Err(e) => {
+                eprintln!("Cluster capacity check failed, launching anyway: {}", e);
+                ClusterSlot::Unchecked
+            }
+        }
+    }
+
+    /// Deletes the jobs' SQS messages in the background without launching
+    /// anything.
+    pub fn discard(&self, jobs: Vec<VideoMessage>) {
+        let launcher = self.clone();
+        tokio::spawn(async move {
+            for job in jobs.iter().filter(|job| !job.receipt_handle.is_empty()) {
+                match delete_message(&launcher.sqs_client, &job.source.url, &job.receipt_handle)
+                    .await
+                {
+                    Ok(_) => eprintln!("Discarded SQS message for key {}", job.key),
+                    Err(e) => eprintln!("Failed to discard {}: {}", job.key, e),
+                }
+            }
+            launcher.notify.notify_one();
+        });
+    }
+
+    /// Launches the job in the background and records it in the recent-jobs
+    /// list once the task has been started.
+    pub fn spawn(&self, job: VideoMessage, state: Arc<Mutex<AppState>>, trigger: LaunchTrigger) {
+        // Counted before the task starts so a caller checking capacity right
+        // after this returns sees the launch.
+        self.launching.fetch_add(1, Ordering::SeqCst);
+        let launcher = self.clone();
+        tokio::spawn(async move {
+            // Held jobs go back to pending with their messages untouched,
+            // and auto-launching pauses until the count is refreshed.
+            let slot = launcher.reserve_cluster_slot().await;
+            if slot == ClusterSlot::Full {
+                eprintln!("Holding {}: cluster at capacity", job.key);
+                if let Ok(mut st) = state.lock() {
+                    st.finish_launch(&job);
+                    st.hold_for_capacity();
+                    st.push_toast(
+                        format!("Holding {} — cluster at capacity", job.key),
+                        ToastLevel::Info,
+                    );
+                    st.push_message(job);
+                }
+                launcher.launching.fetch_sub(1, Ordering::SeqCst);
+                launcher.notify.notify_one();
+                return;
+            }
+            let resources = launcher.resources_for(&job).await;
+            // The container echoes the trigger in its completion notice.
+            let mut env = launcher.extra_env.to_vec();
+            env.push(("LAUNCH_TRIGGER".to_string(), trigger.label().to_string()));
+            match start_task(job.clone(), resources, &env, &launcher).await {
+                Ok(task_arn) => {
+                    let log_url = launcher.log_url(&task_arn).await;
+                    if let Some(url) = &log_url {
+                        eprintln!("Logs for {}: {}", job.key, url);
+                    }
+                    if slot == ClusterSlot::Reserved {
+                        launcher.cluster_load.record_launch();
+                    }
+                    if let Ok(mut st) = state.lock() {
+                        st.finish_launch(&job);
+                        st.record_launch();
+                        st.push_toast(format!("Launched {}", job.key), ToastLevel::Success);
+                        st.push_completed(CompletedJob {
+                            job,
+                            task_arn: Some(task_arn),
+                            log_url,
+                            launched_at: SystemTime::now(),
+                            finished_at: None,
+                            status: TaskStatus::Starting,
+                            trigger: Some(trigger),
+                        });
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error running ECS task: {}", e);
+                    if slot == ClusterSlot::Reserved {
+                        launcher.cluster_load.release();
+                    }
+                    if let Ok(mut st) = state.lock() {
+                        st.finish_launch(&job);
+                        st.push_toast(
+                            format!("Launch failed for {}: {}", job.key, e),
+                            ToastLevel::Error,
+                        );
+                    }
+                }
+            }
+            launcher.launching.fetch_sub(1, Ordering::SeqCst);
+            launcher.notify.notify_one();
+        });
+    }
+
+    /// Jobs being launched plus launched tasks that haven't stopped.
+    pub fn running_count(&self, st: &AppState) -> usize {
+        self.launching.load(Ordering::SeqCst) + st.active_task_count()
+    }
+}
+
+/// Starts the job's task. Its SQS message is left in place, hidden, for the
+/// task monitor to delete or release once the task stops.
+pub async fn start_task(
+    job: VideoMessage,
+    resources: Option<TaskResources>,
+    extra_env: &[(String, String)],
+    launcher: &Launcher,
+) -> Result<String, PipelineError> {
+    eprintln!("Starting ECS task for key: {}", job.key);
+    let ecs_client = &launcher.ecs_client;
+
+    let aws_access_key = env::var("AWS_ACCESS_KEY_ID").unwrap_or_default();
+    let aws_secret_key = env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default();
+    let aws_region = client_region(ecs_client);
+    let aws_session_token = env::var("AWS_SESSION_TOKEN").unwrap_or_default();
+
+    let mut env_vars = vec![
+        KeyValuePair::builder()
+            .name("SOURCE_KEY")
+            .value(job.key.clone())
+            .build(),
+        KeyValuePair::builder()
+            .name("AWS_ACCESS_KEY_ID")
+            .value(aws_access_key)
+            .build(),
+        KeyValuePair::builder()
+            .name("AWS_SECRET_ACCESS_KEY")
+            .value(aws_secret_key)
+            .build(),
+        KeyValuePair::builder()
+            .name("AWS_REGION")
+            .value(aws_region)
+            .build(),
+    ];
+    if !aws_session_token.is_empty() {
+        env_vars.push(
+            KeyValuePair::builder()
+                .name("AWS_SESSION_TOKEN")
+                .value(aws_session_token)
+                .build(),
+        );
+    }
+    env_vars.extend(
+        extra_env
+            .iter()
+            .map(|(name, value)| KeyValuePair::builder().name(name).value(value).build()),
+    );
+
+    let vpc_config = AwsVpcConfiguration::builder()
+        .subnets("subnet-0e00f1da12d6bc546")
+        .subnets("subnet-0bfd5432d208ee8ff")
+        .subnets("subnet-069cb61c8663259a3")
+        .security_groups("sg-0d946d4f1351b5bfd")
+        .assign_public_ip(AssignPublicIp::Enabled)
+        .build()?;
+
+    let network_config = NetworkConfiguration::builder()
+        .awsvpc_configuration(vpc_config)
+        .build();
+
+    let container_override = ContainerOverride::builder()
+        .name(CONTAINER_NAME)
+        .set_environment(Some(env_vars))
+        .build();
+
+    let mut task_override =
+        TaskOverride::builder().set_container_overrides(Some(vec![container_override]));
+    if let Some(resources) = resources {
+        let resources = resources.validate().map_err(PipelineError::Resources)?;
+        eprintln!(
+            "Sizing task for {}: {} CPU units, {} MiB",
+            job.key, resources.cpu, resources.memory
+        );
+        task_override = task_override
+            .cpu(resources.cpu.to_string())
+            .memory(resources.memory.to_string());
+    }
+    let task_override = task_override.build();
+
+    // For cost allocation. Cost Explorer only sees them once they are
+    // activated as cost allocation tags in the billing console.
+    let tags = [
+        ("source-key".to_string(), tag_value(&job.key)),
+        ("bucket".to_string(), tag_value(&job.bucket)),
+    ]
+    .into_iter()
+    .chain(launcher.task_tags.iter().cloned())
+    .map(|(key, value)| Tag::builder().key(key).value(value).build())
+    .collect();
+
+    let run_resp = ecs_client
+        .run_task()
+        .cluster(&launcher.cluster_name)
+        .task_definition(&launcher.task_definition)
+        .launch_type(LaunchType::Fargate)
+        .network_configuration(network_config)
+        .overrides(task_override)
+        .set_tags(Some(tags))
+        .count(1)
+        .send()
+        .await;
+
+    let out = match run_resp {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("ECS run_task error: {:?}", e);
+            return Err(PipelineError::AwsRunTask(Box::new(e.into())));
+        }
+    };
+    // ECS reports placement problems, e.g. no capacity, as `failures` on an
+    // otherwise successful call. Nothing was started, so the message stays.
+    let Some(task_arn) = out
+        .tasks()
+        .first()
+        .and_then(|t| t.task_arn())
+        .map(str::to_string)
+    else {
+        eprintln!("ECS failures: {:?}", out.failures());
+        let reason = out
+            .failures()
+            .iter()
+            .filter_map(|f| f.reason())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(PipelineError::NoTaskStarted(if reason.is_empty() {
+            "no tasks and no failures returned".to_string()
+        } else {
+            reason
+        }));
+    };
+    eprintln!("ECS started: {}", task_arn);
+
+    // The message is only deleted once the task succeeds; until then it is
+    // kept hidden so SQS doesn't hand it out again.
+    if !job.receipt_handle.is_empty()
+        && let Err(e) = set_visibility(&launcher.sqs_client, &job, VISIBILITY_EXTENSION).await
+    {
+        eprintln!("Failed to hide SQS message for key {}: {}", job.key, e);
+    }
+
+    Ok(task_arn)
+}
+
+/// Hides a job's message from other receivers for `timeout` from now. Zero
+/// makes it visible again straight away.
+pub async fn set_visibility(
+    sqs_client: &SqsClient,
+    job: &VideoMessage,
+    timeout: Duration,
+) -> Result<(), PipelineError> {
+    sqs_client
+        .change_message_visibility()
+        .queue_url(&job.source.url)
+        .receipt_handle(&job.receipt_handle)
+        .visibility_timeout(timeout.as_secs() as i32)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| PipelineError::SqsVisibility(Box::new(e.into())))
+}
+
+/// Deletes a finished job's message, logging what happened.
+pub async fn delete_job_message(sqs_client: &SqsClient, job: &VideoMessage) {
+    match delete_message(sqs_client, &job.source.url, &job.receipt_handle).await {
+        Ok(DeleteOutcome::Deleted) => eprintln!("Deleted SQS message for key {}", job.key),
+        Ok(DeleteOutcome::AlreadyGone) => eprintln!(
+            "SQS message for key {} was already gone (stale receipt handle)",
+            job.key
+        ),
+        Err(e) => eprintln!("Failed to delete SQS message: {}", e),
+    }
+}
+
+/// What happened to a consumed SQS message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    Deleted,
+    /// The receipt handle was invalid or expired. This is expected once the
+    /// visibility timeout has passed or another record of the same message
+    /// already deleted it, so it is not a failure.
+    AlreadyGone,
+}
+
+pub async fn delete_message(
+    sqs_client: &SqsClient,
+    queue_url: &str,
+    receipt_handle: &str,
+) -> Result<DeleteOutcome, PipelineError> {
+    match sqs_client
+        .delete_message()
+        .queue_url(queue_url)
+        .receipt_handle(receipt_handle)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(DeleteOutcome::Deleted),
+        Err(SdkError::ServiceError(e)) if is_stale_receipt(e.err()) => {
+            Ok(DeleteOutcome::AlreadyGone)
+        }
+        Err(e) => Err(PipelineError::SqsDelete(Box::new(e.into()))),
+    }
+}
+
+/// SQS reports an expired handle as a generic `InvalidParameterValue`, so
+/// that case is recognised by its message.
+fn is_stale_receipt(err: &DeleteMessageError) -> bool {
+    match err {
+        DeleteMessageError::ReceiptHandleIsInvalid(_) => true,
+        _ => {
+            err.code() == Some("InvalidParameterValue")
+                && err
+                    .message()
+                    .is_some_and(|m| m.to_ascii_lowercase().contains("receipt handle"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_sqs::types::error::{ReceiptHandleIsInvalid, RequestThrottled};
+
+    fn generic_error(code: &str, message: &str) -> DeleteMessageError {
+        DeleteMessageError::generic(
+            aws_sdk_sqs::error::ErrorMetadata::builder()
+                .code(code)
+                .message(message)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn invalid_receipt_handles_are_stale() {
+        let err = DeleteMessageError::ReceiptHandleIsInvalid(
+            ReceiptHandleIsInvalid::builder()
+                .message("The input receipt handle is invalid.")
+                .build(),
+        );
+        assert!(is_stale_receipt(&err));
+    }
+
+    #[test]
+    fn expired_receipt_handles_are_stale() {
+        let err = generic_error(
+            "InvalidParameterValue",
+            "Value AQEB... for parameter ReceiptHandle is invalid. Reason: The receipt handle has expired.",
+        );
+        assert!(is_stale_receipt(&err));
+    }
+
+    #[test]
+    fn other_delete_errors_are_real_failures() {
+        assert!(!is_stale_receipt(&generic_error(
+            "InvalidParameterValue",
+            "Value for parameter QueueUrl is invalid.",
+        )));
+        assert!(!is_stale_receipt(&generic_error(
+            "AccessDenied",
+            "Access to the resource is denied. The receipt handle was not checked.",
+        )));
+        assert!(!is_stale_receipt(&DeleteMessageError::RequestThrottled(
+            RequestThrottled::builder().build(),
+        )));
+    }
+
+    #[test]
+    fn env_values_may_contain_equals_signs() {
+        assert_eq!(
+            parse_env_var("FFMPEG_EXTRA=-x264-params=keyint=48").unwrap(),
+            (
+                "FFMPEG_EXTRA".to_string(),
+                "-x264-params=keyint=48".to_string()
+            )
+        );
+        assert_eq!(
+            parse_env_var("EMPTY=").unwrap(),
+            ("EMPTY".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn invalid_env_args_are_rejected() {
+        for arg in [
+            "NO_VALUE",
+            "=value",
+            "1ST=x",
+            "BAD-NAME=x",
+            "SOURCE_KEY=other.mp4",
+            "AWS_REGION=eu-west-1",
+        ] {
+            assert!(parse_env_var(arg).is_err(), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn task_tags_split_on_the_first_equals() {
+        assert_eq!(
+            parse_task_tag("cost-center=media"),
+            Ok(("cost-center".to_string(), "media".to_string()))
+        );
+        assert_eq!(
+            parse_task_tag("query=a=b"),
+            Ok(("query".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_task_tag("team=Video Platform @ HQ"),
+            Ok(("team".to_string(), "Video Platform @ HQ".to_string()))
+        );
+        assert_eq!(
+            parse_task_tag("empty="),
+            Ok(("empty".to_string(), String::new()))
+        );
+    }
+
+    #[test]
+    fn task_tags_are_checked_against_ecs_limits() {
+        assert!(parse_task_tag("no-equals").is_err());
+        assert!(parse_task_tag("=value").is_err());
+        assert!(parse_task_tag(&format!("{}=v", "k".repeat(MAX_TAG_KEY_LEN))).is_ok());
+        assert!(parse_task_tag(&format!("{}=v", "k".repeat(MAX_TAG_KEY_LEN + 1))).is_err());
+        assert!(parse_task_tag(&format!("k={}", "v".repeat(MAX_TAG_VALUE_LEN))).is_ok());
+        assert!(parse_task_tag(&format!("k={}", "v".repeat(MAX_TAG_VALUE_LEN + 1))).is_err());
+        assert!(parse_task_tag("team=a,b").is_err());
+        assert!(parse_task_tag("te#am=a").is_err());
+    }
+
+    #[test]
+    fn task_tags_cannot_claim_reserved_keys() {
+        assert!(parse_task_tag("aws:createdBy=me").is_err());
+        assert!(parse_task_tag("AWS:createdBy=me").is_err());
+        for key in RESERVED_TAGS {
+            assert!(parse_task_tag(&format!("{}=x", key)).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn tag_values_from_s3_names_are_made_acceptable() {
+        assert_eq!(tag_value("uploads/my clip.mp4"), "uploads/my clip.mp4");
+        assert_eq!(
+            tag_value("uploads/clip#1,final.mp4"),
+            "uploads/clip_1_final.mp4"
+        );
+        assert_eq!(tag_value(&"a".repeat(300)).len(), MAX_TAG_VALUE_LEN);
+    }
+}
@@ -0,0 +1,82 @@
+use aws_sdk_ecs::Client as EcsClient;
+use aws_sdk_ecs::types::LogDriver;
+
+/// The `awslogs` settings of the transcoder container, used to point at a
+/// launched task's log stream.
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    pub group: String,
+    pub stream_prefix: String,
+    pub region: String,
+}
+
+impl LogConfig {
+    /// Reads the awslogs options for `container_name` from the task
+    /// definition. Returns `None` if the container doesn't log to CloudWatch.
+    pub async fn fetch(
+        ecs_client: &EcsClient,
+        task_definition: &str,
+        container_name: &str,
+        default_region: &str,
+    ) -> Option<Self> {
+        let resp = match ecs_client
+            .describe_task_definition()
+            .task_definition(task_definition)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                eprintln!("describe_task_definition error: {:?}", e);
+                return None;
+            }
+        };
+        let container = resp
+            .task_definition()?
+            .container_definitions()
+            .iter()
+            .find(|c| c.name() == Some(container_name))?;
+        let log = container.log_configuration()?;
+        if log.log_driver() != &LogDriver::Awslogs {
+            return None;
+        }
+        let options = log.options()?;
+        Some(Self {
+            group: options.get("awslogs-group")?.clone(),
+            stream_prefix: options.get("awslogs-stream-prefix")?.clone(),
+            region: options
+                .get("awslogs-region")
+                .cloned()
+                .unwrap_or_else(|| default_region.to_string()),
+        })
+    }
+
+    /// awslogs names streams `<prefix>/<container>/<task id>`.
+    pub fn stream_name(&self, container_name: &str, task_arn: &str) -> String {
+        let task_id = task_arn.rsplit('/').next().unwrap_or(task_arn);
+        format!("{}/{}/{}", self.stream_prefix, container_name, task_id)
+    }
+
+    pub fn console_url(&self, stream_name: &str) -> String {
+        format!(
+            "https://{region}.console.aws.amazon.com/cloudwatch/home?region={region}#logsV2:log-groups/log-group/{group}/log-events/{stream}",
+            region = self.region,
+            group = console_escape(&self.group),
+            stream = console_escape(stream_name),
+        )
+    }
+}
+
+/// The CloudWatch console double-encodes path segments in its fragment and
+/// swaps `%` for `$`, so `/` becomes `$252F`.
+fn console_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("$25{:02X}", b));
+        }
+    }
+    out
+}
@@ -1,425 +1,742 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::{env, io, time::Duration};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{io, time::Duration};
 
+use aws_config::BehaviorVersion;
+use aws_config::timeout::TimeoutConfig;
 use aws_sdk_ecs::Client as EcsClient;
-use aws_sdk_ecs::types::{
-    AssignPublicIp, AwsVpcConfiguration, ContainerOverride, KeyValuePair, LaunchType,
-    NetworkConfiguration, TaskOverride,
-};
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sns::Client as SnsClient;
 use aws_sdk_sqs::Client as SqsClient;
 
-use crossterm::event::{self, Event as CEvent, KeyCode};
+use crossterm::cursor::Show;
+use crossterm::event::{Event as CEvent, EventStream, KeyCode};
 use crossterm::execute;
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 
-use ratatui::{
-    Terminal,
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-};
+use ratatui::{Terminal, backend::CrosstermBackend};
 
+use futures_util::StreamExt;
+use tokio::sync::{Notify, OnceCell};
 use tokio::time::sleep;
 
+mod actions;
+mod app;
+mod capacity;
+mod cli;
+mod daemon;
+mod debug;
+mod error;
+mod inventory;
+mod launch;
+mod logs;
+mod monitor;
+mod poller;
+mod redrive;
+mod report;
+mod reprocess;
+mod resources;
+mod throughput;
 mod types;
-use types::S3Event;
-
-#[derive(Clone, Debug)]
-struct VideoMessage {
-    bucket: String,
-    key: String,
-    receipt_handle: String,
-}
+mod ui;
+mod web;
+use actions::{Action, palette_matches};
+use app::{
+    AppState, CompletedJob, ConfirmAction, Focus, LaunchTrigger, Palette, Priority, QueueSource,
+    ToastLevel, VideoMessage,
+};
+use capacity::ClusterLoad;
+use clap::Parser;
+use error::PipelineError;
+use launch::Launcher;
 
-#[derive(Debug)]
-struct AppState {
-    messages: Vec<VideoMessage>,
-    selected: usize,
-}
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = cli::Args::parse();
+    let idle_timeout = (args.idle_timeout > 0).then(|| Duration::from_secs(args.idle_timeout));
+    let resource_tiers = match &args.resource_tiers {
+        Some(v) => resources::parse_tiers(v)?,
+        None => resources::default_tiers(),
+    };
+    if args.task_tags.len() > launch::MAX_TASK_TAGS {
+        return Err(format!(
+            "at most {} --task-tag flags are allowed",
+            launch::MAX_TASK_TAGS
+        )
+        .into());
+    }
 
-impl AppState {
-    fn new() -> Self {
-        Self {
-            messages: Vec::new(),
-            selected: 0,
+    let inferred_region = cli::region_from_queue_url(&args.queue_url[0]);
+    let region = match (&args.region, &inferred_region) {
+        (Some(region), Some(inferred)) if region != inferred => {
+            eprintln!(
+                "Warning: --region {} does not match the queue URL's region {}",
+                region, inferred
+            );
+            Some(region.clone())
         }
+        (Some(region), _) => Some(region.clone()),
+        (None, inferred) => inferred.clone(),
+    };
+
+    let mut loader =
+        aws_config::defaults(BehaviorVersion::latest()).timeout_config(aws_timeouts()?);
+    if let Some(region) = region {
+        loader = loader.region(aws_config::Region::new(region));
     }
+    let config = loader.load().await;
+    let sqs_client = SqsClient::new(&config);
+    let ecs_client = EcsClient::new(&config);
+    let s3_client = S3Client::new(&config);
 
-    fn push_message(&mut self, m: VideoMessage) {
-        self.messages.push(m);
-        if self.messages.len() == 1 {
-            self.selected = 0;
-        }
+    let queues: Vec<Arc<QueueSource>> = args
+        .queue_url
+        .iter()
+        .map(|url| Arc::new(QueueSource::new(url)))
+        .collect();
+    let cluster_name = "0342-video";
+    let task_definition = "video-transcoder:1";
+
+    if args.redrive_dlq {
+        let dlq_url = args.dlq_url.ok_or(PipelineError::MissingDlqUrl)?;
+        // Messages go back to the first queue; a DLQ belongs to one queue.
+        let queue_url = &queues[0].url;
+        let count =
+            redrive::redrive_dlq(&sqs_client, &dlq_url, queue_url, args.redrive_limit).await?;
+        println!(
+            "Re-drove {} message(s) from {} to {}",
+            count, dlq_url, queue_url
+        );
+        return Ok(());
+    }
+
+    let state = Arc::new(Mutex::new(AppState {
+        compact: args.compact,
+        export_dir: args.export_dir.clone(),
+        ..AppState::new()
+    }));
+    let notify = Arc::new(Notify::new());
+
+    let mut extra_env = args.env;
+    if args.allow_destructive {
+        eprintln!("Destructive deletes from permanent buckets are enabled");
+        extra_env.push(("ALLOW_DESTRUCTIVE".to_string(), "true".to_string()));
     }
 
-    fn remove_selected(&mut self) -> Option<VideoMessage> {
-        if self.messages.is_empty() {
-            return None;
+    let launcher = Launcher {
+        ecs_client: ecs_client.clone(),
+        sqs_client: sqs_client.clone(),
+        s3_client,
+        cluster_name: cluster_name.to_string(),
+        task_definition: task_definition.to_string(),
+        notify: Arc::clone(&notify),
+        log_config: Arc::new(OnceCell::new()),
+        resource_tiers: Arc::new(resource_tiers),
+        extra_env: Arc::new(extra_env),
+        task_tags: Arc::new(args.task_tags.clone()),
+        launching: Arc::new(AtomicUsize::new(0)),
+        cluster_ceiling: args.cluster_task_ceiling.map(|n| n as usize),
+        cluster_load: Arc::new(ClusterLoad::default()),
+    };
+
+    if let Some(cli::Command::Reprocess {
+        key,
+        bucket,
+        local: true,
+        transcoder_bin,
+    }) = &args.command
+    {
+        let status = reprocess::reprocess_local(&launcher, transcoder_bin, bucket, key).await?;
+        if !status.success() {
+            return Err(format!("transcoder {}", status).into());
         }
-        if self.selected >= self.messages.len() {
-            self.selected = self.messages.len() - 1;
+        return Ok(());
+    }
+    if let Some(cli::Command::Reprocess { key, bucket, .. }) = &args.command {
+        let arn = reprocess::reprocess(&launcher, Arc::clone(&queues[0]), bucket, key).await?;
+        println!("Launched {} for s3://{}/{}", arn, bucket, key);
+        if let Some(url) = launcher.log_url(&arn).await {
+            println!("Logs: {}", url);
         }
-        Some(self.messages.remove(self.selected))
+        return Ok(());
     }
 
-    fn next(&mut self) {
-        if !self.messages.is_empty() {
-            self.selected = (self.selected + 1).min(self.messages.len() - 1);
+    // Catch up on the backlog left from downtime before the pollers start,
+    // so only messages that were already waiting are auto-launched.
+    if args.drain_on_start {
+        let mut received = 0;
+        for source in &queues {
+            received += poller::drain_queue(
+                source,
+                &sqs_client,
+                &launcher.s3_client,
+                &state,
+                &notify,
+                args.drain_limit - received,
+            )
+            .await;
+        }
+        // Taken one at a time in queue order, so priorities hold and only
+        // the first job of each FIFO group goes now; the rest wait for it.
+        let (jobs, waiting) = {
+            let mut st = state.lock().unwrap();
+            let jobs: Vec<_> = std::iter::from_fn(|| st.take_next_launchable()).collect();
+            (jobs, st.messages.len())
+        };
+        println!(
+            "Drained {} message(s); launching {} job(s), {} waiting",
+            received,
+            jobs.len(),
+            waiting
+        );
+        for job in jobs {
+            launcher.spawn(job, Arc::clone(&state), LaunchTrigger::Auto);
         }
     }
 
-    fn previous(&mut self) {
-        if !self.messages.is_empty() {
-            if self.selected == 0 {
-                self.selected = 0;
-            } else {
-                self.selected -= 1;
-            }
-        }
+    let paused = Arc::clone(&state.lock().unwrap().paused);
+    for source in queues
+        .iter()
+        .flat_map(|source| std::iter::repeat_n(source, args.poll_concurrency.into()))
+    {
+        tokio::spawn(poller::poll_queue(
+            Arc::clone(source),
+            sqs_client.clone(),
+            launcher.s3_client.clone(),
+            Arc::clone(&state),
+            Arc::clone(&notify),
+            Arc::clone(&paused),
+        ));
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = aws_config::load_from_env().await;
-    let sqs_client = SqsClient::new(&config);
-    let ecs_client = EcsClient::new(&config);
+    tokio::spawn(monitor::adopt_running_tasks(
+        ecs_client.clone(),
+        cluster_name.to_string(),
+        task_definition.to_string(),
+        Arc::clone(&queues[0]),
+        Arc::clone(&state),
+        Arc::clone(&notify),
+    ));
+    tokio::spawn(monitor::monitor_tasks(
+        ecs_client.clone(),
+        cluster_name.to_string(),
+        Arc::clone(&state),
+        Arc::clone(&notify),
+        (args.task_timeout > 0).then(|| Duration::from_secs(args.task_timeout)),
+        monitor::RetryPolicy {
+            max_retries: args.max_retries,
+            sqs_client: sqs_client.clone(),
+            dlq_url: args.dlq_url.clone(),
+            s3_client: launcher.s3_client.clone(),
+        },
+    ));
+    tokio::spawn(monitor::keep_messages_hidden(
+        sqs_client.clone(),
+        Arc::clone(&state),
+    ));
+
+    if args.deadman_window > 0 {
+        tokio::spawn(monitor::watch_completions(
+            args.alert_topic_arn
+                .clone()
+                .map(|arn| (SnsClient::new(&config), arn)),
+            Arc::clone(&state),
+            Arc::clone(&notify),
+            Duration::from_secs(args.deadman_window),
+        ));
+    }
 
-    let queue_url = "https://sqs.us-east-1.amazonaws.com/091049244748/video-pipeline-queue-0342";
-    let cluster_name = "0342-video";
-    let task_definition = "video-transcoder:1";
+    // Bind before the UI starts so a port in use fails the launch.
+    if let Some(port) = args.web_port {
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+        tokio::spawn(web::serve(listener, Arc::clone(&state)));
+    }
 
-    let state = Arc::new(Mutex::new(AppState::new()));
+    if args.daemon {
+        return daemon::run(
+            state,
+            launcher,
+            notify,
+            args.max_running as usize,
+            args.report_interval.map(Duration::from_secs),
+        )
+        .await;
+    }
 
-    let sqs_for_poller = sqs_client.clone();
-    let queue_for_poller = queue_url.to_string();
-    let state_for_poller = Arc::clone(&state);
+    install_panic_hook();
+    let _guard = TerminalGuard::enter()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
 
-    tokio::spawn(async move {
-        loop {
-            let resp = sqs_for_poller
-                .receive_message()
-                .queue_url(&queue_for_poller)
-                .max_number_of_messages(10)
-                .wait_time_seconds(4)
-                .send()
-                .await;
-
-            match resp {
-                Ok(output) => {
-                    if let Some(messages) = output.messages {
-                        for msg in messages {
-                            if let (Some(body), Some(receipt)) = (msg.body(), msg.receipt_handle())
-                            {
-                                match serde_json::from_str::<S3Event>(body) {
-                                    Ok(event) => {
-                                        for rec in event.records {
-                                            let v = VideoMessage {
-                                                bucket: rec.s3.bucket.name,
-                                                key: rec.s3.object.key,
-                                                receipt_handle: receipt.to_string(),
-                                            };
-                                            if let Ok(mut st) = state_for_poller.lock() {
-                                                st.push_message(v.clone());
-                                                eprintln!(
-                                                    "SQS poller: added job {} / {} (total {})",
-                                                    v.bucket,
-                                                    v.key,
-                                                    st.messages.len()
-                                                );
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!(
-                                            "Failed to parse S3 event from SQS body: {:?}. body: {}",
-                                            e, body
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("SQS receive_message error: {:?}", e);
-                }
-            }
+    let result = run_app(&mut terminal, state, launcher, idle_timeout, notify).await;
 
-            sleep(Duration::from_secs(5)).await;
-        }
-    });
+    restore_terminal()?;
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    result
+}
 
-    let result = run_app(
-        &mut terminal,
-        state,
-        sqs_client,
-        ecs_client,
-        queue_url.to_string(),
-        cluster_name.to_string(),
-        task_definition.to_string(),
-    )
-    .await;
+/// Redraw interval when nothing else happens, so relative ages stay current.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long before an idle exit the countdown is shown in the status line.
+const IDLE_COUNTDOWN: Duration = Duration::from_secs(10);
+
+/// Ceiling on any one SQS, ECS or S3 call (`AWS_OP_TIMEOUT_SECS`). Receives
+/// long-poll for a few seconds, well inside this.
+const DEFAULT_AWS_OP_TIMEOUT: Duration = Duration::from_secs(30);
+const AWS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeouts for the AWS clients. A call stuck on a dead connection fails
+/// and goes through the usual retry and error paths instead of wedging the
+/// poller or a launch.
+fn aws_timeouts() -> Result<TimeoutConfig, String> {
+    let operation = match std::env::var("AWS_OP_TIMEOUT_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid AWS_OP_TIMEOUT_SECS: {}", v))?,
+        Err(_) => DEFAULT_AWS_OP_TIMEOUT,
+    };
+    Ok(TimeoutConfig::builder()
+        .connect_timeout(AWS_CONNECT_TIMEOUT)
+        .operation_timeout(operation)
+        .build())
+}
+
+static TERMINAL_RESTORED: AtomicBool = AtomicBool::new(false);
+
+/// Restores the terminal on drop so an early return or panic inside the UI
+/// never leaves the shell in raw mode.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        TERMINAL_RESTORED.store(false, Ordering::SeqCst);
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
 
+/// Leaves raw mode and the alternate screen. Only the first call does any
+/// work, so the normal quit path, the guard and the panic hook can all call it.
+fn restore_terminal() -> io::Result<()> {
+    if TERMINAL_RESTORED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+    Ok(())
+}
 
-    result
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
 }
 
 async fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     state: Arc<Mutex<AppState>>,
-    sqs_client: SqsClient,
-    ecs_client: EcsClient,
-    queue_url: String,
-    cluster_name: String,
-    task_definition: String,
+    launcher: Launcher,
+    idle_timeout: Option<Duration>,
+    notify: Arc<Notify>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        let (items_snapshot, selected_index) = {
-            let st = state.lock().unwrap();
-            (st.messages.clone(), st.selected)
-        };
+    let mut events = EventStream::new();
+    let mut last_interaction = Instant::now();
+    let mut dirty = true;
 
-        terminal.draw(|f| {
-            let size = f.area();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(1)
-                .constraints(
-                    [
-                        Constraint::Length(4),
-                        Constraint::Min(6),
-                        Constraint::Length(3),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            let header = Paragraph::new(vec![
-                Line::from(Span::styled(
-                    "Transcode no Jutsu",
-                    Style::default()
-                        .fg(Color::LightCyan)
-                        .add_modifier(Modifier::BOLD),
-                )),
-                Line::from(Span::raw(
-                    "A highly efficient video transcoding library built in Rust using FFmpeg",
-                )),
-            ])
-            .block(Block::default().borders(Borders::ALL).title("About"));
-            f.render_widget(header, chunks[0]);
-
-            if items_snapshot.is_empty() {
-                let empty = Paragraph::new("No video upload events yet...").block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title("Pending Uploads"),
-                );
-                f.render_widget(empty, chunks[1]);
-            } else {
-                let list_items: Vec<ListItem> = items_snapshot
-                    .iter()
-                    .map(|m| {
-                        let line = format!("{} / {}", m.bucket, m.key);
-                        ListItem::new(Span::raw(line))
-                    })
-                    .collect();
-
-                let mut list_state = ratatui::widgets::ListState::default();
-                list_state.select(Some(selected_index));
-
-                let list = List::new(list_items)
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .title("Pending Uploads"),
-                    )
-                    .highlight_style(
-                        Style::default()
-                            .bg(Color::Blue)
-                            .fg(Color::White)
-                            .add_modifier(Modifier::BOLD),
-                    )
-                    .highlight_symbol(">> ");
-
-                f.render_stateful_widget(list, chunks[1], &mut list_state);
+    loop {
+        let (wait, tick_redraws) = {
+            let mut st = state.lock().unwrap();
+            let toast_change = st.expire_toasts();
+
+            // Both keypresses and new queue messages count as activity.
+            let idle_remaining = idle_timeout.map(|timeout| {
+                let idle_for = last_interaction
+                    .elapsed()
+                    .min(st.last_queue_activity.elapsed());
+                timeout.saturating_sub(idle_for)
+            });
+            if idle_remaining == Some(Duration::ZERO) {
+                break;
+            }
+            let idle_countdown = idle_remaining.filter(|remaining| *remaining <= IDLE_COUNTDOWN);
+
+            if dirty {
+                let ctx = ui::ViewContext {
+                    idle_countdown: idle_countdown.map(|remaining| remaining.as_secs() + 1),
+                };
+                let mut page_size = st.page_size;
+                terminal.draw(|f| page_size = ui::draw(f, &st, &ctx))?;
+                st.page_size = page_size;
+                dirty = false;
             }
 
-            let help =
-                Paragraph::new("Controls: ↑/↓ to move • Enter to transcode selected • q to quit")
-                    .block(Block::default().borders(Borders::ALL).title("Help"));
-            f.render_widget(help, chunks[2]);
-        })?;
-
-        tokio::task::yield_now().await;
-
-        if event::poll(Duration::from_millis(150))? {
-            if let CEvent::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Down => {
-                        let mut st = state.lock().unwrap();
-                        st.next();
+            // Sleep until something time-based on screen needs to change:
+            // every second during the idle countdown, at the start of the
+            // countdown, when a toast fades or expires, or a slow tick to
+            // keep relative ages fresh.
+            let wait = match (idle_remaining, idle_countdown) {
+                (_, Some(remaining)) => remaining.min(Duration::from_secs(1)),
+                (Some(remaining), None) => (remaining - IDLE_COUNTDOWN).min(REFRESH_INTERVAL),
+                (None, None) => REFRESH_INTERVAL,
+            };
+            // With none of those on screen the tick only re-checks the idle
+            // timeout and the frame is left as is.
+            let countdown_due = idle_remaining.is_some_and(|remaining| {
+                remaining.saturating_sub(IDLE_COUNTDOWN) <= REFRESH_INTERVAL
+            });
+            let ages_shown = !st.completed.is_empty() || st.stalled;
+            let tick_redraws = countdown_due || toast_change.is_some() || ages_shown;
+            (
+                toast_change.map_or(wait, |change| wait.min(change)),
+                tick_redraws,
+            )
+        };
+
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(CEvent::Key(key))) => {
+                    last_interaction = Instant::now();
+                    dirty = true;
+                    if handle_key(key.code, &state, &launcher) {
+                        break;
                     }
-                    KeyCode::Up => {
-                        let mut st = state.lock().unwrap();
-                        st.previous();
+                }
+                Some(Ok(CEvent::Resize(_, _))) => {
+                    // Resize the buffers now so the redraw below uses the
+                    // new size rather than waiting for the next draw to notice.
+                    terminal.autoresize()?;
+                    dirty = true;
+                }
+                Some(Ok(_)) => dirty = true,
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            _ = notify.notified() => dirty = true,
+            _ = sleep(wait) => dirty |= tick_redraws,
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies a keypress to the state. Returns `true` when the app should quit.
+fn handle_key(code: KeyCode, state: &Arc<Mutex<AppState>>, launcher: &Launcher) -> bool {
+    let mut st = state.lock().unwrap();
+
+    if let Some(action) = st.confirm.clone() {
+        match code {
+            KeyCode::Char('y') => {
+                st.confirm = None;
+                match action {
+                    ConfirmAction::Replay(job) => {
+                        launcher.spawn(job, Arc::clone(state), LaunchTrigger::Manual);
                     }
-                    KeyCode::Enter => {
-                        let maybe_job = {
-                            let mut st = state.lock().unwrap();
-                            st.remove_selected()
-                        };
-                        if let Some(job) = maybe_job {
-                            let ecs_for_task = ecs_client.clone();
-                            let sqs_for_task = sqs_client.clone();
-                            let queue_for_task = queue_url.clone();
-                            let cluster_for_task = cluster_name.clone();
-                            let task_def_for_task = task_definition.clone();
-
-                            tokio::spawn(async move {
-                                if let Err(e) = run_and_delete(
-                                    job,
-                                    ecs_for_task,
-                                    sqs_for_task,
-                                    queue_for_task,
-                                    cluster_for_task,
-                                    task_def_for_task,
-                                )
-                                .await
-                                {
-                                    eprintln!("Error running ECS task: {:?}", e);
-                                }
-                            });
-                        }
+                    ConfirmAction::DeleteChecked(_) => {
+                        let jobs = st.remove_checked();
+                        st.push_toast(
+                            format!("Deleting {} job(s)", jobs.len()),
+                            ToastLevel::Success,
+                        );
+                        launcher.discard(jobs);
                     }
-                    _ => {}
                 }
             }
+            KeyCode::Char('n') | KeyCode::Esc => st.confirm = None,
+            _ => {}
         }
-        sleep(Duration::from_millis(20)).await;
+        return false;
     }
 
-    Ok(())
+    if let Some(palette) = st.palette.as_mut() {
+        match code {
+            KeyCode::Esc => st.palette = None,
+            KeyCode::Char(c) => {
+                palette.query.push(c);
+                palette.selected = 0;
+            }
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            KeyCode::Down => palette.selected += 1,
+            KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                let matches = palette_matches(&palette.query);
+                let chosen = matches
+                    .get(palette.selected.min(matches.len().saturating_sub(1)))
+                    .copied();
+                st.palette = None;
+                if let Some(action) = chosen {
+                    return apply_action(action, &mut st, state, launcher);
+                }
+            }
+            _ => {}
+        }
+        return false;
+    }
+
+    if st.show_help {
+        if matches!(code, KeyCode::Char('?') | KeyCode::Esc) {
+            st.show_help = false;
+        }
+        return false;
+    }
+
+    if st.show_debug {
+        match code {
+            KeyCode::Char('d') | KeyCode::Esc => st.show_debug = false,
+            KeyCode::Down => st.debug_next(),
+            KeyCode::Up => st.debug_previous(),
+            _ => {}
+        }
+        return false;
+    }
+
+    match Action::from_key(code) {
+        Some(action) => apply_action(action, &mut st, state, launcher),
+        None => false,
+    }
 }
 
-async fn run_and_delete(
+/// Writes a job's message to a timestamped JSON file off the UI thread and
+/// reports the outcome with a toast.
+fn export_message(
     job: VideoMessage,
-    ecs_client: EcsClient,
-    sqs_client: SqsClient,
-    queue_url: String,
-    cluster_name: String,
-    task_definition: String,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    eprintln!("Starting ECS task for key: {}", job.key);
-
-    let aws_access_key = env::var("AWS_ACCESS_KEY_ID").unwrap_or_default();
-    let aws_secret_key = env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default();
-    let aws_region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
-    let aws_session_token = env::var("AWS_SESSION_TOKEN").unwrap_or_default();
-
-    let mut env_vars = vec![
-        KeyValuePair::builder()
-            .name("SOURCE_KEY")
-            .value(job.key.clone())
-            .build(),
-        KeyValuePair::builder()
-            .name("AWS_ACCESS_KEY_ID")
-            .value(aws_access_key)
-            .build(),
-        KeyValuePair::builder()
-            .name("AWS_SECRET_ACCESS_KEY")
-            .value(aws_secret_key)
-            .build(),
-        KeyValuePair::builder()
-            .name("AWS_REGION")
-            .value(aws_region)
-            .build(),
-    ];
-    if !aws_session_token.is_empty() {
-        env_vars.push(
-            KeyValuePair::builder()
-                .name("AWS_SESSION_TOKEN")
-                .value(aws_session_token)
-                .build(),
-        );
-    }
+    dir: PathBuf,
+    state: &Arc<Mutex<AppState>>,
+    launcher: &Launcher,
+) {
+    let state = Arc::clone(state);
+    let notify = Arc::clone(&launcher.notify);
+    tokio::spawn(async move {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let name: String = job
+            .key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = dir.join(format!("message-{}-{}.json", stamp, name));
+        let contents =
+            serde_json::to_string_pretty(&debug::export_message(&job)).unwrap_or_default();
+        let result = match tokio::fs::create_dir_all(&dir).await {
+            Ok(()) => tokio::fs::write(&path, contents).await,
+            Err(e) => Err(e),
+        };
+        if let Ok(mut st) = state.lock() {
+            match result {
+                Ok(()) => st.push_toast(
+                    format!("Exported to {}", path.display()),
+                    ToastLevel::Success,
+                ),
+                Err(e) => st.push_toast(format!("Export failed: {}", e), ToastLevel::Error),
+            }
+        }
+        notify.notify_one();
+    });
+}
 
-    let vpc_config = AwsVpcConfiguration::builder()
-        .subnets("subnet-0e00f1da12d6bc546")
-        .subnets("subnet-0bfd5432d208ee8ff")
-        .subnets("subnet-069cb61c8663259a3")
-        .security_groups("sg-0d946d4f1351b5bfd")
-        .assign_public_ip(AssignPublicIp::Enabled)
-        .build()?;
-
-    let network_config = NetworkConfiguration::builder()
-        .awsvpc_configuration(vpc_config)
-        .build();
-
-    let container_override = ContainerOverride::builder()
-        .name("video-transcoder")
-        .set_environment(Some(env_vars))
-        .build();
-
-    let task_override = TaskOverride::builder()
-        .set_container_overrides(Some(vec![container_override]))
-        .build();
-
-    let run_resp = ecs_client
-        .run_task()
-        .cluster(cluster_name)
-        .task_definition(task_definition)
-        .launch_type(LaunchType::Fargate)
-        .network_configuration(network_config)
-        .overrides(task_override)
-        .count(1)
-        .send()
+/// Writes a CSV report of launched jobs off the UI thread and reports the
+/// outcome with a toast.
+fn export_report(
+    running: Vec<CompletedJob>,
+    dir: PathBuf,
+    state: &Arc<Mutex<AppState>>,
+    launcher: &Launcher,
+) {
+    let state = Arc::clone(state);
+    let notify = Arc::clone(&launcher.notify);
+    tokio::spawn(async move {
+        let result = report::write(&running, &dir).await;
+        if let Ok(mut st) = state.lock() {
+            match result {
+                Ok((path, jobs)) => st.push_toast(
+                    format!("Report of {} job(s) written to {}", jobs, path.display()),
+                    ToastLevel::Success,
+                ),
+                Err(e) => st.push_toast(format!("Report failed: {}", e), ToastLevel::Error),
+            }
+        }
+        notify.notify_one();
+    });
+}
+
+/// HEADs every pending job's source in the background and writes the
+/// results to `inventory-<unix>.json` in `dir`, with toasts as it goes.
+fn export_inventory(
+    jobs: Vec<VideoMessage>,
+    dir: PathBuf,
+    state: &Arc<Mutex<AppState>>,
+    launcher: &Launcher,
+) {
+    let state = Arc::clone(state);
+    let notify = Arc::clone(&launcher.notify);
+    let s3_client = launcher.s3_client.clone();
+    tokio::spawn(async move {
+        let total = jobs.len();
+        let quarter = total.div_ceil(4).max(1);
+        let rows = inventory::collect(s3_client, jobs, |done| {
+            if done % quarter != 0 || done == total {
+                return;
+            }
+            if let Ok(mut st) = state.lock() {
+                st.push_toast(
+                    format!("Inventory: {}/{} looked up", done, total),
+                    ToastLevel::Info,
+                );
+            }
+            notify.notify_one();
+        })
         .await;
 
-    match run_resp {
-        Ok(out) => {
-            if let Some(tasks) = out.tasks {
-                if let Some(t) = tasks.get(0) {
-                    eprintln!("ECS started: {:?}", t.task_arn());
-                }
-            } else if let Some(failures) = out.failures {
-                eprintln!("ECS failures: {:?}", failures);
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = dir.join(format!("inventory-{}.json", stamp));
+        let contents = serde_json::to_string_pretty(&rows).unwrap_or_default();
+        let result = match tokio::fs::create_dir_all(&dir).await {
+            Ok(()) => tokio::fs::write(&path, contents).await,
+            Err(e) => Err(e),
+        };
+        let failed = rows.iter().filter(|row| row.error.is_some()).count();
+        if let Ok(mut st) = state.lock() {
+            match result {
+                Ok(()) if failed > 0 => st.push_toast(
+                    format!(
+                        "Inventory of {} job(s) written to {} ({} lookup(s) failed)",
+                        total,
+                        path.display(),
+                        failed
+                    ),
+                    ToastLevel::Error,
+                ),
+                Ok(()) => st.push_toast(
+                    format!(
+                        "Inventory of {} job(s) written to {}",
+                        total,
+                        path.display()
+                    ),
+                    ToastLevel::Success,
+                ),
+                Err(e) => st.push_toast(format!("Inventory failed: {}", e), ToastLevel::Error),
+            }
+        }
+        notify.notify_one();
+    });
+}
+
+/// Runs an action, whether it came from a key binding or the palette.
+/// Returns `true` when the app should quit.
+fn apply_action(
+    action: Action,
+    st: &mut AppState,
+    state: &Arc<Mutex<AppState>>,
+    launcher: &Launcher,
+) -> bool {
+    // A pending job the bucket filter hides can't be acted on.
+    let on_pending_job = st.focus == Focus::Pending && st.selected_message().is_some();
+    match action {
+        Action::Quit => return true,
+        Action::MoveDown => st.next(),
+        Action::MoveUp => st.previous(),
+        Action::MoveTop => st.select_first(),
+        Action::MoveBottom => st.select_last(),
+        Action::PageUp => st.page_up(),
+        Action::PageDown => st.page_down(),
+        Action::SwitchPanel => st.toggle_focus(),
+        Action::RaisePriority if on_pending_job => st.reprioritize_selected(Priority::raised),
+        Action::LowerPriority if on_pending_job => st.reprioritize_selected(Priority::lowered),
+        Action::ToggleDebug => st.show_debug = true,
+        Action::ToggleHelp => st.show_help = true,
+        Action::ToggleCompact => st.compact = !st.compact,
+        Action::CycleBucketFilter => st.cycle_bucket_filter(),
+        Action::TogglePause => {
+            st.toggle_paused();
+            let message = if st.is_paused() {
+                "Intake paused"
             } else {
-                eprintln!("ECS run_task returned neither tasks nor failures.");
+                "Intake resumed"
+            };
+            st.push_toast(message, ToastLevel::Info);
+        }
+        Action::CommandPalette => st.palette = Some(Palette::default()),
+        Action::Replay if st.focus == Focus::Completed => {
+            if let Some(done) = st.selected_completed() {
+                // Replays have no SQS message behind them, so there is
+                // nothing to delete after launch.
+                let job = VideoMessage {
+                    receipt_handle: String::new(),
+                    message_group_id: None,
+                    attempts: 0,
+                    ..done.job.clone()
+                };
+                st.confirm = Some(ConfirmAction::Replay(job));
             }
         }
-        Err(e) => {
-            eprintln!("ECS run_task error: {:?}", e);
-            return Err(Box::new(e));
+        Action::ExportMessage => {
+            let job = match st.focus {
+                Focus::Pending => st.selected_message().cloned(),
+                Focus::Completed => st.selected_completed().map(|done| done.job.clone()),
+            };
+            if let Some(job) = job {
+                export_message(job, st.export_dir.clone(), state, launcher);
+            }
         }
-    }
-
-    if !job.receipt_handle.is_empty() {
-        match sqs_client
-            .delete_message()
-            .queue_url(&queue_url)
-            .receipt_handle(job.receipt_handle.clone())
-            .send()
-            .await
-        {
-            Ok(_) => eprintln!("Deleted SQS message for key {}", job.key),
-            Err(e) => eprintln!("Failed to delete SQS message: {:?}", e),
+        Action::ExportInventory if !st.messages.is_empty() => {
+            st.push_toast(
+                format!("Looking up {} pending job(s)...", st.messages.len()),
+                ToastLevel::Info,
+            );
+            export_inventory(st.messages.clone(), st.export_dir.clone(), state, launcher);
+        }
+        Action::ExportReport => {
+            export_report(st.running_jobs(), st.export_dir.clone(), state, launcher);
+        }
+        Action::ToggleChecked if on_pending_job => st.toggle_checked_selected(),
+        Action::CheckAll if st.focus == Focus::Pending => st.check_all(),
+        Action::ClearChecked => st.checked.clear(),
+        Action::LaunchChecked => {
+            let (jobs, skipped) = st.take_checked_launchable();
+            for reason in skipped {
+                st.push_toast(format!("Not launching: {}", reason), ToastLevel::Error);
+            }
+            for job in jobs {
+                launcher.spawn(job, Arc::clone(state), LaunchTrigger::Manual);
+            }
+        }
+        Action::DeleteChecked if !st.checked.is_empty() => {
+            st.confirm = Some(ConfirmAction::DeleteChecked(st.checked.len()));
         }
+        Action::Launch if on_pending_job => match st.take_launchable_selected() {
+            Ok(Some(job)) => launcher.spawn(job, Arc::clone(state), LaunchTrigger::Manual),
+            Ok(None) => {}
+            Err(reason) => st.push_toast(format!("Not launching: {}", reason), ToastLevel::Error),
+        },
+        _ => {}
     }
-
-    Ok(())
+    false
 }
@@ -0,0 +1,507 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use aws_sdk_ecs::Client as EcsClient;
+use aws_sdk_ecs::types::{DesiredStatus, Task};
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sns::Client as SnsClient;
+use aws_sdk_sqs::Client as SqsClient;
+use serde_json::json;
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+use crate::app::{
+    AppState, CompletedJob, LaunchTrigger, Priority, QueueSource, TaskStatus, ToastLevel,
+    VideoMessage,
+};
+use crate::launch::{self, CONTAINER_NAME};
+use crate::redrive;
+use crate::report;
+
+/// How often launched tasks are checked with `describe_tasks`.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `describe_tasks` accepts at most this many ARNs per call.
+const DESCRIBE_BATCH: usize = 100;
+
+/// How often the completion watchdog checks for a stall.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often held messages of running tasks have their visibility renewed.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How failed and timed-out jobs are retried.
+pub struct RetryPolicy {
+    /// Times a job is retried before it is given up on.
+    pub max_retries: u32,
+    /// Deletes, releases and dead-letters the jobs' messages.
+    pub sqs_client: SqsClient,
+    /// Where jobs that exhaust their retries are sent, if anywhere.
+    pub dlq_url: Option<String>,
+    /// Totals finished jobs' outputs for the job history.
+    pub s3_client: S3Client,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Re-queue the job for this attempt, counting the first try as 1.
+    Retry { attempt: u32 },
+    /// Give up and dead-letter the job.
+    Exhausted,
+}
+
+/// The one place the retry budget is applied. `attempts` is how many tries
+/// came before the one that just failed.
+pub fn retry_decision(attempts: u32, max_retries: u32) -> RetryDecision {
+    if attempts < max_retries {
+        RetryDecision::Retry {
+            attempt: attempts + 2,
+        }
+    } else {
+        RetryDecision::Exhausted
+    }
+}
+
+/// What became of a stopped task's job.
+enum Settled {
+    Succeeded,
+    /// Retried as `attempt`, once SQS redelivers the released message.
+    Retrying {
+        attempt: u32,
+    },
+    GaveUp {
+        reason: String,
+    },
+}
+
+/// Tracks launched tasks until they stop, and stops any that run past
+/// `task_timeout`. A job's message is deleted once its task succeeds. When
+/// the task fails or times out the message is made visible again so SQS
+/// redelivers it, while `retry` allows, and is sent to the DLQ after that.
+pub async fn monitor_tasks(
+    ecs_client: EcsClient,
+    cluster_name: String,
+    state: Arc<Mutex<AppState>>,
+    notify: Arc<Notify>,
+    task_timeout: Option<Duration>,
+    retry: RetryPolicy,
+) {
+    loop {
+        sleep(MONITOR_INTERVAL).await;
+
+        let arns = match state.lock() {
+            Ok(st) => st.active_task_arns(),
+            Err(_) => continue,
+        };
+        for batch in arns.chunks(DESCRIBE_BATCH) {
+            let tasks = match ecs_client
+                .describe_tasks()
+                .cluster(&cluster_name)
+                .set_tasks(Some(batch.to_vec()))
+                .send()
+                .await
+            {
+                Ok(out) => out.tasks.unwrap_or_default(),
+                Err(e) => {
+                    eprintln!("ECS describe_tasks error: {:?}", e);
+                    continue;
+                }
+            };
+
+            for task in &tasks {
+                let Some(arn) = task.task_arn() else {
+                    continue;
+                };
+                let status = match task.last_status() {
+                    Some("RUNNING") if is_overdue(task, task_timeout) => {
+                        stop_overdue(&ecs_client, &cluster_name, arn, task_timeout).await
+                    }
+                    Some("RUNNING") => TaskStatus::Running,
+                    Some("STOPPED") => TaskStatus::Stopped {
+                        exit_code: transcoder_exit_code(task),
+                    },
+                    _ => continue,
+                };
+
+                let Some(job) = state
+                    .lock()
+                    .ok()
+                    .and_then(|st| st.job_for_status_change(arn, status))
+                else {
+                    continue;
+                };
+                // The message is dealt with before the status is recorded,
+                // so a drain waiting for tasks to stop can't exit while a
+                // delete is still in flight.
+                let settled = if status.is_active() {
+                    None
+                } else {
+                    Some(settle(&retry, &job, status).await)
+                };
+
+                let Ok(mut st) = state.lock() else {
+                    continue;
+                };
+                st.set_task_status(arn, status);
+                notify.notify_one();
+                if !status.is_active()
+                    && let Some(done) = st.task(arn)
+                {
+                    report::record_in_background(
+                        done.clone(),
+                        retry.s3_client.clone(),
+                        st.export_dir.clone(),
+                    );
+                }
+                match settled {
+                    None | Some(Settled::Succeeded) => {}
+                    Some(Settled::Retrying { attempt }) => {
+                        let reason = failure_reason(status).unwrap_or_default();
+                        eprintln!("Retrying {} ({}), attempt {}", job.key, reason, attempt);
+                        st.push_toast(
+                            format!("{} {}; retrying (attempt {})", job.key, reason, attempt),
+                            ToastLevel::Info,
+                        );
+                    }
+                    Some(Settled::GaveUp { reason }) => {
+                        eprintln!("Giving up on {}: {}", job.key, reason);
+                        st.push_toast(
+                            format!("Gave up on {}: {}", job.key, reason),
+                            ToastLevel::Error,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Deletes, releases or dead-letters a stopped task's message according to
+/// how the task ended and the retry budget. Jobs without a message, such as
+/// tasks adopted before their message came back, are left alone: requeuing
+/// them would launch the job a second time once the message does return.
+async fn settle(retry: &RetryPolicy, job: &VideoMessage, status: TaskStatus) -> Settled {
+    let has_message = !job.receipt_handle.is_empty();
+    let Some(reason) = failure_reason(status) else {
+        if has_message {
+            launch::delete_job_message(&retry.sqs_client, job).await;
+        }
+        return Settled::Succeeded;
+    };
+    match retry_decision(job.attempts, retry.max_retries) {
+        RetryDecision::Retry { attempt } => {
+            // SQS counts the redelivery in `ApproximateReceiveCount`, which
+            // is where the next try's attempt number comes from.
+            if has_message
+                && let Err(e) = launch::set_visibility(&retry.sqs_client, job, Duration::ZERO).await
+            {
+                eprintln!("Failed to release SQS message for key {}: {}", job.key, e);
+            }
+            Settled::Retrying { attempt }
+        }
+        RetryDecision::Exhausted => {
+            let reason = format!("{} after {} attempt(s)", reason, job.attempts + 1);
+            // The message is only deleted once a copy is in the DLQ. Without
+            // one it is kept, and comes back once its visibility lapses
+            // until the queue's own redrive policy moves it, rather than
+            // being lost.
+            let parked = match &retry.dlq_url {
+                Some(dlq_url) => {
+                    match redrive::dead_letter(&retry.sqs_client, dlq_url, job, &reason).await {
+                        Ok(true) => {
+                            eprintln!("Sent {} to the DLQ", job.key);
+                            true
+                        }
+                        Ok(false) => {
+                            eprintln!("Not dead-lettering {}: no message body", job.key);
+                            false
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to dead-letter {}: {}", job.key, e);
+                            false
+                        }
+                    }
+                }
+                None => {
+                    eprintln!("No --dlq-url; leaving {} on the queue", job.key);
+                    false
+                }
+            };
+            if parked && has_message {
+                launch::delete_job_message(&retry.sqs_client, job).await;
+            }
+            Settled::GaveUp { reason }
+        }
+    }
+}
+
+/// Renews the visibility of running tasks' messages so SQS doesn't hand
+/// them to another receiver mid-transcode. SQS caps a message's total
+/// invisibility at 12 hours, so `--task-timeout` should stay under that.
+pub async fn keep_messages_hidden(sqs_client: SqsClient, state: Arc<Mutex<AppState>>) {
+    loop {
+        sleep(HEARTBEAT_INTERVAL).await;
+
+        let held = match state.lock() {
+            Ok(st) => st.held_messages(),
+            Err(_) => continue,
+        };
+        for job in &held {
+            if let Err(e) =
+                launch::set_visibility(&sqs_client, job, launch::VISIBILITY_EXTENSION).await
+            {
+                eprintln!("Failed to renew SQS message for key {}: {}", job.key, e);
+            }
+        }
+    }
+}
+
+/// Finds tasks still running from before this process started and records
+/// them as launched jobs, so a restart doesn't launch a second task for a
+/// key whose message was never deleted. The key comes from the task's
+/// `SOURCE_KEY` override. Adopted jobs have no message until the poller
+/// receives it again and matches it to them with
+/// [`AppState::claim_for_adopted`].
+pub async fn adopt_running_tasks(
+    ecs_client: EcsClient,
+    cluster_name: String,
+    task_definition: String,
+    source: Arc<QueueSource>,
+    state: Arc<Mutex<AppState>>,
+    notify: Arc<Notify>,
+) {
+    let family = task_definition
+        .split(':')
+        .next()
+        .unwrap_or(&task_definition);
+    let mut arns = Vec::new();
+    let mut pages = ecs_client
+        .list_tasks()
+        .cluster(&cluster_name)
+        .family(family)
+        .desired_status(DesiredStatus::Running)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        match page {
+            Ok(page) => arns.extend(page.task_arns().iter().cloned()),
+            Err(e) => {
+                eprintln!("ECS list_tasks error: {:?}", e);
+                return;
+            }
+        }
+    }
+
+    for batch in arns.chunks(DESCRIBE_BATCH) {
+        let tasks = match ecs_client
+            .describe_tasks()
+            .cluster(&cluster_name)
+            .set_tasks(Some(batch.to_vec()))
+            .send()
+            .await
+        {
+            Ok(out) => out.tasks.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("ECS describe_tasks error: {:?}", e);
+                continue;
+            }
+        };
+        let Ok(mut st) = state.lock() else {
+            return;
+        };
+        for task in &tasks {
+            let Some(key) = override_env(task, "SOURCE_KEY") else {
+                continue;
+            };
+            if st.active_task_for(&key).is_some() {
+                continue;
+            }
+            eprintln!("Adopting running task {:?} for {}", task.task_arn(), key);
+            st.push_completed(CompletedJob {
+                job: VideoMessage {
+                    bucket: String::new(),
+                    priority: Priority::from_key(&key),
+                    key,
+                    receipt_handle: String::new(),
+                    source: Arc::clone(&source),
+                    message_group_id: None,
+                    attempts: 0,
+                    raw_body: None,
+                    title: None,
+                },
+                task_arn: task.task_arn().map(str::to_string),
+                log_url: None,
+                launched_at: task
+                    .created_at()
+                    .and_then(|at| SystemTime::try_from(*at).ok())
+                    .unwrap_or_else(SystemTime::now),
+                finished_at: None,
+                status: TaskStatus::Starting,
+                trigger: override_env(task, "LAUNCH_TRIGGER")
+                    .as_deref()
+                    .and_then(LaunchTrigger::parse),
+            });
+        }
+    }
+    notify.notify_one();
+}
+
+/// A variable from the transcoder container's environment overrides.
+fn override_env(task: &Task, name: &str) -> Option<String> {
+    task.overrides()?
+        .container_overrides()
+        .iter()
+        .filter(|c| c.name() == Some(CONTAINER_NAME))
+        .flat_map(|c| c.environment())
+        .find(|kv| kv.name() == Some(name))
+        .and_then(|kv| kv.value())
+        .map(str::to_string)
+}
+
+fn is_overdue(task: &Task, task_timeout: Option<Duration>) -> bool {
+    let Some(timeout) = task_timeout else {
+        return false;
+    };
+    task.started_at()
+        .and_then(|at| SystemTime::try_from(*at).ok())
+        .and_then(|at| at.elapsed().ok())
+        .is_some_and(|running_for| running_for > timeout)
+}
+
+/// Stops an overdue task. If `stop_task` fails the task is still reported
+/// as running, so the next poll tries again.
+async fn stop_overdue(
+    ecs_client: &EcsClient,
+    cluster_name: &str,
+    arn: &str,
+    task_timeout: Option<Duration>,
+) -> TaskStatus {
+    let reason = format!(
+        "Exceeded task timeout of {}s",
+        task_timeout.unwrap_or_default().as_secs()
+    );
+    match ecs_client
+        .stop_task()
+        .cluster(cluster_name)
+        .task(arn)
+        .reason(reason)
+        .send()
+        .await
+    {
+        Ok(_) => {
+            eprintln!("Stopped overdue task {}", arn);
+            TaskStatus::TimedOut
+        }
+        Err(e) => {
+            eprintln!("ECS stop_task error for {}: {:?}", arn, e);
+            TaskStatus::Running
+        }
+    }
+}
+
+/// Why a task's job needs retrying, if it does.
+fn failure_reason(status: TaskStatus) -> Option<String> {
+    match status {
+        TaskStatus::TimedOut => Some("timed out".to_string()),
+        TaskStatus::Stopped { exit_code: Some(0) } => None,
+        TaskStatus::Stopped {
+            exit_code: Some(code),
+        } => Some(format!("failed with exit code {}", code)),
+        TaskStatus::Stopped { exit_code: None } => Some("stopped without an exit code".to_string()),
+        TaskStatus::Starting | TaskStatus::Running => None,
+    }
+}
+
+fn transcoder_exit_code(task: &Task) -> Option<i32> {
+    task.containers()
+        .iter()
+        .find(|c| c.name() == Some(CONTAINER_NAME))
+        .and_then(|c| c.exit_code())
+}
+
+/// Dead man's switch: alerts once when jobs keep being launched but none
+/// has succeeded for longer than `window`, e.g. because every task fails.
+/// The alert goes to the log, the UI and, if given, an SNS topic. Any
+/// successful completion re-arms it.
+pub async fn watch_completions(
+    sns: Option<(SnsClient, String)>,
+    state: Arc<Mutex<AppState>>,
+    notify: Arc<Notify>,
+    window: Duration,
+) {
+    loop {
+        sleep(WATCHDOG_INTERVAL).await;
+
+        let (stalled_for, pending) = match state.lock() {
+            Ok(mut st) if !st.stalled => match st.stalled_for(window) {
+                Some(stalled_for) => {
+                    st.stalled = true;
+                    (stalled_for, st.messages.len())
+                }
+                None => continue,
+            },
+            _ => continue,
+        };
+        notify.notify_one();
+        eprintln!(
+            "Watchdog: no job has completed successfully in {}s of launching",
+            stalled_for.as_secs()
+        );
+
+        if let Some((client, topic_arn)) = &sns {
+            let message = json!({
+                "event": "pipeline_stalled",
+                "no_success_secs": stalled_for.as_secs(),
+                "window_secs": window.as_secs(),
+                "pending": pending,
+            });
+            if let Err(e) = client
+                .publish()
+                .topic_arn(topic_arn)
+                .subject("Transcoding pipeline stalled")
+                .message(message.to_string())
+                .send()
+                .await
+            {
+                eprintln!("Watchdog: failed to publish alert: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_until_the_budget_is_spent() {
+        assert_eq!(retry_decision(0, 2), RetryDecision::Retry { attempt: 2 });
+        assert_eq!(retry_decision(1, 2), RetryDecision::Retry { attempt: 3 });
+        assert_eq!(retry_decision(2, 2), RetryDecision::Exhausted);
+    }
+
+    #[test]
+    fn zero_retries_gives_up_after_the_first_try() {
+        assert_eq!(retry_decision(0, 0), RetryDecision::Exhausted);
+    }
+
+    #[test]
+    fn redeliveries_beyond_the_budget_are_exhausted() {
+        // Attempts come from ApproximateReceiveCount, which can overshoot
+        // the budget when SQS redelivers on its own.
+        assert_eq!(retry_decision(7, 2), RetryDecision::Exhausted);
+        assert_eq!(retry_decision(u32::MAX, 2), RetryDecision::Exhausted);
+    }
+
+    #[test]
+    fn only_failed_stops_are_retried() {
+        assert_eq!(
+            failure_reason(TaskStatus::Stopped { exit_code: Some(0) }),
+            None
+        );
+        assert_eq!(failure_reason(TaskStatus::Running), None);
+        assert!(failure_reason(TaskStatus::Stopped { exit_code: Some(1) }).is_some());
+        assert!(failure_reason(TaskStatus::Stopped { exit_code: None }).is_some());
+        assert!(failure_reason(TaskStatus::TimedOut).is_some());
+    }
+}
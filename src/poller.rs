@@ -0,0 +1,406 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sqs::Client as SqsClient;
+use aws_sdk_sqs::types::{Message, MessageSystemAttributeName};
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+use crate::app::{AppState, Priority, QueueSource, TaskStatus, VideoMessage};
+use crate::launch::delete_message;
+use crate::types::{decode_s3_key, parse_event};
+
+/// Pause between receive calls on one receiver.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest pause after repeated receive errors.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Receives S3 events from one queue forever, adding a pending job per
+/// record. `--poll-concurrency` of these run per `--queue-url`.
+pub async fn poll_queue(
+    source: Arc<QueueSource>,
+    sqs_client: SqsClient,
+    s3_client: S3Client,
+    state: Arc<Mutex<AppState>>,
+    notify: Arc<Notify>,
+    paused: Arc<AtomicBool>,
+) {
+    let is_fifo = source.url.ends_with(".fifo");
+    let mut backoff = POLL_INTERVAL;
+    loop {
+        // Operators can quiesce intake without quitting; pending jobs
+        // can still be launched while paused.
+        if paused.load(Ordering::Relaxed) {
+            sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+
+        let mut request = sqs_client
+            .receive_message()
+            .queue_url(&source.url)
+            .max_number_of_messages(10)
+            .wait_time_seconds(4)
+            .message_system_attribute_names(MessageSystemAttributeName::ApproximateReceiveCount);
+        if is_fifo {
+            request =
+                request.message_system_attribute_names(MessageSystemAttributeName::MessageGroupId);
+        }
+
+        match request.send().await {
+            Ok(output) => {
+                backoff = POLL_INTERVAL;
+                handle_messages(
+                    &source,
+                    output.messages.unwrap_or_default(),
+                    &sqs_client,
+                    &s3_client,
+                    &state,
+                    &notify,
+                );
+            }
+            Err(e) => {
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                eprintln!(
+                    "SQS receive_message error on {} (retrying in {}s): {:?}",
+                    source.label,
+                    backoff.as_secs(),
+                    e
+                );
+            }
+        }
+
+        sleep(backoff).await;
+    }
+}
+
+/// Receives whatever is already waiting on the queue, without long polling,
+/// until the queue comes back empty or `limit` messages have been taken.
+/// Returns how many messages were received.
+pub async fn drain_queue(
+    source: &Arc<QueueSource>,
+    sqs_client: &SqsClient,
+    s3_client: &S3Client,
+    state: &Arc<Mutex<AppState>>,
+    notify: &Arc<Notify>,
+    limit: usize,
+) -> usize {
+    let is_fifo = source.url.ends_with(".fifo");
+    let mut received = 0;
+    while received < limit {
+        let mut request = sqs_client
+            .receive_message()
+            .queue_url(&source.url)
+            .max_number_of_messages((limit - received).min(10) as i32)
+            .wait_time_seconds(0)
+            .message_system_attribute_names(MessageSystemAttributeName::ApproximateReceiveCount);
+        if is_fifo {
+            request =
+                request.message_system_attribute_names(MessageSystemAttributeName::MessageGroupId);
+        }
+        match request.send().await {
+            Ok(output) => {
+                let messages = output.messages.unwrap_or_default();
+                if messages.is_empty() {
+                    break;
+                }
+                received += messages.len();
+                handle_messages(source, messages, sqs_client, s3_client, state, notify);
+            }
+            Err(e) => {
+                eprintln!("SQS receive_message error on {}: {:?}", source.label, e);
+                break;
+            }
+        }
+    }
+    received
+}
+
+/// Adds a pending job for every S3 record in a batch of received messages.
+fn handle_messages(
+    source: &Arc<QueueSource>,
+    messages: Vec<Message>,
+    sqs_client: &SqsClient,
+    s3_client: &S3Client,
+    state: &Arc<Mutex<AppState>>,
+    notify: &Arc<Notify>,
+) {
+    for msg in messages {
+        if let (Some(body), Some(receipt)) = (msg.body(), msg.receipt_handle()) {
+            let message_group_id = msg
+                .attributes()
+                .and_then(|a| a.get(&MessageSystemAttributeName::MessageGroupId))
+                .cloned();
+            // A message SQS has handed out before was received and dropped
+            // without being deleted, which counts against the retry budget.
+            let attempts = msg
+                .attributes()
+                .and_then(|a| a.get(&MessageSystemAttributeName::ApproximateReceiveCount))
+                .and_then(|count| count.parse::<u32>().ok())
+                .unwrap_or(1)
+                .saturating_sub(1);
+            let parsed = parse_event(body);
+            if let Ok(mut st) = state.lock() {
+                st.push_raw_body(body, parsed.is_ok());
+            }
+            notify.notify_one();
+            match parsed {
+                // Valid, but filtered down to nothing. It can never become a
+                // job, and left undeleted SQS would hand it out forever.
+                Ok(event) if event.records.is_empty() => {
+                    eprintln!(
+                        "SQS poller [{}]: message has no S3 records, deleting it",
+                        source.label
+                    );
+                    tokio::spawn(discard_message(
+                        sqs_client.clone(),
+                        Arc::clone(source),
+                        receipt.to_string(),
+                    ));
+                }
+                Ok(event) => {
+                    for rec in event.records {
+                        let key = decode_s3_key(&rec.s3.object.key);
+                        let v = VideoMessage {
+                            bucket: rec.s3.bucket.name,
+                            priority: Priority::from_key(&key),
+                            key,
+                            receipt_handle: receipt.to_string(),
+                            source: Arc::clone(source),
+                            message_group_id: message_group_id.clone(),
+                            attempts,
+                            raw_body: Some(Arc::from(body)),
+                            title: None,
+                        };
+                        if let Ok(mut st) = state.lock() {
+                            match st.claim_for_adopted(&v) {
+                                Some(status) if status.is_active() => {
+                                    eprintln!(
+                                        "SQS poller [{}]: {} / {} is running as an adopted task, holding its message",
+                                        source.label, v.bucket, v.key
+                                    );
+                                    continue;
+                                }
+                                Some(TaskStatus::Stopped { exit_code: Some(0) }) => {
+                                    eprintln!(
+                                        "SQS poller [{}]: {} / {} was finished by an adopted task, deleting its message",
+                                        source.label, v.bucket, v.key
+                                    );
+                                    tokio::spawn(discard_message(
+                                        sqs_client.clone(),
+                                        Arc::clone(source),
+                                        receipt.to_string(),
+                                    ));
+                                    continue;
+                                }
+                                // A failed adopted task is retried like any
+                                // other job.
+                                _ => {}
+                            }
+                            if st.refresh_pending(&v) {
+                                eprintln!(
+                                    "SQS poller [{}]: {} / {} is already pending, taking the newer delivery",
+                                    source.label, v.bucket, v.key
+                                );
+                                continue;
+                            }
+                            st.record_intake();
+                            st.push_message(v.clone());
+                            eprintln!(
+                                "SQS poller [{}]: added job {} / {} (total {})",
+                                source.label,
+                                v.bucket,
+                                v.key,
+                                st.messages.len()
+                            );
+                        }
+                        tokio::spawn(lookup_title(
+                            s3_client.clone(),
+                            v.bucket,
+                            v.key,
+                            Arc::clone(state),
+                            Arc::clone(notify),
+                        ));
+                        notify.notify_one();
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Failed to parse S3 event from SQS body: {}. body: {}",
+                        e, body
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Deletes a message that needs no job.
+async fn discard_message(sqs_client: SqsClient, source: Arc<QueueSource>, receipt_handle: String) {
+    if let Err(e) = delete_message(&sqs_client, &source.url, &receipt_handle).await {
+        eprintln!(
+            "SQS poller [{}]: failed to delete message: {}",
+            source.label, e
+        );
+    }
+}
+
+/// Fills in a pending job's title from its source's `title` metadata, as
+/// set by the upload endpoint. Jobs without one keep showing their key.
+async fn lookup_title(
+    s3_client: S3Client,
+    bucket: String,
+    key: String,
+    state: Arc<Mutex<AppState>>,
+    notify: Arc<Notify>,
+) {
+    let title = match s3_client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+    {
+        Ok(head) => head.metadata().and_then(|m| m.get("title")).cloned(),
+        Err(e) => {
+            eprintln!("S3 head_object error for {} / {}: {:?}", bucket, key, e);
+            None
+        }
+    };
+    if let Some(title) = title
+        && let Ok(mut st) = state.lock()
+    {
+        st.set_title(&bucket, &key, title);
+        notify.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clients pointed at a closed port, so any call they make fails fast.
+    fn offline_clients() -> (SqsClient, S3Client) {
+        let sqs = aws_sdk_sqs::config::Builder::new()
+            .behavior_version(aws_sdk_sqs::config::BehaviorVersion::latest())
+            .region(aws_sdk_sqs::config::Region::new("us-east-1"))
+            .endpoint_url("http://127.0.0.1:1")
+            .build();
+        let s3 = aws_sdk_s3::config::Builder::new()
+            .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new("us-east-1"))
+            .endpoint_url("http://127.0.0.1:1")
+            .build();
+        (SqsClient::from_conf(sqs), S3Client::from_conf(s3))
+    }
+
+    fn message(body: &str) -> Message {
+        Message::builder()
+            .body(body)
+            .receipt_handle("receipt")
+            .build()
+    }
+
+    #[tokio::test]
+    async fn empty_events_add_no_jobs() {
+        let (sqs_client, s3_client) = offline_clients();
+        let source = Arc::new(QueueSource::new(
+            "https://sqs.us-east-1.amazonaws.com/123456789012/uploads",
+        ));
+        let state = Arc::new(Mutex::new(AppState::new()));
+        let notify = Arc::new(Notify::new());
+        let messages = vec![
+            message(r#"{"Records":[]}"#),
+            message(r#"{"Records":[{"s3":{"bucket":{"name":"b"},"object":{"key":"k.mp4"}}}]}"#),
+        ];
+
+        handle_messages(&source, messages, &sqs_client, &s3_client, &state, &notify);
+
+        let st = state.lock().unwrap();
+        let keys: Vec<&str> = st.messages.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["k.mp4"]);
+        // The empty event was valid, not a parse failure.
+        assert!(st.raw_bodies.iter().all(|raw| raw.parsed));
+    }
+
+    fn delivery(body: &str, receipt: &str, receive_count: &str) -> Message {
+        Message::builder()
+            .body(body)
+            .receipt_handle(receipt)
+            .attributes(
+                MessageSystemAttributeName::ApproximateReceiveCount,
+                receive_count,
+            )
+            .build()
+    }
+
+    #[tokio::test]
+    async fn redeliveries_update_the_pending_job() {
+        let (sqs_client, s3_client) = offline_clients();
+        let source = Arc::new(QueueSource::new(
+            "https://sqs.us-east-1.amazonaws.com/123456789012/uploads",
+        ));
+        let state = Arc::new(Mutex::new(AppState::new()));
+        let notify = Arc::new(Notify::new());
+        let body = r#"{"Records":[{"s3":{"bucket":{"name":"b"},"object":{"key":"k.mp4"}}}]}"#;
+
+        for (receipt, count) in [("first", "1"), ("second", "2")] {
+            let messages = vec![delivery(body, receipt, count)];
+            handle_messages(&source, messages, &sqs_client, &s3_client, &state, &notify);
+        }
+
+        let st = state.lock().unwrap();
+        assert_eq!(st.messages.len(), 1);
+        assert_eq!(st.messages[0].receipt_handle, "second");
+        assert_eq!(st.messages[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn deliveries_for_an_adopted_task_attach_to_it() {
+        let (sqs_client, s3_client) = offline_clients();
+        let source = Arc::new(QueueSource::new(
+            "https://sqs.us-east-1.amazonaws.com/123456789012/uploads",
+        ));
+        let state = Arc::new(Mutex::new(AppState::new()));
+        let notify = Arc::new(Notify::new());
+        state
+            .lock()
+            .unwrap()
+            .push_completed(crate::app::CompletedJob {
+                job: VideoMessage {
+                    bucket: String::new(),
+                    key: "k.mp4".to_string(),
+                    receipt_handle: String::new(),
+                    source: Arc::clone(&source),
+                    priority: Priority::Normal,
+                    message_group_id: None,
+                    attempts: 0,
+                    raw_body: None,
+                    title: None,
+                },
+                task_arn: Some("arn:task".to_string()),
+                log_url: None,
+                launched_at: std::time::SystemTime::now(),
+                finished_at: None,
+                status: TaskStatus::Running,
+                trigger: None,
+            });
+        let body = r#"{"Records":[{"s3":{"bucket":{"name":"b"},"object":{"key":"k.mp4"}}}]}"#;
+
+        handle_messages(
+            &source,
+            vec![delivery(body, "held", "2")],
+            &sqs_client,
+            &s3_client,
+            &state,
+            &notify,
+        );
+
+        let st = state.lock().unwrap();
+        assert!(st.messages.is_empty());
+        assert_eq!(st.completed[0].job.receipt_handle, "held");
+        assert_eq!(st.completed[0].job.bucket, "b");
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use aws_sdk_sqs::Client as SqsClient;
+use aws_sdk_sqs::types::{MessageAttributeValue, MessageSystemAttributeName};
+
+use crate::app::VideoMessage;
+use crate::error::PipelineError;
+
+/// SQS returns at most this many messages per receive.
+const BATCH_SIZE: i32 = 10;
+
+/// Moves up to `limit` messages from the dead-letter queue back onto the
+/// main queue, deleting each from the DLQ only after it was re-sent.
+/// Returns how many messages were re-driven.
+pub async fn redrive_dlq(
+    sqs_client: &SqsClient,
+    dlq_url: &str,
+    queue_url: &str,
+    limit: usize,
+) -> Result<usize, PipelineError> {
+    let is_fifo = queue_url.ends_with(".fifo");
+    let mut redriven = 0;
+
+    while redriven < limit {
+        let batch = BATCH_SIZE.min((limit - redriven) as i32);
+        let output = sqs_client
+            .receive_message()
+            .queue_url(dlq_url)
+            .max_number_of_messages(batch)
+            .wait_time_seconds(1)
+            .message_system_attribute_names(MessageSystemAttributeName::MessageGroupId)
+            .send()
+            .await
+            .map_err(|e| PipelineError::SqsRedrive(Box::new(e.into())))?;
+
+        let messages = output.messages.unwrap_or_default();
+        if messages.is_empty() {
+            break;
+        }
+
+        for msg in messages {
+            let (Some(body), Some(receipt)) = (msg.body(), msg.receipt_handle()) else {
+                continue;
+            };
+
+            let mut send = sqs_client
+                .send_message()
+                .queue_url(queue_url)
+                .message_body(body);
+            if is_fifo {
+                // Keep the original group so ordering still holds, and reuse
+                // the DLQ message id so a retried redrive is deduplicated.
+                let group = msg
+                    .attributes()
+                    .and_then(|a| a.get(&MessageSystemAttributeName::MessageGroupId))
+                    .cloned()
+                    .unwrap_or_else(|| "redrive".to_string());
+                send = send
+                    .message_group_id(group)
+                    .set_message_deduplication_id(msg.message_id().map(str::to_string));
+            }
+            send.send()
+                .await
+                .map_err(|e| PipelineError::SqsRedrive(Box::new(e.into())))?;
+
+            sqs_client
+                .delete_message()
+                .queue_url(dlq_url)
+                .receipt_handle(receipt)
+                .send()
+                .await
+                .map_err(|e| PipelineError::SqsDelete(Box::new(e.into())))?;
+            redriven += 1;
+        }
+        eprintln!("Re-drove {} message(s) so far", redriven);
+    }
+
+    Ok(redriven)
+}
+
+/// Sends a job that ran out of retries to the dead-letter queue, with the
+/// reason in a `FailureReason` attribute. The stored body is re-sent and
+/// the caller deletes the original; returns `false` for jobs without one,
+/// such as tasks adopted at startup, whose message must then be kept.
+pub async fn dead_letter(
+    sqs_client: &SqsClient,
+    dlq_url: &str,
+    job: &VideoMessage,
+    reason: &str,
+) -> Result<bool, PipelineError> {
+    let Some(body) = &job.raw_body else {
+        return Ok(false);
+    };
+    let reason_attribute = MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(reason)
+        .build()
+        .map_err(PipelineError::SqsMessage)?;
+    let mut send = sqs_client
+        .send_message()
+        .queue_url(dlq_url)
+        .message_body(body.as_ref())
+        .message_attributes("FailureReason", reason_attribute);
+    if dlq_url.ends_with(".fifo") {
+        let mut hasher = DefaultHasher::new();
+        (&job.key, job.attempts, body.as_ref()).hash(&mut hasher);
+        send = send
+            .message_group_id(
+                job.message_group_id
+                    .clone()
+                    .unwrap_or_else(|| "dead-letter".to_string()),
+            )
+            .message_deduplication_id(format!("{:016x}", hasher.finish()));
+    }
+    send.send()
+        .await
+        .map_err(|e| PipelineError::SqsDeadLetter(Box::new(e.into())))?;
+    Ok(true)
+}
@@ -0,0 +1,230 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::primitives::{DateTime, DateTimeFormat};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::app::CompletedJob;
+use crate::launch::OUTPUT_BUCKET;
+
+const HEADER: &str = "key,started_at,finished_at,duration,status,output_count,total_output_bytes";
+
+/// Every finished job is appended here, in the export directory, so reports
+/// cover more than the jobs the UI still lists and survive restarts.
+pub const HISTORY_FILE: &str = "job-history.csv";
+
+/// Serializes appends, so the header is written once and rows don't
+/// interleave.
+static HISTORY_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// How many objects a job left under its output prefix, and their total
+/// size in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputTotals {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// The prefix the transcoder writes the outputs of `key` under by default:
+/// `<stem>/` in [`OUTPUT_BUCKET`].
+fn output_prefix(key: &str) -> String {
+    let stem = Path::new(key)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(key);
+    format!("{}/", stem)
+}
+
+/// Lists the outputs of the job for `key`.
+pub async fn output_totals(
+    s3_client: &S3Client,
+    key: &str,
+) -> Result<OutputTotals, aws_sdk_s3::Error> {
+    let mut pages = s3_client
+        .list_objects_v2()
+        .bucket(OUTPUT_BUCKET)
+        .prefix(output_prefix(key))
+        .into_paginator()
+        .send();
+    let mut totals = OutputTotals::default();
+    while let Some(page) = pages.next().await {
+        for object in page?.contents() {
+            totals.count += 1;
+            totals.bytes += object.size().unwrap_or(0).max(0) as u64;
+        }
+    }
+    Ok(totals)
+}
+
+/// One CSV row. Times are RFC 3339 and the duration is in seconds; both are
+/// empty while a task is still running, as are the output totals.
+fn row(done: &CompletedJob, outputs: Option<OutputTotals>) -> String {
+    let duration = done
+        .finished_at
+        .and_then(|finished| finished.duration_since(done.launched_at).ok())
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_default();
+    [
+        field(&done.job.key),
+        timestamp(done.launched_at),
+        done.finished_at.map(timestamp).unwrap_or_default(),
+        duration,
+        done.status.label().to_string(),
+        outputs.map(|o| o.count.to_string()).unwrap_or_default(),
+        outputs.map(|o| o.bytes.to_string()).unwrap_or_default(),
+    ]
+    .join(",")
+}
+
+/// Appends a finished job to [`HISTORY_FILE`] in `dir`.
+pub async fn record(
+    done: &CompletedJob,
+    outputs: Option<OutputTotals>,
+    dir: &Path,
+) -> io::Result<()> {
+    let _guard = HISTORY_LOCK.lock().await;
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(HISTORY_FILE);
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    let mut lines = String::new();
+    if file.metadata().await?.len() == 0 {
+        lines.push_str(HEADER);
+        lines.push('\n');
+    }
+    lines.push_str(&row(done, outputs));
+    lines.push('\n');
+    file.write_all(lines.as_bytes()).await
+}
+
+/// Totals a finished job's outputs and appends it in the background,
+/// logging any failure. A job whose outputs can't be listed is recorded
+/// without totals.
+pub fn record_in_background(done: CompletedJob, s3_client: S3Client, dir: PathBuf) {
+    tokio::spawn(async move {
+        let outputs = match output_totals(&s3_client, &done.job.key).await {
+            Ok(outputs) => Some(outputs),
+            Err(e) => {
+                eprintln!("Failed to list the outputs of {}: {}", done.job.key, e);
+                None
+            }
+        };
+        if let Err(e) = record(&done, outputs, &dir).await {
+            eprintln!(
+                "Failed to record {} in the job history: {}",
+                done.job.key, e
+            );
+        }
+    });
+}
+
+/// Writes `report-<unix>.csv` in `dir`: every job in the history, in the
+/// order they finished, then the `running` ones oldest first. Returns the
+/// path and the number of rows.
+pub async fn write(running: &[CompletedJob], dir: &Path) -> io::Result<(PathBuf, usize)> {
+    let history = {
+        let _guard = HISTORY_LOCK.lock().await;
+        match tokio::fs::read_to_string(dir.join(HISTORY_FILE)).await {
+            Ok(history) => history,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        }
+    };
+    let mut out = String::from(HEADER);
+    out.push('\n');
+    let mut rows = 0;
+    for line in history.lines().skip(1).filter(|line| !line.is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+        rows += 1;
+    }
+    for done in running.iter().rev() {
+        out.push_str(&row(done, None));
+        out.push('\n');
+        rows += 1;
+    }
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = dir.join(format!("report-{}.csv", stamp));
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(&path, out).await?;
+    Ok((path, rows))
+}
+
+fn timestamp(time: SystemTime) -> String {
+    DateTime::from(time)
+        .fmt(DateTimeFormat::DateTime)
+        .unwrap_or_default()
+}
+
+/// Quotes a field that would otherwise break the row.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::app::{Priority, QueueSource, TaskStatus, VideoMessage};
+
+    fn finished(key: &str) -> CompletedJob {
+        let launched_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        CompletedJob {
+            job: VideoMessage {
+                bucket: "uploads".to_string(),
+                key: key.to_string(),
+                receipt_handle: String::new(),
+                source: Arc::new(QueueSource::new(
+                    "https://sqs.us-east-1.amazonaws.com/123456789012/uploads",
+                )),
+                priority: Priority::Normal,
+                message_group_id: None,
+                attempts: 0,
+                raw_body: None,
+                title: None,
+            },
+            task_arn: None,
+            log_url: None,
+            launched_at,
+            finished_at: Some(launched_at + Duration::from_secs(95)),
+            status: TaskStatus::Stopped { exit_code: Some(0) },
+            trigger: None,
+        }
+    }
+
+    #[test]
+    fn rows_carry_the_output_totals() {
+        let done = finished("incoming/my, clip.mp4");
+        let outputs = OutputTotals {
+            count: 4,
+            bytes: 123_456,
+        };
+        let row = row(&done, Some(outputs));
+        assert!(row.starts_with("\"incoming/my, clip.mp4\",2023-11-14T22:13:20Z,"));
+        assert!(row.ends_with(",95,done,4,123456"), "{}", row);
+        assert!(super::row(&done, None).ends_with(",95,done,,"));
+    }
+
+    #[test]
+    fn outputs_are_listed_under_the_source_stem() {
+        assert_eq!(output_prefix("clip.mp4"), "clip/");
+        assert_eq!(output_prefix("incoming/2024/clip.final.mp4"), "clip.final/");
+        assert_eq!(output_prefix("incoming/clip"), "clip/");
+    }
+}
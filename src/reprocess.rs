@@ -0,0 +1,98 @@
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::Arc;
+
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use tokio::process::Command;
+
+use crate::app::{LaunchTrigger, Priority, QueueSource, VideoMessage};
+use crate::error::PipelineError;
+use crate::launch::{self, Launcher, SOURCE_BUCKET};
+use crate::resources;
+
+/// Launches a task for one object straight away, without an SQS message,
+/// after checking the object exists. Returns the task ARN.
+pub async fn reprocess(
+    launcher: &Launcher,
+    source: Arc<QueueSource>,
+    bucket: &str,
+    key: &str,
+) -> Result<String, PipelineError> {
+    let head = check_source(launcher, bucket, key).await?;
+
+    // No receipt handle, so there is no message to delete after launch.
+    let job = VideoMessage {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        receipt_handle: String::new(),
+        source,
+        priority: Priority::from_key(key),
+        message_group_id: None,
+        attempts: 0,
+        raw_body: None,
+        title: head.metadata().and_then(|m| m.get("title")).cloned(),
+    };
+    let resources = head
+        .content_length()
+        .and_then(|len| u64::try_from(len).ok())
+        .and_then(|size| resources::tier_for(&launcher.resource_tiers, size));
+    launch::start_task(job, resources, &manual_env(launcher), launcher).await
+}
+
+/// Runs the transcoder binary on this machine instead of in a task, with
+/// the environment a task would get on top of this process's own, and
+/// waits for it to exit.
+pub async fn reprocess_local(
+    launcher: &Launcher,
+    transcoder: &Path,
+    bucket: &str,
+    key: &str,
+) -> Result<ExitStatus, PipelineError> {
+    check_source(launcher, bucket, key).await?;
+    Command::new(transcoder)
+        .env("SOURCE_KEY", key)
+        .env("AWS_REGION", launch::client_region(&launcher.ecs_client))
+        .envs(manual_env(launcher))
+        .status()
+        .await
+        .map_err(|e| PipelineError::LocalTranscoder(transcoder.display().to_string(), e))
+}
+
+/// Fails unless the object exists in the bucket the transcoder reads from.
+async fn check_source(
+    launcher: &Launcher,
+    bucket: &str,
+    key: &str,
+) -> Result<HeadObjectOutput, PipelineError> {
+    if bucket != SOURCE_BUCKET {
+        return Err(PipelineError::ForeignBucket(bucket.to_string()));
+    }
+    match launcher
+        .s3_client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+    {
+        Ok(head) => Ok(head),
+        Err(SdkError::ServiceError(e)) if e.err().is_not_found() => {
+            Err(PipelineError::SourceMissing {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+        }
+        Err(e) => Err(PipelineError::S3Head(Box::new(e.into()))),
+    }
+}
+
+/// `--env` plus the manual trigger the container echoes in its notice.
+fn manual_env(launcher: &Launcher) -> Vec<(String, String)> {
+    let mut env = launcher.extra_env.to_vec();
+    env.push((
+        "LAUNCH_TRIGGER".to_string(),
+        LaunchTrigger::Manual.label().to_string(),
+    ));
+    env
+}
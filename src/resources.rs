@@ -0,0 +1,112 @@
+use aws_sdk_s3::Client as S3Client;
+
+/// CPU units and memory (MiB) for one Fargate task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskResources {
+    pub cpu: u32,
+    pub memory: u32,
+}
+
+impl TaskResources {
+    /// Checks the pair against Fargate's supported CPU/memory combinations.
+    pub fn validate(self) -> Result<Self, String> {
+        if self.cpu == 256 {
+            return match self.memory {
+                512 | 1024 | 2048 => Ok(self),
+                memory => Err(format!(
+                    "{} MiB is not valid with 256 CPU units (Fargate allows 512, 1024 or 2048)",
+                    memory
+                )),
+            };
+        }
+        let (min, max, step) = match self.cpu {
+            512 => (1024, 4096, 1024),
+            1024 => (2048, 8192, 1024),
+            2048 => (4096, 16384, 1024),
+            4096 => (8192, 30720, 1024),
+            8192 => (16384, 61440, 4096),
+            16384 => (32768, 122880, 8192),
+            cpu => return Err(format!("{} is not a Fargate CPU size", cpu)),
+        };
+        if !(min..=max).contains(&self.memory) || !self.memory.is_multiple_of(step) {
+            return Err(format!(
+                "{} MiB is not valid with {} CPU units (Fargate allows {}-{} MiB in {} MiB steps)",
+                self.memory, self.cpu, min, max, step
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Sources at least `min_source_mib` large get `resources`.
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceTier {
+    pub min_source_mib: u64,
+    pub resources: TaskResources,
+}
+
+/// Small clips fit the task definition's defaults; big sources get enough
+/// memory to avoid OOM kills on 4K decodes.
+pub fn default_tiers() -> Vec<ResourceTier> {
+    parse_tiers("0:1024:2048,1024:2048:4096,4096:4096:8192").expect("default tiers are valid")
+}
+
+/// Parses `min_mib:cpu:memory` entries separated by commas, e.g.
+/// `0:1024:2048,4096:4096:8192`. Tiers are returned smallest first.
+pub fn parse_tiers(value: &str) -> Result<Vec<ResourceTier>, String> {
+    let mut tiers = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+        let [min, cpu, memory] = parts[..] else {
+            return Err(format!(
+                "Invalid resource tier (expected min_mib:cpu:memory): {}",
+                entry
+            ));
+        };
+        let number = |s: &str| {
+            s.parse::<u64>()
+                .map_err(|_| format!("Invalid number {:?} in resource tier: {}", s, entry))
+        };
+        let resources = TaskResources {
+            cpu: number(cpu)? as u32,
+            memory: number(memory)? as u32,
+        }
+        .validate()?;
+        tiers.push(ResourceTier {
+            min_source_mib: number(min)?,
+            resources,
+        });
+    }
+    tiers.sort_by_key(|t| t.min_source_mib);
+    if tiers
+        .windows(2)
+        .any(|w| w[0].min_source_mib == w[1].min_source_mib)
+    {
+        return Err("Resource tiers must have distinct sizes".to_string());
+    }
+    Ok(tiers)
+}
+
+/// Picks the largest tier the source qualifies for, or `None` to keep the
+/// task definition's defaults.
+pub fn tier_for(tiers: &[ResourceTier], source_bytes: u64) -> Option<TaskResources> {
+    let source_mib = source_bytes / (1024 * 1024);
+    tiers
+        .iter()
+        .rev()
+        .find(|t| source_mib >= t.min_source_mib)
+        .map(|t| t.resources)
+}
+
+/// Size of the uploaded source, used to choose a tier.
+pub async fn source_size(s3_client: &S3Client, bucket: &str, key: &str) -> Option<u64> {
+    match s3_client.head_object().bucket(bucket).key(key).send().await {
+        Ok(head) => head
+            .content_length()
+            .and_then(|len| u64::try_from(len).ok()),
+        Err(e) => {
+            eprintln!("Could not size s3://{}/{}: {:?}", bucket, key, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many minutes of history the throughput graph covers.
+pub const THROUGHPUT_MINUTES: usize = 30;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Launches and successful completions per minute, oldest first. Only the
+/// last [`THROUGHPUT_MINUTES`] minutes are kept.
+#[derive(Debug)]
+pub struct Throughput {
+    origin: Instant,
+    /// Minute (since `origin`) of the last bucket in each series.
+    latest: u64,
+    launched: VecDeque<u64>,
+    completed: VecDeque<u64>,
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            latest: 0,
+            launched: VecDeque::from([0]),
+            completed: VecDeque::from([0]),
+        }
+    }
+
+    pub fn record_launch(&mut self) {
+        self.advance();
+        *self.launched.back_mut().unwrap() += 1;
+    }
+
+    pub fn record_completion(&mut self) {
+        self.advance();
+        *self.completed.back_mut().unwrap() += 1;
+    }
+
+    /// Per-minute launches and completions up to now, padded with empty
+    /// minutes for any quiet time since the last event.
+    pub fn series(&self) -> (Vec<u64>, Vec<u64>) {
+        let idle = (self.current_minute() - self.latest) as usize;
+        let pad = |buckets: &VecDeque<u64>| {
+            let mut series: Vec<u64> = buckets.iter().copied().collect();
+            series.extend(std::iter::repeat_n(0, idle.min(THROUGHPUT_MINUTES)));
+            let excess = series.len().saturating_sub(THROUGHPUT_MINUTES);
+            series.split_off(excess)
+        };
+        (pad(&self.launched), pad(&self.completed))
+    }
+
+    fn current_minute(&self) -> u64 {
+        self.origin.elapsed().as_secs() / MINUTE.as_secs()
+    }
+
+    /// Rolls both series forward to the current minute.
+    fn advance(&mut self) {
+        let now = self.current_minute();
+        let steps = ((now - self.latest) as usize).min(THROUGHPUT_MINUTES);
+        for series in [&mut self.launched, &mut self.completed] {
+            series.extend(std::iter::repeat_n(0, steps));
+            while series.len() > THROUGHPUT_MINUTES {
+                series.pop_front();
+            }
+        }
+        self.latest = now;
+    }
+}
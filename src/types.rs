@@ -1,4 +1,7 @@
 use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::PipelineError;
 
 #[derive(Debug, Deserialize)]
 pub struct S3Event {
@@ -26,3 +29,201 @@ pub struct S3Bucket {
 pub struct S3Object {
     pub key: String,
 }
+
+/// An S3 event routed through EventBridge. Only the fields the pipeline
+/// needs are modelled.
+#[derive(Debug, Deserialize)]
+pub struct EventBridgeS3Event {
+    #[serde(rename = "detail-type")]
+    pub detail_type: String,
+    pub detail: EventBridgeS3Detail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventBridgeS3Detail {
+    pub bucket: S3Bucket,
+    pub object: S3Object,
+}
+
+impl From<EventBridgeS3Event> for S3Event {
+    fn from(event: EventBridgeS3Event) -> Self {
+        S3Event {
+            records: vec![S3EventRecord {
+                s3: S3Entity {
+                    bucket: event.detail.bucket,
+                    object: event.detail.object,
+                },
+            }],
+        }
+    }
+}
+
+/// Parses a queue message body in any of the shapes S3 notifications
+/// arrive in: a native S3 event, an SNS envelope around one, or an
+/// EventBridge event.
+pub fn parse_event(body: &str) -> Result<S3Event, PipelineError> {
+    let value: Value = serde_json::from_str(body)?;
+    if value.get("Type").and_then(Value::as_str) == Some("Notification")
+        && let Some(message) = value.get("Message").and_then(Value::as_str)
+    {
+        return parse_event(message);
+    }
+    if value.get("source").and_then(Value::as_str) == Some("aws.s3") {
+        let event: EventBridgeS3Event = serde_json::from_value(value)?;
+        if event.detail_type != "Object Created" {
+            return Err(PipelineError::UnsupportedEvent(event.detail_type));
+        }
+        return Ok(event.into());
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// S3 event notifications form-encode object keys: spaces arrive as `+` and
+/// everything else outside the unreserved set as `%XX`. Returns the real key.
+pub fn decode_s3_key(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    (Some(hi), Some(lo)) => {
+                        out.push((hi << 4) | lo);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a key the way S3 does in event notifications.
+    fn encode_like_s3(key: &str) -> String {
+        key.bytes()
+            .map(|b| match b {
+                b' ' => "+".to_string(),
+                b if b.is_ascii_alphanumeric() || b"-_.~/".contains(&b) => (b as char).to_string(),
+                b => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decoded_keys_round_trip() {
+        for key in [
+            "plain.mp4",
+            "my video.mp4",
+            "a+b.mp4",
+            "100% done.mp4",
+            "caf\u{e9}/\u{65e5}\u{672c}\u{8a9e} clip.mov",
+            "dir/sub dir/x%2B.mp4",
+        ] {
+            assert_eq!(decode_s3_key(&encode_like_s3(key)), key);
+        }
+        assert_eq!(encode_like_s3("my video.mp4"), "my+video.mp4");
+    }
+
+    #[test]
+    fn stray_percent_signs_are_kept() {
+        assert_eq!(decode_s3_key("100%"), "100%");
+        assert_eq!(decode_s3_key("50%zz.mp4"), "50%zz.mp4");
+        assert_eq!(decode_s3_key("%4"), "%4");
+    }
+
+    /// An "Object Created" event as EventBridge delivers it to SQS.
+    const EVENTBRIDGE_SAMPLE: &str = r#"{
+        "version": "0",
+        "id": "17793124-05d4-b198-2fde-7ededc63b103",
+        "detail-type": "Object Created",
+        "source": "aws.s3",
+        "account": "123456789012",
+        "time": "2021-11-12T00:00:00Z",
+        "region": "ca-central-1",
+        "resources": ["arn:aws:s3:::example-bucket"],
+        "detail": {
+            "version": "0",
+            "bucket": {"name": "example-bucket"},
+            "object": {
+                "key": "uploads/my+video.mp4",
+                "size": 5,
+                "etag": "b1946ac92492d2347c6235b4d2611184",
+                "version-id": "IYV3p45BT0ac8hjHg1houSdS1a.Mro8e",
+                "sequencer": "00617F08299329D189"
+            },
+            "request-id": "N4N7GDK58NMKJ12R",
+            "requester": "123456789012",
+            "source-ip-address": "1.2.3.4",
+            "reason": "PutObject"
+        }
+    }"#;
+
+    #[test]
+    fn eventbridge_events_parse_to_one_record() {
+        let event = parse_event(EVENTBRIDGE_SAMPLE).unwrap();
+        assert_eq!(event.records.len(), 1);
+        assert_eq!(event.records[0].s3.bucket.name, "example-bucket");
+        assert_eq!(event.records[0].s3.object.key, "uploads/my+video.mp4");
+    }
+
+    #[test]
+    fn other_eventbridge_detail_types_are_unsupported() {
+        let deleted = EVENTBRIDGE_SAMPLE.replace("Object Created", "Object Deleted");
+        assert!(matches!(
+            parse_event(&deleted),
+            Err(PipelineError::UnsupportedEvent(detail_type)) if detail_type == "Object Deleted"
+        ));
+    }
+
+    #[test]
+    fn native_and_sns_wrapped_events_still_parse() {
+        let native = r#"{"Records":[{"s3":{"bucket":{"name":"b"},"object":{"key":"k.mp4"}}}]}"#;
+        assert_eq!(
+            parse_event(native).unwrap().records[0].s3.object.key,
+            "k.mp4"
+        );
+        let wrapped = serde_json::json!({"Type": "Notification", "Message": native}).to_string();
+        assert_eq!(
+            parse_event(&wrapped).unwrap().records[0].s3.bucket.name,
+            "b"
+        );
+    }
+
+    #[test]
+    fn events_without_records_parse_but_are_empty() {
+        let empty = parse_event(r#"{"Records":[]}"#).unwrap();
+        assert!(empty.records.is_empty());
+        let wrapped = serde_json::json!({
+            "Type": "Notification",
+            "Message": r#"{"Records":[]}"#,
+        });
+        assert!(
+            parse_event(&wrapped.to_string())
+                .unwrap()
+                .records
+                .is_empty()
+        );
+
+        // Unlike a body that isn't an S3 event at all.
+        assert!(parse_event("{}").is_err());
+        assert!(parse_event("not json").is_err());
+    }
+}
@@ -0,0 +1,703 @@
+use std::time::SystemTime;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
+};
+
+use crate::actions::{Action, Category, palette_matches};
+use crate::app::{
+    AppState, Focus, LaunchTrigger, Palette, Priority, TaskStatus, ToastLevel, VideoMessage,
+};
+use crate::debug;
+use crate::throughput::THROUGHPUT_MINUTES;
+
+/// Per-frame values computed by the event loop rather than stored in state.
+pub struct ViewContext {
+    pub idle_countdown: Option<u64>,
+}
+
+/// Smallest terminal the layout fits without overlapping panels.
+const MIN_WIDTH: u16 = 40;
+
+/// Height of the detail pane under a focused Recent Jobs list.
+const COMPLETED_DETAIL_HEIGHT: u16 = 7;
+const MIN_HEIGHT: u16 = 15;
+const MIN_HEIGHT_COMPACT: u16 = 8;
+
+/// Draws the whole UI and returns how many rows the focused list has room
+/// for, which sets the PageUp/PageDown step.
+pub fn draw(f: &mut Frame, st: &AppState, ctx: &ViewContext) -> usize {
+    let size = f.area();
+    let min_height = if st.compact {
+        MIN_HEIGHT_COMPACT
+    } else {
+        MIN_HEIGHT
+    };
+    if size.width < MIN_WIDTH || size.height < min_height {
+        let message = Paragraph::new(format!(
+            "Terminal too small ({}x{}); need at least {}x{}",
+            size.width, size.height, MIN_WIDTH, min_height
+        ))
+        .wrap(Wrap { trim: true });
+        f.render_widget(message, size);
+        return st.page_size;
+    }
+    // Compact mode drops the borders and tagline around the header and help
+    // so the job lists get nearly the whole terminal.
+    let (margin, header_height, help_height) = if st.compact { (0, 1, 1) } else { (1, 4, 3) };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(margin)
+        .constraints(
+            [
+                Constraint::Length(header_height),
+                Constraint::Min(6),
+                Constraint::Length(help_height),
+            ]
+            .as_ref(),
+        )
+        .split(size);
+
+    let mut title = vec![Span::styled(
+        "Transcode no Jutsu",
+        Style::default()
+            .fg(Color::LightCyan)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if st.is_paused() {
+        title.push(Span::styled(
+            "  PAUSED — not pulling from SQS (p to resume)",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if st.stalled {
+        let minutes = st
+            .unanswered_since
+            .map_or(0, |since| since.elapsed().as_secs() / 60);
+        title.push(Span::styled(
+            format!("  NO SUCCESSFUL JOBS IN {}m", minutes),
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if st.is_holding() {
+        title.push(Span::styled(
+            "  HOLDING — cluster at capacity",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    title.push(Span::styled(
+        format!("  ↓ {}/min", st.intake_per_minute()),
+        Style::default().fg(Color::DarkGray),
+    ));
+    let header = if st.compact {
+        Paragraph::new(Line::from(title))
+    } else {
+        Paragraph::new(vec![
+            Line::from(title),
+            Line::from(Span::raw(
+                "A highly efficient video transcoding library built in Rust using FFmpeg",
+            )),
+        ])
+        .block(Block::default().borders(Borders::ALL).title("About"))
+    };
+    if st.compact {
+        f.render_widget(header, chunks[0]);
+    } else {
+        let header_parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(20),
+                Constraint::Length(THROUGHPUT_MINUTES as u16 + 12),
+            ])
+            .split(chunks[0]);
+        f.render_widget(header, header_parts[0]);
+        draw_throughput(f, st, header_parts[1]);
+    }
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(chunks[1]);
+    draw_pending(f, st, body[0]);
+    draw_completed(f, st, body[1]);
+    // Less the borders, and the detail pane under a focused Recent Jobs.
+    let list_height = match st.focus {
+        Focus::Pending => body[0].height.saturating_sub(2),
+        Focus::Completed => body[1].height.saturating_sub(2 + COMPLETED_DETAIL_HEIGHT),
+    };
+
+    let controls = Action::STATUS_LINE
+        .iter()
+        .map(|a| format!("{} {}", a.key_label(), a.description().to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" • ");
+    let mut help_line = vec![Span::raw(format!("Controls: {}", controls))];
+    if let Some(secs) = ctx.idle_countdown {
+        help_line.push(Span::styled(
+            format!(" • Idle, exiting in {}s", secs),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let help = Paragraph::new(Line::from(help_line));
+    let help = if st.compact {
+        help
+    } else {
+        help.block(Block::default().borders(Borders::ALL).title("Help"))
+    };
+    f.render_widget(help, chunks[2]);
+
+    draw_toasts(f, st, size);
+
+    if st.show_debug {
+        draw_debug(f, st, size);
+    }
+    if st.show_help {
+        draw_help(f, size);
+    }
+    if let Some(palette) = &st.palette {
+        draw_palette(f, palette, size);
+    }
+    if let Some(action) = &st.confirm {
+        let area = centered_rect(60, 20, size);
+        let modal = Paragraph::new(vec![
+            Line::from(Span::raw(action.prompt())),
+            Line::from(""),
+            Line::from(Span::styled(
+                "y to confirm • n/Esc to cancel",
+                Style::default().fg(Color::Yellow),
+            )),
+        ])
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Confirm"));
+        f.render_widget(Clear, area);
+        f.render_widget(modal, area);
+    }
+    (list_height as usize).max(1)
+}
+
+/// Launches (top) and successful completions (bottom) per minute, one
+/// column per minute, newest on the right.
+fn draw_throughput(f: &mut Frame, st: &AppState, area: Rect) {
+    let (launched, completed) = st.throughput.series();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Jobs/min ({}m)", THROUGHPUT_MINUTES));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Length(1)])
+        .split(inner);
+    for (row, label, series, color) in [
+        (rows[0], "run ", &launched, Color::Green),
+        (rows[1], "done", &completed, Color::Cyan),
+    ] {
+        let parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(5), Constraint::Min(1)])
+            .split(row);
+        f.render_widget(
+            Paragraph::new(label).style(Style::default().fg(Color::DarkGray)),
+            parts[0],
+        );
+        f.render_widget(
+            Sparkline::default()
+                .data(series.iter())
+                .style(Style::default().fg(color)),
+            parts[1],
+        );
+    }
+}
+
+/// Stacks toasts in the bottom-right corner, newest at the bottom, just
+/// above the help bar.
+fn draw_toasts(f: &mut Frame, st: &AppState, size: Rect) {
+    let bottom = size.height.saturating_sub(if st.compact { 1 } else { 4 });
+    let max_width = (size.width / 2).max(20).min(size.width);
+    for (i, toast) in st.toasts.iter().rev().enumerate() {
+        let Some(y) = bottom.checked_sub(i as u16 + 1) else {
+            break;
+        };
+        let text = truncate_end(&toast.message, max_width.saturating_sub(2) as usize);
+        let width = (text.chars().count() as u16 + 2).min(max_width);
+        let area = Rect::new(size.width.saturating_sub(width + 1), y, width, 1);
+        let color = match toast.level {
+            ToastLevel::Success => Color::Green,
+            ToastLevel::Info => Color::Cyan,
+            ToastLevel::Error => Color::Red,
+        };
+        let mut style = Style::default().fg(Color::Black).bg(color);
+        if toast.is_fading() {
+            style = Style::default().fg(color).add_modifier(Modifier::DIM);
+        }
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(format!(" {} ", text)).style(style), area);
+    }
+}
+
+fn panel_block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if focused {
+        block.border_style(Style::default().fg(Color::LightCyan))
+    } else {
+        block
+    }
+}
+
+fn highlight_style() -> Style {
+    Style::default()
+        .bg(Color::Blue)
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD)
+}
+
+fn draw_pending(f: &mut Frame, st: &AppState, area: Rect) {
+    let mut title = "Pending Uploads".to_string();
+    if let Some(bucket) = &st.bucket_filter {
+        title.push_str(&format!(" [bucket: {}]", bucket));
+    }
+    if !st.checked.is_empty() {
+        title.push_str(&format!(" ({} checked)", st.checked.len()));
+    }
+    let block = panel_block(&title, st.focus == Focus::Pending);
+    let visible = st.visible_indices();
+    if visible.is_empty() {
+        let text = match &st.bucket_filter {
+            Some(bucket) if !st.messages.is_empty() => {
+                format!("No pending jobs from {} (b to change)", bucket)
+            }
+            _ => "No video upload events yet...".to_string(),
+        };
+        f.render_widget(Paragraph::new(text).block(block), area);
+        return;
+    }
+    let selected_row = visible.iter().position(|&i| i == st.selected);
+
+    if st.compact {
+        draw_pending_compact(f, st, area, block, &visible, selected_row);
+        return;
+    }
+
+    // Borders plus the highlight symbol.
+    let inner_width = area.width.saturating_sub(5) as usize;
+    let mut selected_truncated = false;
+    let list_items: Vec<ListItem> = visible
+        .iter()
+        .map(|&i| {
+            let m = &st.messages[i];
+            let check = checkbox(st, m);
+            let group = m
+                .message_group_id
+                .as_ref()
+                .map(|g| format!("  [group {}]", g))
+                .unwrap_or_default();
+            let retry = if m.attempts > 0 {
+                format!("  [attempt {}]", m.attempts + 1)
+            } else {
+                String::new()
+            };
+            // The bucket keeps up to a third of the row; the key gets the rest.
+            let bucket = truncate_end(&m.bucket, (inner_width / 3).max(8));
+            let used = check.chars().count()
+                + 7
+                + m.source.label.chars().count()
+                + 1
+                + bucket.chars().count()
+                + 3
+                + group.chars().count()
+                + retry.chars().count();
+            let key = truncate_middle(m.display_name(), inner_width.saturating_sub(used));
+            if i == st.selected {
+                selected_truncated = key != m.display_name() || bucket != m.bucket;
+            }
+            ListItem::new(Line::from(vec![
+                Span::raw(check),
+                Span::styled(
+                    format!("[{:<4}] ", m.priority.label()),
+                    priority_style(m.priority),
+                ),
+                Span::styled(
+                    format!("{} ", m.source.label),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(format!("{} / {}", bucket, key)),
+                Span::styled(group, Style::default().fg(Color::DarkGray)),
+                Span::styled(retry, Style::default().fg(Color::LightRed)),
+            ]))
+        })
+        .collect();
+
+    // A shortened selection is spelled out in full beneath the list.
+    let area = match st.selected_message() {
+        Some(m) if selected_truncated => {
+            let full = format!("{} / {}", m.bucket, m.key);
+            let width = area.width.saturating_sub(2).max(1) as usize;
+            let lines = full.chars().count().div_ceil(width).clamp(1, 3) as u16;
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(lines + 1)])
+                .split(area);
+            let detail = Paragraph::new(full)
+                .wrap(Wrap { trim: false })
+                .style(Style::default().fg(Color::DarkGray))
+                .block(Block::default().borders(Borders::TOP).title("Selected"));
+            f.render_widget(detail, parts[1]);
+            parts[0]
+        }
+        _ => area,
+    };
+
+    let mut list_state = ListState::default();
+    list_state.select(selected_row);
+
+    let list = List::new(list_items)
+        .block(block)
+        .highlight_style(highlight_style())
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// One dense line per job: a one-letter priority, a shortened bucket and
+/// the key cut in the middle so the filename stays visible.
+fn draw_pending_compact(
+    f: &mut Frame,
+    st: &AppState,
+    area: Rect,
+    block: Block,
+    visible: &[usize],
+    selected_row: Option<usize>,
+) {
+    let block = block.borders(Borders::TOP);
+    let inner_width = area.width.saturating_sub(1) as usize;
+    let list_items: Vec<ListItem> = visible
+        .iter()
+        .map(|&i| {
+            let m = &st.messages[i];
+            let bucket = truncate_end(&m.bucket, 12);
+            let check = checkbox(st, m);
+            let used = check.chars().count() + 2 + bucket.chars().count() + 1;
+            ListItem::new(Line::from(vec![
+                Span::raw(check),
+                Span::styled(
+                    format!("{} ", &m.priority.label()[..1]),
+                    priority_style(m.priority),
+                ),
+                Span::styled(format!("{} ", bucket), Style::default().fg(Color::DarkGray)),
+                Span::raw(truncate_middle(
+                    m.display_name(),
+                    inner_width.saturating_sub(used),
+                )),
+            ]))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select(selected_row);
+
+    let list = List::new(list_items)
+        .block(block)
+        .highlight_style(highlight_style())
+        .highlight_symbol(">");
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// A checkbox column, shown only while some job is checked.
+fn checkbox(st: &AppState, job: &VideoMessage) -> String {
+    match (st.checked.is_empty(), st.checked.contains(&job.object_id())) {
+        (true, _) => String::new(),
+        (false, true) => "[x] ".to_string(),
+        (false, false) => "[ ] ".to_string(),
+    }
+}
+
+fn priority_style(priority: Priority) -> Style {
+    match priority {
+        Priority::High => Style::default().fg(Color::LightRed),
+        Priority::Normal => Style::default(),
+        Priority::Low => Style::default().fg(Color::DarkGray),
+    }
+}
+
+fn truncate_end(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(1)).collect();
+    format!("{}…", kept)
+}
+
+/// Shortens an S3 key to `max` characters by cutting out the middle of the
+/// path, so `uploads/2024/06/team-a/interview.mp4` at 22 characters becomes
+/// `uploads…/interview.mp4`. The filename is only cut when it alone does
+/// not fit, and then its end (with the extension) is kept.
+fn truncate_middle(key: &str, max: usize) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= max {
+        return key.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let name_start = key
+        .rfind('/')
+        .map(|i| key[..i].chars().count())
+        .unwrap_or(0);
+    let name = &chars[name_start..];
+    if name.len() + 1 >= max {
+        let tail: String = chars[chars.len() - (max - 1)..].iter().collect();
+        return format!("…{}", tail);
+    }
+    let head: String = chars[..max - 1 - name.len()].iter().collect();
+    let name: String = name.iter().collect();
+    format!("{}…{}", head, name)
+}
+
+fn draw_completed(f: &mut Frame, st: &AppState, area: Rect) {
+    let area = if st.focus == Focus::Completed {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(COMPLETED_DETAIL_HEIGHT),
+            ])
+            .split(area);
+        draw_completed_detail(f, st, parts[1]);
+        parts[0]
+    } else {
+        area
+    };
+
+    let block = panel_block("Recent Jobs", st.focus == Focus::Completed);
+    if st.completed.is_empty() {
+        let empty = Paragraph::new("No jobs launched yet...").block(block);
+        f.render_widget(empty, area);
+        return;
+    }
+
+    // Full keys are in the detail pane.
+    let inner_width = area.width.saturating_sub(5) as usize;
+    let list_items: Vec<ListItem> = st
+        .completed
+        .iter()
+        .map(|c| {
+            let age = format!("{} ", format_age(c.launched_at));
+            let trigger = format!("{} ", c.trigger.map_or("?", LaunchTrigger::glyph));
+            let used = age.chars().count() + 12;
+            ListItem::new(Line::from(vec![
+                Span::styled(trigger, Style::default().fg(Color::DarkGray)),
+                Span::raw(age),
+                Span::styled(
+                    format!("{:<9} ", c.status.label()),
+                    task_status_style(c.status),
+                ),
+                Span::raw(truncate_middle(
+                    c.job.display_name(),
+                    inner_width.saturating_sub(used),
+                )),
+            ]))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    if st.focus == Focus::Completed {
+        list_state.select(Some(st.completed_selected));
+    }
+
+    let list = List::new(list_items)
+        .block(block)
+        .highlight_style(highlight_style())
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+fn task_status_style(status: TaskStatus) -> Style {
+    match status {
+        TaskStatus::Starting | TaskStatus::Running => Style::default().fg(Color::Yellow),
+        TaskStatus::Stopped { exit_code: Some(0) } => Style::default().fg(Color::Green),
+        TaskStatus::Stopped { .. } | TaskStatus::TimedOut => Style::default().fg(Color::LightRed),
+    }
+}
+
+fn draw_completed_detail(f: &mut Frame, st: &AppState, area: Rect) {
+    let lines = match st.selected_completed() {
+        Some(done) => vec![
+            Line::from(format!("Key:  {} / {}", done.job.bucket, done.job.key)),
+            Line::from(format!(
+                "Title: {}",
+                done.job.title.as_deref().unwrap_or("(none)")
+            )),
+            Line::from(format!("Queue: {}", done.job.source.label)),
+            Line::from(format!(
+                "Status: {} (attempt {}, {} launch)",
+                done.status.label(),
+                done.job.attempts + 1,
+                done.trigger.map_or("unknown", LaunchTrigger::label)
+            )),
+            Line::from(format!(
+                "Task: {}",
+                done.task_arn.as_deref().unwrap_or("(not started)")
+            )),
+            Line::from(format!(
+                "Logs: {}",
+                done.log_url
+                    .as_deref()
+                    .unwrap_or("(no awslogs configuration)")
+            )),
+        ],
+        None => vec![Line::from("No job selected")],
+    };
+    let detail = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Details"));
+    f.render_widget(detail, area);
+}
+
+fn draw_palette(f: &mut Frame, palette: &Palette, size: Rect) {
+    let area = centered_rect(60, 50, size);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+
+    let input = Paragraph::new(format!(":{}", palette.query)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Command palette • Enter to run • Esc to close"),
+    );
+
+    let matches = palette_matches(&palette.query);
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|a| {
+            ListItem::new(Line::from(vec![
+                Span::raw(a.description()),
+                Span::styled(
+                    format!("  ({})", a.key_label()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let mut list_state = ListState::default();
+    if !matches.is_empty() {
+        list_state.select(Some(palette.selected.min(matches.len() - 1)));
+    }
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(highlight_style())
+        .highlight_symbol(">> ");
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, parts[0]);
+    f.render_stateful_widget(list, parts[1], &mut list_state);
+}
+
+/// Full keybinding reference, generated from [`Action::ALL`] so it always
+/// matches what the keys actually do.
+fn draw_help(f: &mut Frame, size: Rect) {
+    let area = centered_rect(90, 90, size);
+    let mut lines = Vec::new();
+    for category in Category::ALL {
+        let actions: Vec<Action> = Action::ALL
+            .iter()
+            .copied()
+            .filter(|a| a.category() == *category)
+            .collect();
+        if actions.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            category.label(),
+            Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for action in actions {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<8}", action.key_label()),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(action.description()),
+            ]));
+        }
+    }
+    let overlay = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Keybindings • ?/Esc to close"),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(overlay, area);
+}
+
+fn draw_debug(f: &mut Frame, st: &AppState, size: Rect) {
+    let area = centered_rect(80, 80, size);
+    let (title, text) = match st.raw_bodies.get(st.debug_selected) {
+        Some(raw) => (
+            format!(
+                "Raw message {}/{} ({}) • ↑/↓ to browse • d/Esc to close",
+                st.debug_selected + 1,
+                st.raw_bodies.len(),
+                if raw.parsed { "parsed" } else { "parse failed" }
+            ),
+            debug::pretty_redacted(&raw.body),
+        ),
+        None => (
+            "Raw messages • d/Esc to close".to_string(),
+            "No messages received yet...".to_string(),
+        ),
+    };
+    let overlay = Paragraph::new(text)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, area);
+    f.render_widget(overlay, area);
+}
+
+/// Short "3m ago" style age for list rows.
+fn format_age(at: SystemTime) -> String {
+    let secs = at.elapsed().map(|d| d.as_secs()).unwrap_or(0);
+    if secs < 60 {
+        format!("{:>3}s", secs)
+    } else if secs < 3600 {
+        format!("{:>3}m", secs / 60)
+    } else {
+        format!("{:>3}h", secs / 3600)
+    }
+}
+
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
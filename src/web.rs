@@ -0,0 +1,206 @@
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::app::AppState;
+
+/// The page served at `/`. It polls `/api/jobs` and renders the result.
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// Requests are a single GET line plus headers; anything longer is cut off.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Serves the read-only dashboard on `listener` until the process exits.
+/// Each request takes the state lock just long enough to copy out a snapshot.
+pub async fn serve(listener: TcpListener, state: Arc<Mutex<AppState>>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("Dashboard accept error: {:?}", e);
+                continue;
+            }
+        };
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &state).await {
+                eprintln!("Dashboard connection error: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: &Mutex<AppState>) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    // Ignore any query string, e.g. a cache buster.
+    let path = parts
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/") => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            DASHBOARD_HTML.to_string(),
+        ),
+        ("GET", "/api/jobs") => {
+            let snapshot = match state.lock() {
+                Ok(st) => jobs_json(&st),
+                Err(_) => json!({ "error": "state unavailable" }),
+            };
+            ("200 OK", "application/json", snapshot.to_string())
+        }
+        ("GET", "/metrics") => match state.lock() {
+            Ok(st) => ("200 OK", "text/plain; version=0.0.4", metrics_text(&st)),
+            Err(_) => (
+                "503 Service Unavailable",
+                "text/plain",
+                "state unavailable".to_string(),
+            ),
+        },
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Read-only".to_string(),
+        ),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Counts and gauges in the Prometheus text format, for scraping a
+/// `--daemon` process.
+fn metrics_text(st: &AppState) -> String {
+    let metrics = [
+        (
+            "transcode_jobs_pending",
+            "gauge",
+            "Jobs received and not yet launched.",
+            st.messages.len() as u64,
+        ),
+        (
+            "transcode_tasks_active",
+            "gauge",
+            "Launched tasks that have not stopped.",
+            st.active_task_count() as u64,
+        ),
+        (
+            "transcode_jobs_launched_total",
+            "counter",
+            "Tasks launched since startup.",
+            st.totals.launched,
+        ),
+        (
+            "transcode_jobs_succeeded_total",
+            "counter",
+            "Tasks that exited 0.",
+            st.totals.succeeded,
+        ),
+        (
+            "transcode_jobs_failed_total",
+            "counter",
+            "Tasks that failed, timed out or stopped without an exit code.",
+            st.totals.failed,
+        ),
+        (
+            "transcode_intake_paused",
+            "gauge",
+            "1 while intake is paused or draining.",
+            st.is_paused() as u64,
+        ),
+        (
+            "transcode_launches_held",
+            "gauge",
+            "1 while launches are held for cluster capacity.",
+            st.is_holding() as u64,
+        ),
+        (
+            "transcode_pipeline_stalled",
+            "gauge",
+            "1 once the completion watchdog has alerted.",
+            st.stalled as u64,
+        ),
+    ];
+    metrics
+        .iter()
+        .map(|(name, kind, help, value)| {
+            format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n")
+        })
+        .collect()
+}
+
+/// The `/api/jobs` payload: pending jobs in list order and launched jobs
+/// newest first, as the TUI shows them.
+fn jobs_json(st: &AppState) -> Value {
+    let pending: Vec<Value> = st
+        .messages
+        .iter()
+        .map(|job| {
+            json!({
+                "bucket": job.bucket,
+                "key": job.key,
+                "title": job.display_name(),
+                "queue": job.source.label,
+                "priority": job.priority.label(),
+                "attempts": job.attempts,
+            })
+        })
+        .collect();
+    let launched: Vec<Value> = st
+        .completed
+        .iter()
+        .map(|done| {
+            json!({
+                "bucket": done.job.bucket,
+                "key": done.job.key,
+                "title": done.job.display_name(),
+                "queue": done.job.source.label,
+                "status": done.status.label(),
+                "active": done.status.is_active(),
+                "task_arn": done.task_arn,
+                "log_url": done.log_url,
+                "launched_at": done
+                    .launched_at
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()),
+                "trigger": done.trigger.map(|t| t.label()),
+            })
+        })
+        .collect();
+    json!({
+        "paused": st.is_paused(),
+        "stalled": st.stalled,
+        "holding": st.is_holding(),
+        "pending": pending,
+        "launched": launched,
+    })
+}